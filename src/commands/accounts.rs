@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::{token, utils};
+
+#[derive(serde::Serialize)]
+struct AccountRow {
+    address: String,
+    mint: String,
+    program: &'static str,
+    amount: u64,
+    decimals: Option<u8>,
+    balance: Option<f64>,
+    frozen: bool,
+    delegated: bool,
+    rent_lamports: u64,
+    #[serde(skip)]
+    mint_key: Pubkey,
+}
+
+/// Row filters, all given ones must match
+#[derive(Default)]
+pub struct AccountFilter {
+    pub empty: bool,
+    pub frozen: bool,
+    pub delegated: bool,
+    pub mint: Option<Pubkey>,
+}
+
+impl AccountFilter {
+    fn matches(&self, acc: &token::ParsedTokenAccount) -> bool {
+        (!self.empty || acc.amount == 0)
+            && (!self.frozen || acc.frozen)
+            && (!self.delegated || acc.delegated)
+            && self.mint.is_none_or(|m| m == acc.mint)
+    }
+}
+
+pub async fn run(rpc_url: &str, wallet_str: &str, filter: AccountFilter, json: bool) -> Result<()> {
+    let wallet = utils::parse_pubkey(wallet_str)?;
+
+    if !json {
+        println!(
+            "\n{} Token accounts for {}\n",
+            "📋".bold(),
+            utils::short_key(&wallet).cyan()
+        );
+    }
+
+    let t22: Pubkey = crate::rpc::TOKEN_2022_PROGRAM_ID.parse().unwrap();
+    let mut rows = Vec::new();
+    let mut total = 0usize;
+
+    for (program, label) in [(spl_token::id(), "spl-token"), (t22, "token-2022")] {
+        let accounts = tokio::task::spawn_blocking({
+            let c = crate::rpc::client(rpc_url);
+            move || crate::rpc::get_token_accounts(&c, &wallet, &program)
+        })
+        .await?
+        .context("Failed to get token accounts")?;
+
+        for (addr, acc) in accounts {
+            let Some(tok) = token::parse_account(&acc) else {
+                continue;
+            };
+            total += 1;
+            if !filter.matches(&tok) {
+                continue;
+            }
+            rows.push(AccountRow {
+                address: addr.to_string(),
+                mint: tok.mint.to_string(),
+                program: label,
+                amount: tok.amount,
+                decimals: None,
+                balance: None,
+                frozen: tok.frozen,
+                delegated: tok.delegated,
+                rent_lamports: acc.lamports,
+                mint_key: tok.mint,
+            });
+        }
+    }
+
+    // real balances need mint decimals
+    let decimals = fetch_decimals(rpc_url, &rows).await?;
+    for r in &mut rows {
+        r.decimals = decimals.get(&r.mint_key).copied();
+        r.balance = r.decimals.map(|d| utils::token_amount(r.amount, d));
+    }
+
+    if json {
+        utils::print_json(
+            "accounts",
+            serde_json::json!({
+                "wallet": wallet.to_string(),
+                "total": total,
+                "count": rows.len(),
+                "accounts": rows,
+            }),
+        );
+        return Ok(());
+    }
+
+    if rows.is_empty() {
+        println!("  {}\n", "No matching accounts".dimmed());
+        return Ok(());
+    }
+
+    for r in &rows {
+        let balance = match r.balance {
+            Some(b) => format!("{b}"),
+            None => format!("{} raw", r.amount),
+        };
+        let mut flags = Vec::new();
+        if r.frozen {
+            flags.push("frozen".red().to_string());
+        }
+        if r.delegated {
+            flags.push("delegated".yellow().to_string());
+        }
+        if r.program == "token-2022" {
+            flags.push("t22".dimmed().to_string());
+        }
+
+        println!(
+            "  {} {} {:>18} {} {}",
+            r.address.white(),
+            utils::short_key(&r.mint_key).cyan(),
+            if r.amount == 0 {
+                balance.dimmed()
+            } else {
+                balance.green()
+            },
+            utils::format_sol(utils::lamports_to_sol(r.rent_lamports)).dimmed(),
+            flags.join(" ")
+        );
+    }
+
+    let rent: u64 = rows.iter().map(|r| r.rent_lamports).sum();
+    println!(
+        "\n  {} of {} accounts, {} rent\n",
+        rows.len().to_string().white().bold(),
+        total,
+        utils::format_sol(utils::lamports_to_sol(rent)).green()
+    );
+
+    Ok(())
+}
+
+/// Decimals for every mint in `rows` (mints that fail to load are left out)
+async fn fetch_decimals(rpc_url: &str, rows: &[AccountRow]) -> Result<HashMap<Pubkey, u8>> {
+    let mut mints: Vec<Pubkey> = rows.iter().map(|r| r.mint_key).collect();
+    mints.sort();
+    mints.dedup();
+
+    let mut out = HashMap::new();
+    for chunk in mints.chunks(100) {
+        let keys = chunk.to_vec();
+        let c = crate::rpc::client(rpc_url);
+        let accs = tokio::task::spawn_blocking(move || c.get_multiple_accounts(&keys))
+            .await?
+            .context("Failed to get mint info")?;
+
+        for (mint, acc) in chunk.iter().zip(accs) {
+            if let Some(d) = acc.and_then(|a| a.data.get(44).copied()) {
+                out.insert(*mint, d);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tok(amount: u64, frozen: bool, delegated: bool, mint: Pubkey) -> token::ParsedTokenAccount {
+        token::ParsedTokenAccount {
+            mint,
+            amount,
+            delegated,
+            frozen,
+        }
+    }
+
+    #[test]
+    fn test_account_filter() {
+        let mint = Pubkey::new_unique();
+        let empty = tok(0, false, false, mint);
+        let frozen = tok(5, true, false, mint);
+        let delegated = tok(0, false, true, Pubkey::new_unique());
+
+        let all = AccountFilter::default();
+        assert!(all.matches(&empty) && all.matches(&frozen) && all.matches(&delegated));
+
+        let f = AccountFilter {
+            empty: true,
+            ..Default::default()
+        };
+        assert!(f.matches(&empty) && !f.matches(&frozen) && f.matches(&delegated));
+
+        // filters combine
+        let f = AccountFilter {
+            empty: true,
+            mint: Some(mint),
+            ..Default::default()
+        };
+        assert!(f.matches(&empty) && !f.matches(&delegated));
+
+        let f = AccountFilter {
+            frozen: true,
+            ..Default::default()
+        };
+        assert!(!f.matches(&empty) && f.matches(&frozen));
+
+        let f = AccountFilter {
+            delegated: true,
+            ..Default::default()
+        };
+        assert!(f.matches(&delegated) && !f.matches(&empty));
+    }
+}
@@ -0,0 +1,217 @@
+//! Recent activity: the last N transactions of a wallet as a static table
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::HashSet;
+
+use crate::commands::monitor::{self, TxEvent};
+use crate::utils;
+
+/// CLI options for `activity`
+pub struct ActivityOptions<'a> {
+    pub wallet: &'a str,
+    pub limit: usize,
+    /// Fetch every transaction for its SOL change
+    pub detailed: bool,
+    pub filter: ActivityFilter,
+    pub json: bool,
+}
+
+/// `--direction`: which way SOL moved for the wallet
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+/// Row filters on the SOL change, all given ones must match
+#[derive(Default)]
+pub struct ActivityFilter {
+    pub direction: Option<Direction>,
+    /// Minimum SOL moved, either way
+    pub min_amount: Option<f64>,
+}
+
+impl ActivityFilter {
+    /// Filters need the SOL change, so they imply `--detailed`
+    fn is_empty(&self) -> bool {
+        self.direction.is_none() && self.min_amount.is_none()
+    }
+
+    /// Txs with no known change only pass without filters
+    fn matches(&self, change: Option<f64>) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let Some(change) = change else {
+            return false;
+        };
+        let direction = match self.direction {
+            Some(Direction::In) => change > 0.0,
+            Some(Direction::Out) => change < 0.0,
+            None => true,
+        };
+        direction && self.min_amount.is_none_or(|min| change.abs() >= min)
+    }
+}
+
+pub async fn run(rpc_url: &str, opts: ActivityOptions<'_>) -> Result<()> {
+    let ActivityOptions {
+        wallet: wallet_str,
+        limit,
+        detailed,
+        filter,
+        json,
+    } = opts;
+    let wallet = utils::parse_pubkey(wallet_str)?;
+
+    if !json {
+        println!(
+            "\n{} Recent activity for {}\n",
+            "📜".bold(),
+            utils::short_key(&wallet).cyan()
+        );
+    }
+
+    let client = crate::rpc::client(rpc_url);
+    let config = monitor::signatures_config(Some(limit.clamp(1, 1000)));
+    let sigs = tokio::task::spawn_blocking(move || {
+        client.get_signatures_for_address_with_config(&wallet, config)
+    })
+    .await?
+    .context("Failed to fetch signatures")?;
+
+    // newest first, as the RPC returns them
+    let pending: Vec<_> = sigs
+        .iter()
+        .filter_map(|info| Some((utils::parse_signature(&info.signature).ok()?, info)))
+        .collect();
+    let detailed = detailed || !filter.is_empty();
+    let mut events = if detailed {
+        monitor::fetch_events(rpc_url, &wallet, pending, &HashSet::new()).await?
+    } else {
+        pending
+            .into_iter()
+            .map(|(sig, info)| TxEvent::new(sig, info, None))
+            .collect()
+    };
+    events.retain(|ev| filter.matches(ev.sol_change));
+
+    if json {
+        utils::print_json(
+            "activity",
+            serde_json::json!({
+                "wallet": wallet.to_string(),
+                "fetched": sigs.len(),
+                "count": events.len(),
+                "transactions": events,
+            }),
+        );
+        return Ok(());
+    }
+
+    if events.is_empty() {
+        println!("  {}\n", "No transactions found".dimmed());
+        return Ok(());
+    }
+
+    let header = format!(
+        "{:<19} {:<6} {:<17} {:>16}",
+        "TIME", "STATUS", "SIGNATURE", "SOL"
+    );
+    println!("  {}", header.dimmed());
+    for ev in &events {
+        print_row(ev);
+    }
+    println!(
+        "\n  {} of {} transactions\n",
+        events.len().to_string().white().bold(),
+        sigs.len()
+    );
+
+    Ok(())
+}
+
+/// One table row; without details the change is unknown, not zero
+fn print_row(ev: &TxEvent) {
+    let time = ev
+        .block_time
+        .and_then(|bt| chrono::DateTime::from_timestamp(bt, 0))
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "?".into());
+    let time = format!("{time:<19}");
+    let status = if ev.success {
+        format!("{:<6}", "OK").green().bold()
+    } else {
+        format!("{:<6}", "FAIL").red().bold()
+    };
+    let change = format_change(ev.sol_change);
+    let change = match ev.sol_change {
+        Some(d) if d > 0.0 => change.green(),
+        Some(d) if d < 0.0 => change.red(),
+        _ => change.dimmed(),
+    };
+    let memo = ev
+        .memo
+        .as_ref()
+        .map(|m| format!(" memo:{}", m.dimmed()))
+        .unwrap_or_default();
+
+    println!(
+        "  {} {} {} {}{}",
+        time.dimmed(),
+        status,
+        utils::short_sig(&ev.sig).white(),
+        change,
+        memo
+    );
+}
+
+/// Right-aligned SOL change: `—` when not fetched (or the fetch failed),
+/// `0` when nothing moved
+fn format_change(change: Option<f64>) -> String {
+    let s = match change {
+        Some(d) if d > 0.0 => format!("+{d:.6}"),
+        Some(d) if d < 0.0 => format!("{d:.6}"),
+        Some(_) => "0".to_string(),
+        None => "—".to_string(),
+    };
+    format!("{s:>16}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches() {
+        let all = ActivityFilter::default();
+        assert!(all.matches(None));
+        assert!(all.matches(Some(-1.0)));
+
+        let incoming = ActivityFilter {
+            direction: Some(Direction::In),
+            min_amount: None,
+        };
+        assert!(incoming.matches(Some(0.5)));
+        assert!(!incoming.matches(Some(-0.5)));
+        assert!(!incoming.matches(None));
+
+        let big_out = ActivityFilter {
+            direction: Some(Direction::Out),
+            min_amount: Some(1.0),
+        };
+        assert!(big_out.matches(Some(-1.0)));
+        assert!(!big_out.matches(Some(-0.1)));
+        assert!(!big_out.matches(Some(2.0)));
+        assert!(!incoming.matches(Some(0.0)));
+    }
+
+    #[test]
+    fn test_format_change() {
+        assert_eq!(format_change(Some(1.5)).trim(), "+1.500000");
+        assert_eq!(format_change(Some(-0.25)).trim(), "-0.250000");
+        assert_eq!(format_change(Some(0.0)).trim(), "0");
+        assert_eq!(format_change(None).trim(), "—");
+    }
+}
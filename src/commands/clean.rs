@@ -1,49 +1,187 @@
 use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use colored::Colorize;
-use dialoguer::Confirm;
 use indicatif::{ProgressBar, ProgressStyle};
-use solana_account_decoder::UiAccountEncoding;
-use solana_client::{
-    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
-    rpc_filter::{Memcmp, RpcFilterType},
-};
+use solana_client::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, pubkey::Pubkey,
-    signature::Keypair, signer::Signer, transaction::Transaction,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::Transaction,
 };
-use spl_token::instruction::close_account;
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
 
 use crate::solanapay;
-use crate::utils;
+use crate::{price, token, utils};
+
+/// Relay payload (base64) above which one QR may be too much for a wallet
+const LARGE_PAYLOAD_BYTES: usize = 16 * 1024;
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, Clone)]
 struct CloseableAccount {
     address: String,
     mint: String,
     token_balance: f64,
     rent_lamports: u64,
+    #[serde(skip)]
+    program_id: Pubkey,
+    #[serde(skip)]
+    raw_amount: u64,
 }
 
-pub async fn run(
-    rpc_url: &str,
-    wallet_str: Option<&str>,
-    keypair_path: Option<&str>,
-    file_path: Option<&str>,
-    dry_run: bool,
-    batch_size: usize,
-    dust_threshold: Option<f64>,
-    connect: bool,
-    json: bool,
-) -> Result<()> {
+/// CLI options for `clean`
+pub struct CleanOptions<'a> {
+    pub wallet: Option<&'a str>,
+    pub keypair: Option<&'a str>,
+    pub file: Option<&'a str>,
+    pub dry_run: bool,
+    pub batch_size: usize,
+    pub dust: Option<f64>,
+    /// Close accounts whose tokens are worth less than their rent
+    pub dust_below_rent: bool,
+    /// Close accounts holding less than this percent of the mint's supply
+    pub dust_supply_pct: Option<f64>,
+    pub connect: bool,
+    /// Comma-separated RPC URLs, spread across wallets in batch mode
+    pub rpc_pool: Option<&'a str>,
+    /// token | token2022 | both
+    pub owner_program: &'a str,
+    /// micro-lamports per CU, or "auto"
+    pub priority_fee: &'a str,
+    /// Prompt before each batch in keypair mode
+    pub confirm_each: bool,
+    /// Accounts listed before sending, 0 = all
+    pub preview: usize,
+    /// Decimal places for dust balances in that list
+    pub precision: usize,
+    /// List one line per mint instead of per account
+    pub group: bool,
+    /// Refuse to burn an account whose tokens are worth more than this (USD)
+    pub max_dust_usd: f64,
+    /// Burn even above `max_dust_usd`
+    pub force: bool,
+    /// Leave one closeable account open per mint
+    pub keep_one_per_mint: bool,
+    /// Batch mode: stop every wallet after the first failure
+    pub fail_fast: bool,
+    /// Burn dust balances so those accounts can be closed
+    pub burn: bool,
+    /// Let `burn` destroy 0-decimal (NFT) and unknown-decimal mints too
+    pub burn_nfts: bool,
+    /// Connect mode: also print the `solana:` link under the QR
+    pub deep_link: bool,
+    /// Connect mode: transactions per QR, one relay session each
+    pub qr_chunk: Option<usize>,
+    /// Keypair mode: compare the SOL balance change with the expected net
+    pub verify: bool,
+    /// Mints whose accounts are never closed (`--ignore-mints`)
+    pub ignore_mints: &'a HashSet<Pubkey>,
+    /// Write the unsigned batch transactions here instead of sending
+    pub dump_tx: Option<&'a str>,
+    /// Durable nonce accounts for `dump_tx`, one per batch
+    pub nonce: &'a [Pubkey],
+    /// Write the batch instructions for a multisig vault (the wallet) here
+    pub multisig: Option<&'a str>,
+    pub json: bool,
+}
+
+pub async fn run(rpc_url: &str, opts: CleanOptions<'_>) -> Result<()> {
+    let CleanOptions {
+        wallet: wallet_str,
+        keypair: keypair_path,
+        file: file_path,
+        dry_run,
+        batch_size,
+        dust: dust_threshold,
+        dust_below_rent,
+        dust_supply_pct,
+        connect,
+        rpc_pool,
+        owner_program,
+        priority_fee,
+        confirm_each,
+        preview,
+        precision,
+        group,
+        max_dust_usd,
+        force,
+        keep_one_per_mint,
+        fail_fast,
+        burn,
+        burn_nfts,
+        deep_link,
+        qr_chunk,
+        verify,
+        ignore_mints,
+        dump_tx,
+        nonce,
+        multisig,
+        json,
+    } = opts;
+
+    if confirm_each && json {
+        anyhow::bail!("--confirm-each is interactive and can't be combined with --json");
+    }
+    if confirm_each && utils::assume_yes() {
+        anyhow::bail!("--confirm-each asks per batch and can't be combined with --yes");
+    }
+
+    if let Some(pct) = dust_supply_pct {
+        if !(pct > 0.0 && pct <= 100.0) {
+            anyhow::bail!("--dust-supply-pct must be in (0, 100], got {pct}");
+        }
+    }
+
+    let programs = owner_programs(owner_program)?;
+    let priority_fee = PriorityFee::parse(priority_fee)?;
+    let dust = DustRules {
+        amount: dust_threshold,
+        below_rent: dust_below_rent,
+        supply_pct: dust_supply_pct,
+        burn,
+        burn_nfts,
+        max_usd: max_dust_usd,
+        force,
+    };
+    if dust.per_mint() && !burn && !json {
+        eprintln!(
+            "{} Accounts holding dust can only be closed with --burn, skipping them",
+            "⚠".yellow()
+        );
+    }
+    let cache = Arc::new(MintInfoCache::default());
+
     //  Batch mode: process CSV file
     if let Some(path) = file_path {
-        return run_batch(rpc_url, path, dry_run, batch_size, dust_threshold, json).await;
+        let pool = parse_rpc_pool(rpc_url, rpc_pool);
+        return run_batch(
+            &pool,
+            &programs,
+            path,
+            dry_run,
+            batch_size,
+            dust,
+            cache,
+            priority_fee,
+            keep_one_per_mint,
+            fail_fast,
+            ignore_mints,
+            json,
+        )
+        .await;
     }
 
     //  Connect Flow
     let wallet = if connect && wallet_str.is_none() {
         // use shared logic
-        crate::solanapay::connect_wallet().await?
+        crate::solanapay::connect_wallet(deep_link, json).await?
     } else {
         // parse arg
         let s = wallet_str.ok_or_else(|| {
@@ -62,20 +200,40 @@ pub async fn run(
         );
     }
 
-    let closeable = fetch_and_analyze(rpc_url, &wallet, dust_threshold).await?;
+    let (closeable, kept) = fetch_and_analyze(
+        rpc_url,
+        &wallet,
+        &programs,
+        dust,
+        ignore_mints,
+        &cache,
+        keep_one_per_mint,
+    )
+    .await?;
 
     if !json {
         println!(
             "  Found {} accounts total",
             closeable.len().to_string().white().bold()
         );
+        if kept > 0 {
+            println!("  Keeping {kept} accounts open (one per mint)");
+        }
     }
 
     if closeable.is_empty() {
-        if json {
-            println!(
-                "{}",
-                serde_json::json!({ "status": "clean", "closeable": 0 })
+        let unused = tokio::task::spawn_blocking({
+            let c = crate::rpc::client(rpc_url);
+            move || crate::rpc::wallet_unused(&c, &wallet)
+        })
+        .await?
+        .unwrap_or(false);
+        if unused {
+            utils::print_unused_wallet("clean", &wallet, json);
+        } else if json {
+            utils::print_json(
+                "clean",
+                serde_json::json!({ "status": "clean", "closeable": 0, "kept": kept }),
             );
         } else {
             println!("\n  {}", "✅ Wallet is clean!".green());
@@ -87,22 +245,34 @@ pub async fn run(
     let total_sol = utils::lamports_to_sol(total_rent);
 
     // get price
-    let sol_usd = crate::price::sol_price().await.unwrap_or(0.0);
-    let total_usd = total_sol * sol_usd;
+    let sol_usd = crate::price::sol_price().await;
+    let total_usd = sol_usd.map(|p| total_sol * p);
 
     if json {
         let accs: Vec<&CloseableAccount> = closeable.iter().map(|c| &c.1).collect();
-        println!(
-            "{}",
-            serde_json::json!({
-                "status": "found",
-                "closeable": closeable.len(),
-                "reclaimable_sol": total_sol,
-                "reclaimable_usd": total_usd,
-                "dry_run": dry_run,
-                "accounts": accs,
-            })
-        );
+        let mut out = serde_json::json!({
+            "status": "found",
+            "closeable": closeable.len(),
+            "kept": kept,
+            "reclaimable_sol": total_sol,
+            "reclaimable_usd": total_usd,
+            "dry_run": dry_run,
+            "accounts": accs,
+        });
+        if group {
+            let groups: Vec<serde_json::Value> = group_by_mint(&closeable)
+                .iter()
+                .map(|(mint, count, rent)| {
+                    serde_json::json!({
+                        "mint": mint,
+                        "accounts": count,
+                        "rent_sol": utils::lamports_to_sol(*rent),
+                    })
+                })
+                .collect();
+            out["by_mint"] = groups.into();
+        }
+        utils::print_json("clean", out);
         if dry_run {
             return Ok(());
         }
@@ -112,24 +282,36 @@ pub async fn run(
             closeable.len().to_string().yellow().bold()
         );
 
-        let usd_str = if sol_usd > 0.0 {
-            format!("(≈ {})", utils::format_usd(total_usd))
-                .dimmed()
-                .to_string()
-        } else {
-            "".to_string()
-        };
         println!(
             "  {} reclaimable {}",
             utils::format_sol(total_sol).green().bold(),
-            usd_str
+            utils::approx_usd(total_sol, sol_usd).dimmed()
         );
 
         println!();
-        let show = closeable.len().min(12);
+        if group {
+            let groups = group_by_mint(&closeable);
+            let show = preview_len(groups.len(), preview);
+            for (mint, count, rent) in &groups[..show] {
+                println!(
+                    "    {}: {} accounts, {} rent",
+                    mint_label(mint).white(),
+                    count,
+                    utils::format_sol(utils::lamports_to_sol(*rent))
+                );
+            }
+            if groups.len() > show {
+                println!("    … and {} more mints", groups.len() - show);
+            }
+        }
+        let show = if group {
+            0
+        } else {
+            preview_len(closeable.len(), preview)
+        };
         for (_, acc) in &closeable[..show] {
             let dust = if acc.token_balance > 0.0 {
-                format!(" dust:{:.6}", acc.token_balance)
+                format!(" dust:{:.precision$}", acc.token_balance)
                     .dimmed()
                     .to_string()
             } else {
@@ -142,7 +324,7 @@ pub async fn run(
                 dust
             );
         }
-        if closeable.len() > show {
+        if !group && closeable.len() > show {
             println!("    … and {} more", closeable.len() - show);
         }
 
@@ -154,59 +336,134 @@ pub async fn run(
 
     //  Execution
 
+    if let Some(dir) = dump_tx {
+        let nonces = nonce
+            .iter()
+            .map(|n| crate::rpc::DurableNonce::fetch(&client, n))
+            .collect::<Result<Vec<_>>>()?;
+        let txs = unsigned_batches(
+            &client,
+            &closeable,
+            batch_size,
+            &wallet,
+            priority_fee,
+            &nonces,
+        )?;
+        let files = utils::dump_transactions(dir, "clean", &txs)?;
+        utils::print_dumped("clean", &files, !nonces.is_empty(), json);
+        return Ok(());
+    }
+
+    if let Some(path) = multisig {
+        let bundle = multisig_bundle(&closeable, batch_size, &wallet);
+        std::fs::write(path, serde_json::to_string_pretty(&bundle)?)
+            .context(format!("Failed to write {path}"))?;
+        let batches = closeable.len().div_ceil(batch_size);
+        if json {
+            utils::print_json(
+                "clean",
+                serde_json::json!({ "status": "multisig", "file": path, "batches": batches }),
+            );
+        } else {
+            println!(
+                "\n  {} Wrote {} batch(es) of instructions for vault {} to {}",
+                "💾".bold(),
+                batches,
+                utils::short_key(&wallet).cyan(),
+                path.dimmed()
+            );
+            println!(
+                "  {}",
+                "Import each batch as one multisig transaction, nothing was signed.".dimmed()
+            );
+        }
+        return Ok(());
+    }
+
     if connect {
         // SOLANA PAY MODE
         if !json {
-            if !Confirm::new()
-                .with_prompt(format!(
-                    "Close {} accounts via mobile wallet?",
-                    closeable.len()
-                ))
-                .default(true)
-                .interact()?
-            {
+            if !utils::confirm(
+                &format!("Close {} accounts via mobile wallet?", closeable.len()),
+                true,
+            )? {
                 println!("{}", "  Cancelled.".dimmed());
                 return Ok(());
             }
         }
 
-        let recent_hash = client.get_latest_blockhash()?;
-        let mut all_transactions = Vec::new();
+        let mut all_transactions =
+            unsigned_batches(&client, &closeable, batch_size, &wallet, priority_fee, &[])?;
 
-        // batch ixs
-        for batch in closeable.chunks(batch_size) {
-            let mut ixs = vec![
-                ComputeBudgetInstruction::set_compute_unit_limit(batch.len() as u32 * 3000 + 5000),
-                ComputeBudgetInstruction::set_compute_unit_price(1000),
-            ];
-            for (addr, _) in batch.iter() {
-                ixs.push(close_account(
-                    &spl_token::id(),
-                    addr,
-                    &wallet,
-                    &wallet,
-                    &[],
-                )?);
-            }
-            let mut tx = Transaction::new_with_payer(&ixs, Some(&wallet));
-            tx.message.recent_blockhash = recent_hash;
-            all_transactions.push(tx);
-        }
+        utils::print_status(
+            format!("\n{}", "📱 Preparing transaction...".cyan().bold()),
+            json,
+        );
 
-        println!("\n{}", "📱 Preparing transaction...".cyan().bold());
+        let payload = payload_bytes(&all_transactions);
+        if qr_chunk.is_none() && payload > LARGE_PAYLOAD_BYTES && !json {
+            eprintln!(
+                "{} {} transactions ({} KB) behind one QR, if your wallet struggles retry with --qr-chunk 5",
+                "⚠".yellow(),
+                all_transactions.len(),
+                payload / 1024
+            );
+        }
 
-        // upload to relay
-        let solana_pay_url = solanapay::upload_transactions(
-            solanapay::DEFAULT_RELAY_URL,
-            &all_transactions,
-            &wallet,
-            "sol-tool: Close Empty Accounts",
-        )
-        .await?;
+        // one relay session per chunk, scanned in order
+        let chunk_len = qr_chunk.unwrap_or(all_transactions.len()).max(1);
+        let total_chunks = all_transactions.len().div_ceil(chunk_len);
+        for (i, chunk) in all_transactions.chunks_mut(chunk_len).enumerate() {
+            // waiting on earlier QRs outlives a blockhash, so stamp each
+            // chunk just before it goes up
+            let recent_hash = client.get_latest_blockhash()?;
+            for tx in chunk.iter_mut() {
+                tx.message.recent_blockhash = recent_hash;
+            }
+            let label = qr_label(i, total_chunks);
+            let solana_pay_url = solanapay::upload_transactions(
+                solanapay::DEFAULT_RELAY_URL,
+                chunk,
+                &wallet,
+                &format!("sol-tool: Close Empty Accounts{label}"),
+            )
+            .await?;
+
+            utils::print_status("✓ Uploaded successfully".green(), json);
+            if total_chunks > 1 {
+                let header = format!(
+                    "QR {} of {} ({} transactions)",
+                    i + 1,
+                    total_chunks,
+                    chunk.len()
+                );
+                utils::print_status(format!("\n{}", header.cyan().bold()), json);
+            }
+            solanapay::display_qr(&solana_pay_url, deep_link, json);
 
-        println!("{}", "✓ Uploaded successfully".green());
-        solanapay::display_qr(&solana_pay_url)?;
+            let more = i + 1 < total_chunks;
+            if more && !json && std::io::stdin().is_terminal() {
+                println!(
+                    "\n{}",
+                    "Sign this one, then press Enter for the next QR".dimmed()
+                );
+                std::io::stdin().read_line(&mut String::new())?;
+            }
+        }
 
+        if json {
+            utils::print_json(
+                "clean",
+                serde_json::json!({
+                    "status": "uploaded",
+                    "accounts": closeable.len(),
+                    "reclaimable_sol": total_sol,
+                    "transactions": all_transactions.len(),
+                    "qr_codes": total_chunks,
+                }),
+            );
+            return Ok(());
+        }
         println!(
             "\n{}",
             "Scan QR with your wallet to sign and send.".dimmed()
@@ -225,45 +482,27 @@ pub async fn run(
     }
 
     // KEYPAIR MODE
-    let keypair = if let Some(path_or_key) = keypair_path {
-        if std::path::Path::new(path_or_key).exists() {
-            solana_sdk::signature::read_keypair_file(path_or_key)
-                .map_err(|e| anyhow::anyhow!("Failed keypair file: {}", e))?
-        } else {
-            let bytes = bs58::decode(path_or_key)
-                .into_vec()
-                .map_err(|_| anyhow::anyhow!("Invalid keypair"))?;
-            Keypair::try_from(bytes.as_slice())
-                .map_err(|e| anyhow::anyhow!("Invalid bytes: {}", e))?
-        }
-    } else {
-        utils::load_keypair(None)?
+    let keypair = match keypair_path {
+        Some(arg) => utils::parse_keypair_arg(arg)?,
+        None => utils::load_keypair(None)?,
     };
 
     utils::verify_keypair(&keypair, &wallet)?;
 
-    if !json {
-        if !Confirm::new()
-            .with_prompt(format!(
-                "Close {} accounts and reclaim {}?",
-                closeable.len(),
-                utils::format_sol(total_sol)
-            ))
-            .default(false)
-            .interact()?
-        {
+    // with --confirm-each every batch gets its own prompt instead
+    if !json && !confirm_each {
+        let fees = estimate_fees(&client, &closeable, batch_size, &wallet, priority_fee)?;
+        if !utils::confirm(&confirm_prompt(closeable.len(), total_rent, fees), false)? {
             println!("{}", "  Cancelled.".dimmed());
             return Ok(());
         }
     }
 
     // execute batches
-    let batches: Vec<Vec<&(Pubkey, CloseableAccount)>> = closeable
-        .chunks(batch_size)
-        .map(|c| c.iter().collect())
-        .collect();
+    let batches: Vec<&[(Pubkey, CloseableAccount)]> = closeable.chunks(batch_size).collect();
 
-    let pb: Option<ProgressBar> = if !json {
+    // prompts and the progress bar would garble each other
+    let pb: Option<ProgressBar> = if !json && !confirm_each {
         let p = ProgressBar::new(batches.len() as u64);
         p.set_style(
             ProgressStyle::default_bar()
@@ -276,8 +515,15 @@ pub async fn run(
         None
     };
 
+    let balance_before = if verify {
+        Some(client.get_balance(&wallet)?)
+    } else {
+        None
+    };
+
     let mut closed = 0usize;
     let mut reclaimed = 0u64;
+    let mut fees = 0u64;
     let mut sigs = Vec::new();
 
     for (i, batch) in batches.iter().enumerate() {
@@ -285,21 +531,19 @@ pub async fn run(
             p.set_message(format!("batch {}", i + 1));
         }
 
-        let mut ixs = vec![
-            ComputeBudgetInstruction::set_compute_unit_limit(batch.len() as u32 * 3000 + 5000),
-            ComputeBudgetInstruction::set_compute_unit_price(1000),
-        ];
-
-        for (addr, _) in batch {
-            ixs.push(close_account(
-                &spl_token::id(),
-                addr,
-                &wallet,
-                &wallet,
-                &[],
-            )?);
+        if confirm_each && !confirm_batch(batch, i, batches.len())? {
+            println!("  {}", format!("Skipped batch {}", i + 1).dimmed());
+            continue;
         }
 
+        let addrs: Vec<Pubkey> = batch.iter().map(|(a, _)| *a).collect();
+        let ixs = build_close_batch_ixs(
+            batch,
+            &wallet,
+            priority_fee.price(&client, &wallet, &addrs),
+            close_cu_limit(batch),
+        );
+
         let lh = client.get_latest_blockhash()?;
         let tx = Transaction::new_signed_with_payer(&ixs, Some(&wallet), &[&keypair], lh);
 
@@ -308,16 +552,18 @@ pub async fn run(
                 let br: u64 = batch.iter().map(|b| b.1.rent_lamports).sum();
                 closed += batch.len();
                 reclaimed += br;
+                fees += client.get_fee_for_message(&tx.message).unwrap_or(0);
                 sigs.push(sig.to_string());
                 if let Some(ref p) = pb {
                     p.inc(1);
                 }
             }
             Err(e) => {
-                if !json {
-                    if let Some(ref p) = pb {
-                        p.println(format!("  {} batch {} failed: {}", "⚠".yellow(), i + 1, e));
-                    }
+                let line = format!("  {} batch {} failed: {}", "⚠".yellow(), i + 1, e);
+                // no bar with --confirm-each or --json
+                match &pb {
+                    Some(p) => p.println(line),
+                    None => utils::print_status(line, json),
                 }
             }
         }
@@ -327,32 +573,36 @@ pub async fn run(
         p.finish_and_clear();
     }
 
+    let expected = net_lamports(reclaimed, fees);
+    let observed = match balance_before {
+        Some(before) => Some(client.get_balance(&wallet)? as i64 - before as i64),
+        None => None,
+    };
+
     if json {
-        println!(
-            "{}",
-            serde_json::json!({
-                "status": "done",
-                "closed": closed,
-                "reclaimed_sol": utils::lamports_to_sol(reclaimed),
-                "signatures": sigs
-            })
-        );
+        let mut out = serde_json::json!({
+            "status": "done",
+            "closed": closed,
+            "reclaimed_sol": utils::lamports_to_sol(reclaimed),
+            "fees_sol": utils::lamports_to_sol(fees),
+            "net_sol": net_sol(expected),
+            "signatures": sigs
+        });
+        if let Some(observed) = observed {
+            out["verify"] = serde_json::json!({
+                "expected_lamports": expected,
+                "observed_lamports": observed,
+                "matches": observed == expected,
+            });
+        }
+        utils::print_json("clean", out);
     } else {
         println!(
             "\n  {} {} accounts closed",
             "✅".green(),
             closed.to_string().green().bold()
         );
-        let usd_str = if sol_usd > 0.0 {
-            format!(
-                "(≈ {})",
-                utils::format_usd(utils::lamports_to_sol(reclaimed) * sol_usd)
-            )
-            .dimmed()
-            .to_string()
-        } else {
-            "".to_string()
-        };
+        let usd_str = utils::approx_usd(utils::lamports_to_sol(reclaimed), sol_usd).dimmed();
         println!(
             "  {} {} reclaimed {}",
             "💰",
@@ -361,96 +611,727 @@ pub async fn run(
                 .bold(),
             usd_str
         );
+        print_fee_summary(reclaimed, fees);
+        if let Some(observed) = observed {
+            println!("  {}", verify_line(expected, observed));
+        }
+
+        for sig in &sigs {
+            println!("     https://solscan.io/tx/{}", sig.dimmed());
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// List the accounts in a batch and ask whether to send it
+fn confirm_batch(batch: &[(Pubkey, CloseableAccount)], idx: usize, total: usize) -> Result<bool> {
+    println!("\n  {} Batch {}/{}", "📦".bold(), idx + 1, total);
+    for (_, acc) in batch {
+        println!(
+            "    {} mint {} → {}",
+            acc.address.dimmed(),
+            acc.mint.cyan(),
+            utils::format_sol(utils::lamports_to_sol(acc.rent_lamports)).white()
+        );
+    }
+    let rent: u64 = batch.iter().map(|b| b.1.rent_lamports).sum();
+
+    utils::confirm(
+        &format!(
+            "Close these {} accounts ({})?",
+            batch.len(),
+            utils::format_sol(utils::lamports_to_sol(rent))
+        ),
+        false,
+    )
+}
+
+/// `--priority-fee`: compute-unit price in micro-lamports
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PriorityFee {
+    Fixed(u64),
+    /// Estimated per batch from recent fees
+    Auto,
+}
+
+impl PriorityFee {
+    fn parse(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        s.parse()
+            .map(Self::Fixed)
+            .context(format!("Invalid priority fee: {s} (use a number or auto)"))
+    }
+
+    /// Price for a batch closing `accounts` (blocking in auto mode)
+    fn price(self, client: &RpcClient, wallet: &Pubkey, accounts: &[Pubkey]) -> u64 {
+        match self {
+            Self::Fixed(p) => p,
+            Self::Auto => {
+                let mut writable = accounts.to_vec();
+                writable.push(*wallet);
+                crate::rpc::estimate_priority_fee(client, &writable)
+            }
+        }
+    }
+}
+
+/// Which non-empty accounts count as dust
+#[derive(Clone, Copy)]
+struct DustRules {
+    /// `--dust`: token balance (mint decimals applied) at or below this
+    amount: Option<f64>,
+    /// `--dust-below-rent`: token value below the account's rent value
+    below_rent: bool,
+    /// `--dust-supply-pct`: balance below this percent of the mint's supply
+    supply_pct: Option<f64>,
+    /// `--burn`: burn dust before closing; without it dust can't be closed
+    burn: bool,
+    /// `--burn-nfts`: also burn 0-decimal and unknown-decimal mints
+    burn_nfts: bool,
+    /// `--max-dust-usd`: refuse to burn any single account worth more
+    max_usd: f64,
+    /// `--force`: skip that check
+    force: bool,
+}
+
+impl DustRules {
+    /// Every rule needs per-mint info, so every open account is a candidate
+    fn per_mint(&self) -> bool {
+        self.amount.is_some() || self.below_rent || self.supply_pct.is_some()
+    }
+}
+
+/// Mint decimals, supplies and prices, shared by all wallets in batch mode
+#[derive(Default)]
+struct MintInfoCache {
+    decimals: Mutex<HashMap<Pubkey, u8>>,
+    /// Raw total supply, read from the same mint accounts as the decimals
+    supplies: Mutex<HashMap<Pubkey, u64>>,
+    /// `None` = no price known, never retried
+    prices: Mutex<HashMap<String, Option<f64>>>,
+}
+
+impl MintInfoCache {
+    /// Fetch decimals and prices for mints not seen yet (plus SOL)
+    async fn resolve(&self, rpc_url: &str, mints: &[Pubkey]) -> Result<()> {
+        self.resolve_decimals(rpc_url, mints).await?;
+
+        let unpriced: Vec<String> = {
+            let prices = self.prices.lock().unwrap();
+            std::iter::once(price::SOL_MINT.to_string())
+                .chain(mints.iter().map(|m| m.to_string()))
+                .filter(|m| !prices.contains_key(m))
+                .collect()
+        };
+        if !unpriced.is_empty() {
+            let fetched = price::fetch_prices(&unpriced).await.unwrap_or_default();
+            let mut prices = self.prices.lock().unwrap();
+            for m in unpriced {
+                let p = fetched.get(&m).copied().filter(|p| *p > 0.0);
+                prices.insert(m, p);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetch decimals and supply for mints not seen yet, 100 mints per request
+    async fn resolve_decimals(&self, rpc_url: &str, mints: &[Pubkey]) -> Result<()> {
+        let missing: Vec<Pubkey> = {
+            let decimals = self.decimals.lock().unwrap();
+            let mut m: Vec<Pubkey> = mints
+                .iter()
+                .filter(|m| !decimals.contains_key(m))
+                .copied()
+                .collect();
+            m.sort();
+            m.dedup();
+            m
+        };
+
+        for chunk in missing.chunks(100) {
+            let client = crate::rpc::client(rpc_url);
+            let keys = chunk.to_vec();
+            let accs = tokio::task::spawn_blocking(move || client.get_multiple_accounts(&keys))
+                .await?
+                .context("Failed to get mint info")?;
+
+            let mut decimals = self.decimals.lock().unwrap();
+            let mut supplies = self.supplies.lock().unwrap();
+            for (mint, acc) in chunk.iter().zip(accs) {
+                if let Some(acc) = acc.filter(|a| a.data.len() > 44) {
+                    // Mint layout: authority (36), supply u64, decimals u8
+                    let supply = u64::from_le_bytes(acc.data[36..44].try_into().unwrap());
+                    supplies.insert(*mint, supply);
+                    decimals.insert(*mint, acc.data[44]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn decimals(&self, mint: &Pubkey) -> Option<u8> {
+        self.decimals.lock().unwrap().get(mint).copied()
+    }
+
+    fn supply(&self, mint: &Pubkey) -> Option<u64> {
+        self.supplies.lock().unwrap().get(mint).copied()
+    }
+
+    fn price(&self, mint: &str) -> Option<f64> {
+        self.prices.lock().unwrap().get(mint).copied().flatten()
+    }
+}
+
+/// How many of `total` accounts to list, `--preview 0` shows all
+fn preview_len(total: usize, preview: usize) -> usize {
+    if preview == 0 {
+        total
+    } else {
+        total.min(preview)
+    }
+}
+
+/// `--dust-below-rent`: closing is net-positive when the tokens are worth
+/// less than the rent. Unknown prices never qualify.
+fn worth_less_than_rent(
+    balance: f64,
+    token_usd: Option<f64>,
+    rent_lamports: u64,
+    sol_usd: Option<f64>,
+) -> bool {
+    match (token_usd, sol_usd) {
+        (Some(p), Some(s)) => balance * p < utils::lamports_to_sol(rent_lamports) * s,
+        _ => false,
+    }
+}
+
+/// `--dust`: compared in tokens, so 0.001 means 0.001 USDC rather than
+/// 1000 raw units (which would be a whole NFT)
+fn within_dust(raw_amount: u64, decimals: u8, max: f64) -> bool {
+    utils::token_amount(raw_amount, decimals) <= max
+}
+
+/// `--burn` only destroys fungible dust unless `--burn-nfts`: a 0-decimal
+/// mint is usually an NFT, and unknown decimals can't be judged at all
+fn safe_to_burn(decimals: Option<u8>) -> bool {
+    decimals.is_some_and(|d| d > 0)
+}
+
+/// `--dust-supply-pct`: both sides are raw amounts, so decimals cancel out.
+/// A zero supply (burned out mint) never qualifies.
+fn below_supply_pct(raw_amount: u64, supply: u64, pct: f64) -> bool {
+    supply > 0 && (raw_amount as f64) < supply as f64 * pct / 100.0
+}
+
+/// Keep empty accounts, plus those within `--dust`, worth less than their
+/// rent or holding less than `--dust-supply-pct` of the supply
+async fn apply_mint_rules(
+    rpc_url: &str,
+    candidates: Vec<(Pubkey, CloseableAccount)>,
+    dust: DustRules,
+    cache: &MintInfoCache,
+) -> Result<Vec<(Pubkey, CloseableAccount)>> {
+    let mints: Vec<Pubkey> = candidates
+        .iter()
+        .filter(|(_, c)| c.raw_amount > 0)
+        .filter_map(|(_, c)| c.mint.parse().ok())
+        .collect();
+    // prices only matter for the rent rule
+    if dust.below_rent {
+        cache.resolve(rpc_url, &mints).await?;
+    } else {
+        cache.resolve_decimals(rpc_url, &mints).await?;
+    }
+
+    let sol_usd = cache.price(price::SOL_MINT);
+    let mut kept = Vec::new();
+    for (addr, mut acc) in candidates {
+        if acc.raw_amount == 0 {
+            kept.push((addr, acc));
+            continue;
+        }
+        let Ok(mint) = acc.mint.parse::<Pubkey>() else {
+            continue;
+        };
+        // unknown decimals count as 0: the raw amount is the largest reading,
+        // so the threshold can only under-match
+        let decimals = cache.decimals(&mint);
+        let by_threshold = dust
+            .amount
+            .is_some_and(|max| within_dust(acc.raw_amount, decimals.unwrap_or(0), max));
+        let Some(decimals) = decimals else {
+            if by_threshold {
+                kept.push((addr, acc));
+            }
+            continue;
+        };
+        acc.token_balance = utils::token_amount(acc.raw_amount, decimals);
+
+        let by_rent = dust.below_rent
+            && worth_less_than_rent(
+                acc.token_balance,
+                cache.price(&acc.mint),
+                acc.rent_lamports,
+                sol_usd,
+            );
+        let by_supply = dust.supply_pct.is_some_and(|pct| {
+            cache
+                .supply(&mint)
+                .is_some_and(|s| below_supply_pct(acc.raw_amount, s, pct))
+        });
+        if by_threshold || by_rent || by_supply {
+            kept.push((addr, acc));
+        }
+    }
+    Ok(kept)
+}
+
+async fn fetch_and_analyze(
+    rpc_url: &str,
+    wallet: &Pubkey,
+    programs: &[Pubkey],
+    dust: DustRules,
+    ignore: &HashSet<Pubkey>,
+    cache: &MintInfoCache,
+    keep_one_per_mint: bool,
+) -> Result<(Vec<(Pubkey, CloseableAccount)>, usize)> {
+    // with dust rules every open account is a candidate until checked
+    let dust_lamports = if dust.per_mint() { u64::MAX } else { 0 };
+    let mut closeable = Vec::new();
+    // every token account and its mint, to tell which mints stay open
+    let mut all = Vec::new();
+
+    for program in programs {
+        let rpc_url = rpc_url.to_string();
+        let wallet = *wallet;
+        let program = *program;
+
+        let accounts = tokio::task::spawn_blocking(move || {
+            let client = crate::rpc::client(&rpc_url);
+            crate::rpc::get_token_accounts(&client, &wallet, &program)
+        })
+        .await?
+        .context("Failed to fetch accounts")?;
+        all.extend(accounts.iter().filter_map(|(addr, acc)| {
+            token::parse_account(acc).map(|tok| (*addr, tok.mint.to_string()))
+        }));
+
+        // Use the pure logic filter
+        closeable.extend(filter_closeable_accounts(accounts, &program, dust_lamports));
+    }
+
+    closeable = without_ignored(closeable, ignore);
+    if dust.per_mint() {
+        closeable = apply_mint_rules(rpc_url, closeable, dust, cache).await?;
+    }
+    if !dust.burn {
+        closeable = without_balance(closeable);
+    }
+    set_token_balances(rpc_url, &mut closeable, cache).await?;
+    if dust.burn && !dust.burn_nfts {
+        closeable.retain(|(_, c)| {
+            c.raw_amount == 0 || safe_to_burn(c.mint.parse().ok().and_then(|m| cache.decimals(&m)))
+        });
+    }
+    if dust.burn && !dust.force {
+        check_dust_value(rpc_url, &closeable, dust.max_usd, cache).await?;
+    }
+    if !keep_one_per_mint {
+        return Ok((closeable, 0));
+    }
+
+    let closing: HashSet<Pubkey> = closeable.iter().map(|(addr, _)| *addr).collect();
+    let open_mints: HashSet<String> = all
+        .into_iter()
+        .filter(|(addr, _)| !closing.contains(addr))
+        .map(|(_, mint)| mint)
+        .collect();
+    Ok(keep_one_each(closeable, wallet, &open_mints))
+}
+
+/// Error out, listing each account, if any dust about to be burned is
+/// worth more than `limit` USD or has no price to check it against
+async fn check_dust_value(
+    rpc_url: &str,
+    closeable: &[(Pubkey, CloseableAccount)],
+    limit: f64,
+    cache: &MintInfoCache,
+) -> Result<()> {
+    let mints: Vec<Pubkey> = closeable
+        .iter()
+        .filter(|(_, c)| c.raw_amount > 0)
+        .filter_map(|(_, c)| c.mint.parse().ok())
+        .collect();
+    if mints.is_empty() {
+        return Ok(());
+    }
+    cache.resolve(rpc_url, &mints).await?;
+
+    let valuable = valuable_dust(closeable, |m| cache.price(m), limit);
+    if valuable.is_empty() {
+        return Ok(());
+    }
+    let lines: Vec<String> = valuable
+        .iter()
+        .map(|(acc, usd)| {
+            let value = match usd {
+                Some(usd) => format!("≈ {}", utils::format_usd(*usd)),
+                None => "no price".to_string(),
+            };
+            format!(
+                "  {} {} {} {}",
+                &acc.address[..16],
+                acc.token_balance,
+                mint_label(&acc.mint),
+                value
+            )
+        })
+        .collect();
+    anyhow::bail!(
+        "Refusing to burn {} account(s) worth more than {} each or unpriced (raise --max-dust-usd or use --force):\n{}",
+        valuable.len(),
+        utils::format_usd(limit),
+        lines.join("\n")
+    )
+}
+
+/// Dust accounts whose token value is above `limit`, with that value.
+/// Unpriced ones (`--offline`, price API down) count too, as `None`
+fn valuable_dust(
+    closeable: &[(Pubkey, CloseableAccount)],
+    price: impl Fn(&str) -> Option<f64>,
+    limit: f64,
+) -> Vec<(&CloseableAccount, Option<f64>)> {
+    closeable
+        .iter()
+        .filter(|(_, c)| c.raw_amount > 0)
+        .map(|(_, c)| (c, price(&c.mint).map(|p| c.token_balance * p)))
+        .filter(|(_, usd)| usd.is_none_or(|usd| usd > limit))
+        .collect()
+}
+
+/// Human dust balances from each mint's real decimals (cached, so free
+/// after the rent rule); unknown mints keep their raw amount
+async fn set_token_balances(
+    rpc_url: &str,
+    closeable: &mut [(Pubkey, CloseableAccount)],
+    cache: &MintInfoCache,
+) -> Result<()> {
+    let mints: Vec<Pubkey> = closeable
+        .iter()
+        .filter(|(_, c)| c.raw_amount > 0)
+        .filter_map(|(_, c)| c.mint.parse().ok())
+        .collect();
+    if mints.is_empty() {
+        return Ok(());
+    }
+    cache.resolve_decimals(rpc_url, &mints).await?;
+    for (_, acc) in closeable.iter_mut().filter(|(_, c)| c.raw_amount > 0) {
+        if let Some(decimals) = acc.mint.parse().ok().and_then(|m| cache.decimals(&m)) {
+            acc.token_balance = utils::token_amount(acc.raw_amount, decimals);
+        }
+    }
+    Ok(())
+}
+
+/// Base64 size of everything uploaded to the relay
+fn payload_bytes(transactions: &[Transaction]) -> usize {
+    transactions
+        .iter()
+        .map(|tx| solanapay::encode_transaction(tx).len())
+        .sum()
+}
+
+/// `--qr-chunk` relay label suffix, empty for a single QR
+fn qr_label(index: usize, total: usize) -> String {
+    if total > 1 {
+        format!(" ({} of {})", index + 1, total)
+    } else {
+        String::new()
+    }
+}
+
+/// `--group`: (mint, accounts, rent lamports), most accounts first
+fn group_by_mint(closeable: &[(Pubkey, CloseableAccount)]) -> Vec<(String, usize, u64)> {
+    let mut by_mint: HashMap<&str, (usize, u64)> = HashMap::new();
+    for (_, acc) in closeable {
+        let e = by_mint.entry(&acc.mint).or_default();
+        e.0 += 1;
+        e.1 += acc.rent_lamports;
+    }
+    let mut groups: Vec<(String, usize, u64)> = by_mint
+        .into_iter()
+        .map(|(m, (count, rent))| (m.to_string(), count, rent))
+        .collect();
+    groups.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+    groups
+}
+
+/// Symbol for known mints, else the shortened address
+fn mint_label(mint: &str) -> String {
+    match mint.parse::<Pubkey>() {
+        Ok(pk) => token::label(&pk),
+        Err(_) => mint.to_string(),
+    }
+}
+
+/// `--keep-one-per-mint`: drop one account of every mint from the close
+/// set, the wallet's canonical ATA if it's among them, else the first.
+/// Mints in `open_mints` already keep an account, so nothing is spared
+/// there. Returns the rest and how many were kept
+fn keep_one_each(
+    closeable: Vec<(Pubkey, CloseableAccount)>,
+    wallet: &Pubkey,
+    open_mints: &HashSet<String>,
+) -> (Vec<(Pubkey, CloseableAccount)>, usize) {
+    let mut keep: HashMap<String, Pubkey> = HashMap::new();
+    for (addr, acc) in &closeable {
+        if open_mints.contains(&acc.mint) {
+            continue;
+        }
+        let canonical = acc.mint.parse().is_ok_and(|mint| {
+            *addr
+                == spl_associated_token_account::get_associated_token_address_with_program_id(
+                    wallet,
+                    &mint,
+                    &acc.program_id,
+                )
+        });
+        if canonical {
+            keep.insert(acc.mint.clone(), *addr);
+        } else {
+            keep.entry(acc.mint.clone()).or_insert(*addr);
+        }
+    }
+    let kept = keep.len();
+    let rest = closeable
+        .into_iter()
+        .filter(|(addr, acc)| keep.get(&acc.mint) != Some(addr))
+        .collect();
+    (rest, kept)
+}
+
+/// Programs to scan for `--owner-program`
+fn owner_programs(sel: &str) -> Result<Vec<Pubkey>> {
+    let token_2022: Pubkey = crate::rpc::TOKEN_2022_PROGRAM_ID.parse().unwrap();
+    Ok(match sel {
+        "token" => vec![spl_token::id()],
+        "token2022" => vec![token_2022],
+        "both" => vec![spl_token::id(), token_2022],
+        _ => anyhow::bail!("Unknown owner program: {sel} (use token, token2022 or both)"),
+    })
+}
+
+/// CloseAccount instruction, same layout for SPL Token and Token-2022
+/// (spl_token's builder rejects any other program id)
+fn close_account_ix(program_id: &Pubkey, account: &Pubkey, wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*wallet, false),
+            AccountMeta::new_readonly(*wallet, true),
+        ],
+        data: vec![9], // TokenInstruction::CloseAccount
+    }
+}
+
+/// Burn instruction, built by hand for the same reason as `close_account_ix`
+fn burn_ix(
+    program_id: &Pubkey,
+    account: &Pubkey,
+    mint: &Pubkey,
+    wallet: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let mut data = vec![8]; // TokenInstruction::Burn
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*mint, false),
+            AccountMeta::new_readonly(*wallet, true),
+        ],
+        data,
+    }
+}
+
+/// Drop accounts that still hold tokens: CloseAccount rejects a non-zero
+/// balance, so without a Burn first they would only fail the whole batch
+fn without_balance(closeable: Vec<(Pubkey, CloseableAccount)>) -> Vec<(Pubkey, CloseableAccount)> {
+    closeable
+        .into_iter()
+        .filter(|(_, c)| c.raw_amount == 0)
+        .collect()
+}
+
+/// `--multisig`: the close instructions per batch, the vault being owner and
+/// rent destination. No compute budget ixs: they can't run inside a vault
+/// transaction, the proposer's own transaction sets those.
+fn multisig_bundle(
+    closeable: &[(Pubkey, CloseableAccount)],
+    batch_size: usize,
+    vault: &Pubkey,
+) -> serde_json::Value {
+    let batches: Vec<Vec<serde_json::Value>> = closeable
+        .chunks(batch_size)
+        .map(|batch| {
+            close_ixs(batch, vault)
+                .iter()
+                .map(instruction_json)
+                .collect()
+        })
+        .collect();
+    serde_json::json!({
+        "vault": vault.to_string(),
+        "accounts": closeable.len(),
+        "reclaim_lamports": closeable.iter().map(|(_, c)| c.rent_lamports).sum::<u64>(),
+        "batches": batches,
+    })
+}
 
-        for sig in &sigs {
-            println!("     https://solscan.io/tx/{}", sig.dimmed());
-        }
-        println!();
-    }
+/// Instruction with account metas and base64 data, as multisig tools import it
+fn instruction_json(ix: &Instruction) -> serde_json::Value {
+    let accounts: Vec<serde_json::Value> = ix
+        .accounts
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "pubkey": m.pubkey.to_string(),
+                "is_signer": m.is_signer,
+                "is_writable": m.is_writable,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "program_id": ix.program_id.to_string(),
+        "accounts": accounts,
+        "data": STANDARD.encode(&ix.data),
+    })
+}
 
-    Ok(())
+/// Drop accounts of `--ignore-mints` mints (WSOL by default)
+fn without_ignored(
+    closeable: Vec<(Pubkey, CloseableAccount)>,
+    ignore: &HashSet<Pubkey>,
+) -> Vec<(Pubkey, CloseableAccount)> {
+    closeable
+        .into_iter()
+        .filter(|(_, c)| !c.mint.parse().is_ok_and(|m: Pubkey| ignore.contains(&m)))
+        .collect()
 }
 
-async fn fetch_and_analyze(
-    rpc_url: &str,
+/// Compute units for one tx closing `accounts` (burns cost extra)
+fn close_cu_limit(accounts: &[(Pubkey, CloseableAccount)]) -> u32 {
+    let burns = accounts.iter().filter(|(_, a)| a.raw_amount > 0).count();
+    (accounts.len() + burns) as u32 * 3000 + 5000
+}
+
+/// Compute budget ixs followed by `close_ixs`
+fn build_close_batch_ixs(
+    accounts: &[(Pubkey, CloseableAccount)],
     wallet: &Pubkey,
-    dust_threshold: Option<f64>,
-) -> Result<Vec<(Pubkey, CloseableAccount)>> {
-    let rpc_url = rpc_url.to_string();
-    let wallet = *wallet;
-
-    let accounts = tokio::task::spawn_blocking(move || {
-        let client = crate::rpc::client(&rpc_url);
-        let config = RpcProgramAccountsConfig {
-            filters: Some(vec![
-                RpcFilterType::DataSize(165),
-                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, wallet.to_bytes().to_vec())),
-            ]),
-            account_config: RpcAccountInfoConfig {
-                encoding: Some(UiAccountEncoding::Base64),
-                commitment: Some(CommitmentConfig::confirmed()),
-                ..Default::default()
-            },
-            ..Default::default()
-        };
-        client.get_program_accounts_with_config(&spl_token::id(), config)
-    })
-    .await?
-    .context("Failed to fetch accounts")?;
+    cu_price: u64,
+    cu_limit: u32,
+) -> Vec<Instruction> {
+    let mut ixs = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(cu_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(cu_price),
+    ];
+    ixs.extend(close_ixs(accounts, wallet));
+    ixs
+}
 
-    let dust_lamports = dust_threshold.map(|d| (d * 1e9) as u64).unwrap_or(0);
-    // Use the pure logic filter
-    // Transform Account to (Pubkey, Account) for the filter
-    let accounts_with_pubkey: Vec<(Pubkey, solana_sdk::account::Account)> = accounts;
-    Ok(filter_closeable_accounts(
-        accounts_with_pubkey,
-        dust_lamports,
-    ))
+/// One CloseAccount per account, each under its own token program.
+/// Accounts with a balance (`--burn`) get a Burn of the whole balance first.
+fn close_ixs(accounts: &[(Pubkey, CloseableAccount)], wallet: &Pubkey) -> Vec<Instruction> {
+    let mut ixs = Vec::new();
+    for (addr, acc) in accounts {
+        if acc.raw_amount > 0 {
+            let mint: Pubkey = acc.mint.parse().unwrap();
+            ixs.push(burn_ix(
+                &acc.program_id,
+                addr,
+                &mint,
+                wallet,
+                acc.raw_amount,
+            ));
+        }
+        ixs.push(close_account_ix(&acc.program_id, addr, wallet));
+    }
+    ixs
 }
 
-/// Pure logic: Filter accounts that should be closed
+/// Pure logic: Filter accounts that should be closed.
+/// Only reads the first `token::TOKEN_FIELDS_LEN` bytes, which is all
+/// `rpc::get_token_accounts` fetches.
 fn filter_closeable_accounts(
     accounts: Vec<(Pubkey, solana_sdk::account::Account)>,
+    program_id: &Pubkey,
     dust_lamports: u64,
 ) -> Vec<(Pubkey, CloseableAccount)> {
     let mut closeable = Vec::new();
 
     for (addr, acc) in accounts {
-        let data = &acc.data;
-        if data.len() < 72 {
+        let Some(tok) = token::parse_account(&acc) else {
             continue;
-        }
-
-        let amount = u64::from_le_bytes(data[64..72].try_into().unwrap_or([0u8; 8]));
-        let mint = Pubkey::try_from(&data[0..32]).unwrap_or_default();
+        };
+        let amount = tok.amount;
 
         let is_empty = amount == 0;
         let is_dust = dust_lamports > 0 && amount > 0 && amount <= dust_lamports;
 
-        if is_empty || is_dust {
-            // Check delegate (u32 at offset 72)
-            let has_delegate = data.len() > 76
-                && u32::from_le_bytes(data[72..76].try_into().unwrap_or([0u8; 4])) == 1;
-
-            // Check frozen (u8 at offset 108)
-            let is_frozen = data.len() > 108 && data[108] == 2;
-
-            if !has_delegate && !is_frozen {
-                closeable.push((
-                    addr,
-                    CloseableAccount {
-                        address: addr.to_string(),
-                        mint: mint.to_string(),
-                        token_balance: utils::token_amount(amount, 9),
-                        rent_lamports: acc.lamports,
-                    },
-                ));
-            }
+        // never touch delegated or frozen accounts
+        if (is_empty || is_dust) && !tok.delegated && !tok.frozen {
+            closeable.push((
+                addr,
+                CloseableAccount {
+                    address: addr.to_string(),
+                    mint: tok.mint.to_string(),
+                    // raw until `set_token_balances` knows the decimals
+                    token_balance: amount as f64,
+                    rent_lamports: acc.lamports,
+                    program_id: *program_id,
+                    raw_amount: amount,
+                },
+            ));
         }
     }
     closeable
 }
 
+/// `pubkey,privatekey` columns of a batch CSV line; a dry run signs
+/// nothing, so there a lone public key is enough
+fn csv_fields(line: &str, dry_run: bool) -> Option<(&str, Option<&str>)> {
+    match line.split(',').map(str::trim).collect::<Vec<_>>()[..] {
+        [pk, sk] => Some((pk, Some(sk))),
+        [pk] if dry_run => Some((pk, None)),
+        _ => None,
+    }
+}
+
+/// RPC endpoints for batch mode: the pool if given, else the main RPC
+fn parse_rpc_pool(rpc_url: &str, pool: Option<&str>) -> Vec<String> {
+    let urls: Vec<String> = pool
+        .unwrap_or_default()
+        .split(',')
+        .map(|u| u.trim())
+        .filter(|u| !u.is_empty())
+        .map(String::from)
+        .collect();
+
+    if urls.is_empty() {
+        vec![rpc_url.to_string()]
+    } else {
+        urls
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -481,12 +1362,26 @@ mod tests {
         }
     }
 
+    /// SPL close candidate; `token_balance` stays raw, as before decimals
+    fn closeable(mint: &str, raw: u64, rent: u64) -> (Pubkey, CloseableAccount) {
+        let addr = Pubkey::new_unique();
+        let acc = CloseableAccount {
+            address: addr.to_string(),
+            mint: mint.to_string(),
+            token_balance: raw as f64,
+            rent_lamports: rent,
+            program_id: spl_token::id(),
+            raw_amount: raw,
+        };
+        (addr, acc)
+    }
+
     #[test]
     fn test_filter_empty_accounts() {
         let addr = Pubkey::new_unique();
         let acc = create_token_account(0, None, 1); // Empty, No delegate, Initialized
 
-        let candidates = filter_closeable_accounts(vec![(addr, acc)], 0);
+        let candidates = filter_closeable_accounts(vec![(addr, acc)], &spl_token::id(), 0);
         assert_eq!(candidates.len(), 1);
         assert_eq!(candidates[0].0, addr);
     }
@@ -496,7 +1391,7 @@ mod tests {
         let addr = Pubkey::new_unique();
         let acc = create_token_account(100, None, 1); // Balance 100
 
-        let candidates = filter_closeable_accounts(vec![(addr, acc)], 0);
+        let candidates = filter_closeable_accounts(vec![(addr, acc)], &spl_token::id(), 0);
         assert!(candidates.is_empty(), "Should not close non-empty account");
     }
 
@@ -506,12 +1401,12 @@ mod tests {
         let acc = create_token_account(100, None, 1); // Balance 100
 
         // Dust threshold 200 > 100 -> Should close
-        let candidates = filter_closeable_accounts(vec![(addr, acc)], 200);
+        let candidates = filter_closeable_accounts(vec![(addr, acc)], &spl_token::id(), 200);
         assert_eq!(candidates.len(), 1, "Should close dust account");
 
         // Dust threshold 50 < 100 -> Keep
         let acc2 = create_token_account(100, None, 1);
-        let candidates2 = filter_closeable_accounts(vec![(addr, acc2)], 50);
+        let candidates2 = filter_closeable_accounts(vec![(addr, acc2)], &spl_token::id(), 50);
         assert!(
             candidates2.is_empty(),
             "Should keep account above dust threshold"
@@ -523,7 +1418,7 @@ mod tests {
         let addr = Pubkey::new_unique();
         let acc = create_token_account(0, None, 2); // Empty but Frozen (state=2)
 
-        let candidates = filter_closeable_accounts(vec![(addr, acc)], 0);
+        let candidates = filter_closeable_accounts(vec![(addr, acc)], &spl_token::id(), 0);
         assert!(candidates.is_empty(), "Must NOT close frozen accounts");
     }
 
@@ -533,10 +1428,362 @@ mod tests {
         let delegate = Pubkey::new_unique();
         let acc = create_token_account(0, Some(delegate), 1); // Delegated
 
-        let candidates = filter_closeable_accounts(vec![(addr, acc)], 0);
+        let candidates = filter_closeable_accounts(vec![(addr, acc)], &spl_token::id(), 0);
         assert!(candidates.is_empty()); // Should be filtered out
     }
 
+    #[test]
+    fn test_filter_sliced_accounts() {
+        // what the dataSlice fetch returns: mint through the state byte
+        let sliced = |amount, delegate, state| {
+            let mut acc = create_token_account(amount, delegate, state);
+            acc.data.truncate(token::TOKEN_FIELDS_LEN);
+            (Pubkey::new_unique(), acc)
+        };
+        let empty = sliced(0, None, 1);
+        let dust = sliced(100, None, 1);
+        let accounts = vec![
+            empty.clone(),
+            dust.clone(),
+            sliced(0, None, 2),
+            sliced(0, Some(Pubkey::new_unique()), 1),
+            sliced(5_000, None, 1),
+        ];
+
+        let candidates = filter_closeable_accounts(accounts, &spl_token::id(), 200);
+        let addrs: Vec<Pubkey> = candidates.iter().map(|(a, _)| *a).collect();
+        assert_eq!(addrs, vec![empty.0, dust.0]);
+        assert_eq!(candidates[1].1.raw_amount, 100);
+        assert_eq!(candidates[0].1.rent_lamports, 2_039_280);
+    }
+
+    #[test]
+    fn test_keep_one_each() {
+        let wallet = Pubkey::new_unique();
+        let acc = |mint: &str| closeable(mint, 0, 2_039_280);
+        let closeable = vec![acc("A"), acc("B"), acc("A"), acc("A")];
+        let second_a = closeable[2].0;
+
+        let (rest, kept) = keep_one_each(closeable.clone(), &wallet, &HashSet::new());
+        assert_eq!(kept, 2);
+        assert_eq!(rest.len(), 2);
+        assert!(rest.iter().all(|(_, a)| a.mint == "A"));
+        assert_eq!(rest[0].0, second_a);
+
+        // B already has a funded account: nothing kept for it
+        let open = HashSet::from(["B".to_string()]);
+        let (rest, kept) = keep_one_each(closeable, &wallet, &open);
+        assert_eq!(kept, 1);
+        assert_eq!(rest.len(), 3);
+        assert!(rest.iter().any(|(_, a)| a.mint == "B"));
+    }
+
+    #[test]
+    fn test_keep_one_each_prefers_ata() {
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let ata = spl_associated_token_account::get_associated_token_address(&wallet, &mint);
+        let stray = closeable(&mint.to_string(), 0, 2_039_280);
+        let (_, mut canonical) = closeable(&mint.to_string(), 0, 2_039_280);
+        canonical.address = ata.to_string();
+
+        let (rest, kept) = keep_one_each(
+            vec![stray.clone(), (ata, canonical)],
+            &wallet,
+            &HashSet::new(),
+        );
+        assert_eq!(kept, 1);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].0, stray.0);
+    }
+
+    #[test]
+    fn test_confirm_prompt() {
+        let p = confirm_prompt(3, 6_117_840, 15_000);
+        assert!(p.starts_with("Close 3 accounts, reclaim "));
+        assert!(p.contains(&format!(
+            "net ~{}",
+            utils::format_sol(utils::lamports_to_sol(6_102_840))
+        )));
+        assert!(p.contains(&format!(
+            "after {} fees",
+            utils::format_sol(utils::lamports_to_sol(15_000))
+        )));
+
+        // fees above the rent show as a loss, not zero
+        assert!(confirm_prompt(1, 5_000, 15_000).contains("net ~-0.000010000 SOL"));
+        assert_eq!(net_sol(net_lamports(5_000, 15_000)), -0.00001);
+    }
+
+    #[test]
+    fn test_verify_line() {
+        assert!(verify_line(2_000_000, 2_000_000).contains("as expected"));
+
+        let line = verify_line(2_000_000, 1_995_000);
+        assert!(line.contains("expected +"));
+        assert!(line.contains("-0.000005"));
+    }
+
+    #[test]
+    fn test_preview_len() {
+        assert_eq!(preview_len(30, 12), 12);
+        assert_eq!(preview_len(5, 12), 5);
+        assert_eq!(preview_len(30, 0), 30);
+    }
+
+    #[test]
+    fn test_worth_less_than_rent() {
+        // 0.002 SOL rent at $100 = $0.20
+        let rent = 2_000_000;
+        assert!(worth_less_than_rent(1.0, Some(0.1), rent, Some(100.0)));
+        assert!(!worth_less_than_rent(1.0, Some(0.5), rent, Some(100.0)));
+
+        // unknown prices never qualify
+        assert!(!worth_less_than_rent(1.0, None, rent, Some(100.0)));
+        assert!(!worth_less_than_rent(1.0, Some(0.1), rent, None));
+    }
+
+    #[test]
+    fn test_qr_label() {
+        assert_eq!(qr_label(0, 1), "");
+        assert_eq!(qr_label(0, 3), " (1 of 3)");
+        assert_eq!(qr_label(2, 3), " (3 of 3)");
+    }
+
+    #[test]
+    fn test_below_supply_pct() {
+        // 0.0001% of 1e15 raw = 1e9
+        let supply = 1_000_000_000_000_000;
+        assert!(below_supply_pct(999_999_999, supply, 0.0001));
+        assert!(!below_supply_pct(1_000_000_000, supply, 0.0001));
+        assert!(below_supply_pct(40, 100, 50.0));
+
+        // a zero supply never qualifies
+        assert!(!below_supply_pct(0, 0, 1.0));
+    }
+
+    #[test]
+    fn test_within_dust() {
+        // 0.001 USDC, not 1000 raw units
+        assert!(within_dust(1_000, 6, 0.001));
+        assert!(!within_dust(1_000_000, 6, 0.001));
+        // a single NFT is one whole token
+        assert!(!within_dust(1, 0, 0.001));
+
+        assert!(safe_to_burn(Some(6)));
+        assert!(!safe_to_burn(Some(0)));
+        assert!(!safe_to_burn(None));
+    }
+
+    #[test]
+    fn test_close_account_ix_matches_spl_token() {
+        let account = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+
+        let ours = close_account_ix(&spl_token::id(), &account, &wallet);
+        let theirs = spl_token::instruction::close_account(
+            &spl_token::id(),
+            &account,
+            &wallet,
+            &wallet,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(ours, theirs);
+
+        let t22: Pubkey = crate::rpc::TOKEN_2022_PROGRAM_ID.parse().unwrap();
+        assert_eq!(close_account_ix(&t22, &account, &wallet).program_id, t22);
+    }
+
+    #[test]
+    fn test_build_close_batch_ixs() {
+        let wallet = Pubkey::new_unique();
+        let t22: Pubkey = crate::rpc::TOKEN_2022_PROGRAM_ID.parse().unwrap();
+        let accounts: Vec<(Pubkey, CloseableAccount)> = [spl_token::id(), t22, spl_token::id()]
+            .into_iter()
+            .map(|program_id| {
+                let (addr, mut acc) = closeable(&Pubkey::new_unique().to_string(), 0, 2_039_280);
+                acc.program_id = program_id;
+                (addr, acc)
+            })
+            .collect();
+
+        let ixs = build_close_batch_ixs(&accounts, &wallet, 1000, close_cu_limit(&accounts));
+        assert_eq!(ixs.len(), 2 + accounts.len());
+        assert_eq!(
+            ixs[0],
+            ComputeBudgetInstruction::set_compute_unit_limit(3 * 3000 + 5000)
+        );
+        assert_eq!(
+            ixs[1],
+            ComputeBudgetInstruction::set_compute_unit_price(1000)
+        );
+        for (ix, (addr, acc)) in ixs[2..].iter().zip(&accounts) {
+            assert_eq!(ix.program_id, acc.program_id);
+            assert_eq!(ix.accounts[0].pubkey, *addr);
+        }
+
+        assert_eq!(
+            build_close_batch_ixs(&[], &wallet, 0, close_cu_limit(&[])).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_valuable_dust() {
+        let acc = |mint: &str, raw: u64| closeable(mint, raw, 2_039_280);
+        let closeable = vec![acc("USD", 50), acc("USD", 5), acc("NOPRICE", 1_000_000)];
+        let price = |m: &str| (m == "USD").then_some(0.1);
+
+        let valuable = valuable_dust(&closeable, price, 1.0);
+        assert_eq!(valuable.len(), 2);
+        assert_eq!(valuable[0].1, Some(5.0));
+        // no price can't be checked, so it's refused too
+        assert_eq!(valuable[1].0.mint, "NOPRICE");
+        assert_eq!(valuable[1].1, None);
+
+        let valuable = valuable_dust(&closeable, price, 10.0);
+        assert_eq!(valuable.len(), 1);
+        assert_eq!(valuable[0].1, None);
+    }
+
+    #[test]
+    fn test_group_by_mint() {
+        let acc = |mint: &str, rent: u64| closeable(mint, 0, rent);
+        let closeable = vec![acc("B", 10), acc("A", 5), acc("B", 10), acc("C", 50)];
+        assert_eq!(
+            group_by_mint(&closeable),
+            vec![
+                ("B".to_string(), 2, 20),
+                ("C".to_string(), 1, 50),
+                ("A".to_string(), 1, 5),
+            ]
+        );
+
+        let usdc = token::KNOWN_MINTS[0].1;
+        assert_eq!(mint_label(usdc), "USDC");
+    }
+
+    #[test]
+    fn test_without_ignored() {
+        let wsol = token::DEFAULT_IGNORED_MINTS[0];
+        let acc = |mint: &str| closeable(mint, 0, 2_039_280);
+        let other = Pubkey::new_unique().to_string();
+        let closeable = vec![acc(wsol), acc(&other)];
+
+        let ignore = token::ignored_mints(None).unwrap();
+        let rest = without_ignored(closeable.clone(), &ignore);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].1.mint, other);
+
+        assert_eq!(without_ignored(closeable, &HashSet::new()).len(), 2);
+    }
+
+    #[test]
+    fn test_dust_not_closed_without_burn() {
+        let accounts = vec![
+            (Pubkey::new_unique(), create_token_account(0, None, 1)),
+            (Pubkey::new_unique(), create_token_account(500, None, 1)),
+        ];
+
+        // --dust marks both closeable ...
+        let closeable = filter_closeable_accounts(accounts, &spl_token::id(), 1000);
+        assert_eq!(closeable.len(), 2);
+
+        // ... but only the empty one can be closed without --burn
+        let kept = without_balance(closeable.clone());
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].1.raw_amount, 0);
+
+        // with --burn the dust account gets Burn + CloseAccount
+        let wallet = Pubkey::new_unique();
+        let ixs = build_close_batch_ixs(&closeable, &wallet, 0, close_cu_limit(&closeable));
+        let tags: Vec<u8> = ixs[2..].iter().map(|ix| ix.data[0]).collect();
+        assert_eq!(tags, vec![9, 8, 9]);
+    }
+
+    #[test]
+    fn test_build_close_batch_ixs_burns_dust() {
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let dust = closeable(&mint.to_string(), 42, 2_039_280);
+        let addr = dust.0;
+        let accounts = vec![dust];
+
+        let ixs = build_close_batch_ixs(&accounts, &wallet, 0, close_cu_limit(&accounts));
+        assert_eq!(ixs.len(), 4);
+        assert_eq!(
+            ixs[0],
+            ComputeBudgetInstruction::set_compute_unit_limit(2 * 3000 + 5000)
+        );
+
+        let theirs =
+            spl_token::instruction::burn(&spl_token::id(), &addr, &mint, &wallet, &[], 42).unwrap();
+        assert_eq!(ixs[2], theirs);
+        assert_eq!(ixs[3].data, vec![9]);
+    }
+
+    #[test]
+    fn test_multisig_bundle() {
+        let vault = Pubkey::new_unique();
+        let acc = |raw| closeable(&Pubkey::new_unique().to_string(), raw, 2_039_280);
+        let closeable = vec![acc(0), acc(7), acc(0)];
+
+        let bundle = multisig_bundle(&closeable, 2, &vault);
+        assert_eq!(bundle["vault"], vault.to_string());
+        assert_eq!(bundle["accounts"], 3);
+        assert_eq!(bundle["reclaim_lamports"], 3 * 2_039_280u64);
+
+        // burn + close, close; no compute budget ixs
+        let batches = bundle["batches"].as_array().unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].as_array().unwrap().len(), 3);
+        assert_eq!(batches[1].as_array().unwrap().len(), 1);
+
+        let close = &batches[1][0];
+        assert_eq!(close["program_id"], spl_token::id().to_string());
+        assert_eq!(close["data"], "CQ=="); // [9], CloseAccount
+        let owner = &close["accounts"][2];
+        assert_eq!(owner["pubkey"], vault.to_string());
+        assert_eq!(owner["is_signer"], true);
+        assert_eq!(owner["is_writable"], false);
+    }
+
+    #[test]
+    fn test_priority_fee_parse() {
+        assert_eq!(PriorityFee::parse("auto").unwrap(), PriorityFee::Auto);
+        assert_eq!(PriorityFee::parse("AUTO").unwrap(), PriorityFee::Auto);
+        assert_eq!(
+            PriorityFee::parse("5000").unwrap(),
+            PriorityFee::Fixed(5000)
+        );
+        assert!(PriorityFee::parse("fast").is_err());
+        assert!(PriorityFee::parse("-1").is_err());
+    }
+
+    #[test]
+    fn test_owner_programs() {
+        assert_eq!(owner_programs("token").unwrap(), vec![spl_token::id()]);
+        assert_eq!(owner_programs("both").unwrap().len(), 2);
+        assert_eq!(
+            owner_programs("token2022").unwrap()[0].to_string(),
+            crate::rpc::TOKEN_2022_PROGRAM_ID
+        );
+        assert!(owner_programs("nft").is_err());
+    }
+
+    #[test]
+    fn test_parse_rpc_pool() {
+        let main = "https://main.rpc";
+
+        assert_eq!(parse_rpc_pool(main, None), vec![main]);
+        assert_eq!(parse_rpc_pool(main, Some(" , ")), vec![main]);
+        assert_eq!(
+            parse_rpc_pool(main, Some("https://a.rpc, https://b.rpc,")),
+            vec!["https://a.rpc", "https://b.rpc"]
+        );
+    }
+
     /// Test CSV line parsing logic (simulates batch mode parsing)
     #[test]
     fn test_csv_line_parsing() {
@@ -564,6 +1811,18 @@ mod tests {
         assert_eq!(keypair.unwrap().pubkey(), pubkey.unwrap());
     }
 
+    #[test]
+    fn test_csv_fields() {
+        assert_eq!(csv_fields("pk, sk", false), Some(("pk", Some("sk"))));
+        assert_eq!(csv_fields("pk,sk", true), Some(("pk", Some("sk"))));
+
+        // public key only: fine for a dry run, not for execution
+        assert_eq!(csv_fields("pk", true), Some(("pk", None)));
+        assert_eq!(csv_fields("pk", false), None);
+
+        assert_eq!(csv_fields("a,b,c", true), None);
+    }
+
     #[test]
     fn test_csv_skip_header_and_comments() {
         let lines = vec![
@@ -595,15 +1854,35 @@ mod tests {
     }
 }
 
+/// Outcome of one wallet in batch mode
+#[derive(Default)]
+struct WalletOutcome {
+    idx: usize,
+    wallet: Pubkey,
+    closed: usize,
+    reclaimed: u64,
+    fees: u64,
+    success: bool,
+    /// Not started (or stopped early) because of `--fail-fast`
+    skipped: bool,
+}
+
 /// Batch mode: process multiple wallets from CSV file
 /// Format: public_key,private_key (one per line)
+#[allow(clippy::too_many_arguments)]
 async fn run_batch(
-    rpc_url: &str,
+    rpc_pool: &[String],
+    programs: &[Pubkey],
     file_path: &str,
     dry_run: bool,
     batch_size: usize,
-    dust_threshold: Option<f64>,
-    _json: bool,
+    dust: DustRules,
+    cache: Arc<MintInfoCache>,
+    priority_fee: PriorityFee,
+    keep_one_per_mint: bool,
+    fail_fast: bool,
+    ignore: &HashSet<Pubkey>,
+    json: bool,
 ) -> Result<()> {
     use std::io::BufRead;
 
@@ -611,10 +1890,11 @@ async fn run_batch(
         std::fs::File::open(file_path).context(format!("Failed to open file: {}", file_path))?;
     let reader = std::io::BufReader::new(file);
 
-    let mut wallets: Vec<(Pubkey, Keypair)> = Vec::new();
+    let mut wallets: Vec<(Pubkey, Option<Keypair>)> = Vec::new();
 
     for (line_num, line) in reader.lines().enumerate() {
-        let line = line?;
+        // lines carry private keys, wipe each one once parsed
+        let line = Zeroizing::new(line?);
         let line = line.trim();
 
         // Skip empty lines and comments
@@ -622,36 +1902,39 @@ async fn run_batch(
             continue;
         }
 
-        let parts: Vec<&str> = line.split(',').collect();
-        if parts.len() != 2 {
+        let Some((pubkey_str, privkey_str)) = csv_fields(line, dry_run) else {
+            let expected = if dry_run {
+                "pubkey[,privatekey]"
+            } else {
+                "pubkey,privatekey"
+            };
             eprintln!(
                 "{}",
                 format!(
-                    "⚠ Line {}: invalid format (expected: pubkey,privatekey)",
+                    "⚠ Line {}: invalid format (expected: {expected})",
                     line_num + 1
                 )
                 .yellow()
             );
             continue;
-        }
-
-        let pubkey_str = parts[0].trim();
-        let privkey_str = parts[1].trim();
+        };
 
-        // Parse pubkey
-        let pubkey: Pubkey = match pubkey_str.parse() {
+        // Parse pubkey (explorer URLs and `solana:` URIs too)
+        let pubkey = match utils::parse_pubkey(pubkey_str) {
             Ok(pk) => pk,
-            Err(_) => {
-                eprintln!(
-                    "{}",
-                    format!("⚠ Line {}: invalid public key", line_num + 1).yellow()
-                );
+            Err(e) => {
+                eprintln!("{}", format!("⚠ Line {}: {e}", line_num + 1).yellow());
                 continue;
             }
         };
 
+        let Some(privkey_str) = privkey_str else {
+            wallets.push((pubkey, None));
+            continue;
+        };
+
         // Parse private key (base58)
-        let keypair = match bs58::decode(privkey_str).into_vec() {
+        let keypair = match bs58::decode(privkey_str).into_vec().map(Zeroizing::new) {
             Ok(bytes) => match Keypair::try_from(bytes.as_slice()) {
                 Ok(kp) => kp,
                 Err(_) => {
@@ -680,7 +1963,7 @@ async fn run_batch(
             continue;
         }
 
-        wallets.push((pubkey, keypair));
+        wallets.push((pubkey, Some(keypair)));
     }
 
     if wallets.is_empty() {
@@ -688,94 +1971,129 @@ async fn run_batch(
     }
 
     let wallets_count = wallets.len();
-    println!(
-        "\n{} Processing {} wallets from {} (parallel)\n",
-        "📁".bold(),
-        wallets.len().to_string().cyan(),
-        file_path.dimmed()
-    );
+    if !json {
+        println!(
+            "\n{} Processing {} wallets from {} (parallel)\n",
+            "📁".bold(),
+            wallets.len().to_string().cyan(),
+            file_path.dimmed()
+        );
+    }
+
+    if rpc_pool.len() > 1 && !json {
+        println!(
+            "  {} Spreading load over {} RPC endpoints\n",
+            "ℹ".dimmed(),
+            rpc_pool.len()
+        );
+    }
 
-    let rpc_url_arc = std::sync::Arc::new(rpc_url.to_string());
-    let sol_usd = crate::price::sol_price().await.unwrap_or(0.0);
+    let sol_usd = crate::price::sol_price().await;
 
     // Process wallets in parallel with semaphore for rate limiting
     let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(10)); // 10 concurrent
 
+    // set by the first failure under --fail-fast, checked by every task
+    let cancel = Arc::new(AtomicBool::new(false));
+
     let mut handles = Vec::new();
 
     for (idx, (wallet, keypair)) in wallets.into_iter().enumerate() {
         let sem = semaphore.clone();
-        let rpc = rpc_url_arc.clone();
+        // round-robin over the pool
+        let rpc = rpc_pool[idx % rpc_pool.len()].clone();
+        let programs = programs.to_vec();
+        let ignore = ignore.clone();
+        let cache = cache.clone();
+        let cancel = cancel.clone();
 
         let handle = tokio::spawn(async move {
             let _permit = sem.acquire().await.unwrap();
 
             let client = crate::rpc::client(&rpc);
 
-            // Fetch token accounts
-            let config = RpcProgramAccountsConfig {
-                filters: Some(vec![
-                    RpcFilterType::DataSize(165),
-                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, wallet.to_bytes().to_vec())),
-                ]),
-                account_config: RpcAccountInfoConfig {
-                    encoding: Some(UiAccountEncoding::Base64),
-                    commitment: Some(CommitmentConfig::confirmed()),
-                    ..Default::default()
-                },
+            let mut out = WalletOutcome {
+                idx,
+                wallet,
+                success: true,
                 ..Default::default()
             };
-
-            let accounts = match client.get_program_accounts_with_config(&spl_token::id(), config) {
-                Ok(acc) => acc,
-                Err(_) => {
-                    return (idx, wallet, 0usize, 0u64, false);
+            if cancel.load(Ordering::Relaxed) {
+                out.skipped = true;
+                return out;
+            }
+            let fail = |out: &mut WalletOutcome| {
+                out.success = false;
+                if fail_fast {
+                    cancel.store(true, Ordering::Relaxed);
                 }
             };
 
             // Find closeable accounts (using shared logic)
-            let dust_lamports = dust_threshold.map(|d| (d * 1e9) as u64).unwrap_or(0);
-
-            // filter_closeable_accounts expects Vec<(Pubkey, Account)>
-            // get_program_accounts returns Vec<(Pubkey, Account)>
-            let candidates = filter_closeable_accounts(accounts, dust_lamports);
+            let candidates = match fetch_and_analyze(
+                &rpc,
+                &wallet,
+                &programs,
+                dust,
+                &ignore,
+                &cache,
+                keep_one_per_mint,
+            )
+            .await
+            {
+                Ok((c, _)) => c,
+                Err(e) => {
+                    if !json {
+                        eprintln!("  {} {}: {e}", "⚠".yellow(), utils::short_key(&wallet));
+                    }
+                    fail(&mut out);
+                    return out;
+                }
+            };
 
             if candidates.is_empty() {
-                return (idx, wallet, 0, 0, true);
+                return out;
             }
 
-            let rent_total: u64 = candidates.iter().map(|(_, c)| c.rent_lamports).sum();
-
             if dry_run {
-                return (idx, wallet, candidates.len(), rent_total, true);
+                out.closed = candidates.len();
+                out.reclaimed = candidates.iter().map(|(_, c)| c.rent_lamports).sum();
+                return out;
             }
 
+            // only dry runs accept lines without a private key
+            let Some(keypair) = keypair else {
+                out.success = false;
+                return out;
+            };
+
             // Close accounts
             let closeable = candidates; // alias for clarity
 
             // Fetch blockhash or skip if fails
             let recent_hash = match client.get_latest_blockhash() {
                 Ok(h) => h,
-                Err(_) => return (idx, wallet, closeable.len(), 0, false),
+                Err(_) => {
+                    fail(&mut out);
+                    return out;
+                }
             };
 
-            let mut closed = 0usize;
-            let mut reclaimed = 0u64;
-
             for chunk in closeable.chunks(batch_size) {
-                let mut ixs = vec![
-                    ComputeBudgetInstruction::set_compute_unit_limit(
-                        chunk.len() as u32 * 3000 + 5000,
-                    ),
-                    ComputeBudgetInstruction::set_compute_unit_price(1000),
-                ];
-
-                for (addr, _) in chunk {
-                    if let Ok(ix) = close_account(&spl_token::id(), addr, &wallet, &wallet, &[]) {
-                        ixs.push(ix);
-                    }
+                // another wallet failed, leave the rest of this one alone
+                if cancel.load(Ordering::Relaxed) {
+                    out.skipped = true;
+                    break;
                 }
 
+                let addrs: Vec<Pubkey> = chunk.iter().map(|(a, _)| *a).collect();
+                let ixs = build_close_batch_ixs(
+                    chunk,
+                    &wallet,
+                    priority_fee.price(&client, &wallet, &addrs),
+                    close_cu_limit(chunk),
+                );
+
                 let tx = Transaction::new_signed_with_payer(
                     &ixs,
                     Some(&wallet),
@@ -784,12 +2102,16 @@ async fn run_batch(
                 );
 
                 if client.send_and_confirm_transaction(&tx).is_ok() {
-                    closed += chunk.len();
-                    reclaimed += chunk.iter().map(|(_, acc)| acc.rent_lamports).sum::<u64>();
+                    out.closed += chunk.len();
+                    out.reclaimed += chunk.iter().map(|(_, acc)| acc.rent_lamports).sum::<u64>();
+                    out.fees += client.get_fee_for_message(&tx.message).unwrap_or(0);
+                } else if fail_fast {
+                    fail(&mut out);
+                    break;
                 }
             }
 
-            (idx, wallet, closed, reclaimed, true)
+            out
         });
 
         handles.push(handle);
@@ -798,7 +2120,8 @@ async fn run_batch(
     // Collect results
     let mut total_closed = 0u64;
     let mut total_reclaimed = 0u64;
-    let mut wallet_results: Vec<(usize, Pubkey, usize, u64, bool)> = Vec::new();
+    let mut total_fees = 0u64;
+    let mut wallet_results: Vec<WalletOutcome> = Vec::new();
 
     for handle in handles {
         if let Ok(result) = handle.await {
@@ -807,10 +2130,69 @@ async fn run_batch(
     }
 
     // Sort by index and print
-    wallet_results.sort_by_key(|(idx, _, _, _, _)| *idx);
+    wallet_results.sort_by_key(|r| r.idx);
+
+    if json {
+        let closed: usize = wallet_results.iter().map(|r| r.closed).sum();
+        let reclaimed: u64 = wallet_results.iter().map(|r| r.reclaimed).sum();
+        let fees: u64 = wallet_results.iter().map(|r| r.fees).sum();
+        // same keys as a single wallet: found in a dry run, done otherwise
+        let (count_key, sol_key, usd_key) = if dry_run {
+            ("closeable", "reclaimable_sol", "reclaimable_usd")
+        } else {
+            ("closed", "reclaimed_sol", "reclaimed_usd")
+        };
+        let per_wallet: Vec<serde_json::Value> = wallet_results
+            .iter()
+            .map(|r| {
+                let mut v = serde_json::json!({
+                    "wallet": r.wallet.to_string(),
+                    "success": r.success,
+                    "skipped": r.skipped,
+                });
+                v[count_key] = r.closed.into();
+                v[sol_key] = utils::lamports_to_sol(r.reclaimed).into();
+                v
+            })
+            .collect();
+        let mut out = serde_json::json!({
+            "status": if dry_run { "found" } else { "done" },
+            "dry_run": dry_run,
+            "wallets": per_wallet,
+            "total": {
+                "wallets": wallets_count,
+                "failed": wallet_results.iter().filter(|r| !r.success).count(),
+            },
+        });
+        out["total"][count_key] = closed.into();
+        out["total"][sol_key] = utils::lamports_to_sol(reclaimed).into();
+        out["total"][usd_key] =
+            serde_json::json!(sol_usd.map(|p| utils::lamports_to_sol(reclaimed) * p));
+        if !dry_run {
+            out["total"]["fees_sol"] = utils::lamports_to_sol(fees).into();
+            out["total"]["net_sol"] = net_sol(net_lamports(reclaimed, fees)).into();
+        }
+        utils::print_json("clean", out);
+        return Ok(());
+    }
 
-    for (idx, wallet, closed, reclaimed, success) in wallet_results {
-        if !success {
+    for WalletOutcome {
+        idx,
+        wallet,
+        closed,
+        reclaimed,
+        fees,
+        success,
+        skipped,
+    } in wallet_results
+    {
+        if skipped && closed == 0 {
+            println!(
+                "{} {} Skipped (--fail-fast)",
+                format!("[{}/{}]", idx + 1, wallets_count).dimmed(),
+                utils::short_key(&wallet)
+            );
+        } else if !success {
             println!(
                 "{} {} Failed",
                 format!("[{}/{}]", idx + 1, wallets_count).dimmed(),
@@ -841,6 +2223,14 @@ async fn run_batch(
         }
         total_closed += closed as u64;
         total_reclaimed += reclaimed;
+        total_fees += fees;
+    }
+
+    if cancel.load(Ordering::Relaxed) {
+        println!(
+            "\n  {} Stopped after the first failure (--fail-fast)",
+            "⚠".yellow()
+        );
     }
 
     // Summary
@@ -850,12 +2240,159 @@ async fn run_batch(
         total_closed.to_string().green().bold()
     );
     println!(
-        "  Total reclaimed: {} (≈ {})",
+        "  Total reclaimed: {} {}",
         utils::format_sol(utils::lamports_to_sol(total_reclaimed))
             .green()
             .bold(),
-        utils::format_usd(utils::lamports_to_sol(total_reclaimed) * sol_usd).dimmed()
+        utils::approx_usd(utils::lamports_to_sol(total_reclaimed), sol_usd).dimmed()
     );
+    if !dry_run {
+        print_fee_summary(total_reclaimed, total_fees);
+    }
 
     Ok(())
 }
+
+/// One unsigned close transaction per batch, paid by `wallet` (blocking).
+/// With `nonces` batch i uses the i-th one: a nonce is spent by the first
+/// transaction that uses it.
+fn unsigned_batches(
+    client: &RpcClient,
+    closeable: &[(Pubkey, CloseableAccount)],
+    batch_size: usize,
+    wallet: &Pubkey,
+    priority_fee: PriorityFee,
+    nonces: &[crate::rpc::DurableNonce],
+) -> Result<Vec<Transaction>> {
+    let batches = closeable.len().div_ceil(batch_size);
+    if !nonces.is_empty() && nonces.len() < batches {
+        anyhow::bail!(
+            "{batches} batches need {batches} nonce accounts, got {} (raise --batch or pass more to --nonce)",
+            nonces.len()
+        );
+    }
+    // unused with nonces
+    let recent_hash = if nonces.is_empty() {
+        client.get_latest_blockhash()?
+    } else {
+        Default::default()
+    };
+    let txs = closeable
+        .chunks(batch_size)
+        .enumerate()
+        .map(|(i, batch)| {
+            let addrs: Vec<Pubkey> = batch.iter().map(|(a, _)| *a).collect();
+            let ixs = build_close_batch_ixs(
+                batch,
+                wallet,
+                priority_fee.price(client, wallet, &addrs),
+                close_cu_limit(batch),
+            );
+            let (ixs, blockhash) = match nonces.get(i) {
+                Some(nonce) => (nonce.instructions(&ixs), nonce.blockhash),
+                None => (ixs, recent_hash),
+            };
+            let mut tx = Transaction::new_with_payer(&ixs, Some(wallet));
+            tx.message.recent_blockhash = blockhash;
+            tx
+        })
+        .collect();
+    Ok(txs)
+}
+
+/// Network fees for closing `closeable` in `batch_size` chunks, from the
+/// unsigned batch messages (blocking)
+fn estimate_fees(
+    client: &RpcClient,
+    closeable: &[(Pubkey, CloseableAccount)],
+    batch_size: usize,
+    wallet: &Pubkey,
+    priority_fee: PriorityFee,
+) -> Result<u64> {
+    let lh = client.get_latest_blockhash()?;
+    let mut fees = 0;
+    for batch in closeable.chunks(batch_size) {
+        let addrs: Vec<Pubkey> = batch.iter().map(|(a, _)| *a).collect();
+        let ixs = build_close_batch_ixs(
+            batch,
+            wallet,
+            priority_fee.price(client, wallet, &addrs),
+            close_cu_limit(batch),
+        );
+        let msg = Message::new_with_blockhash(&ixs, Some(wallet), &lh);
+        fees += client.get_fee_for_message(&msg).unwrap_or(0);
+    }
+    Ok(fees)
+}
+
+/// Keypair-mode go/no-go question, gross and net of fees
+fn confirm_prompt(accounts: usize, rent: u64, fees: u64) -> String {
+    format!(
+        "Close {accounts} accounts, reclaim {} (net ~{} after {} fees)?",
+        utils::format_sol(utils::lamports_to_sol(rent)),
+        format_net(net_lamports(rent, fees)),
+        utils::format_sol(utils::lamports_to_sol(fees))
+    )
+}
+
+/// `--verify`: observed balance change next to the expected net
+fn verify_line(expected: i64, observed: i64) -> String {
+    let sol = |l: i64| utils::lamports_to_sol(l.unsigned_abs());
+    let sign = |l: i64| if l < 0 { "-" } else { "+" };
+    if observed == expected {
+        return format!(
+            "{} Balance changed by {}{}, as expected",
+            "✓".green(),
+            sign(observed),
+            utils::format_sol(sol(observed))
+        );
+    }
+    let diff = observed - expected;
+    format!(
+        "{} Balance changed by {}{}, expected {}{} ({}{} off, other activity or extra fees?)",
+        "⚠".yellow(),
+        sign(observed),
+        utils::format_sol(sol(observed)),
+        sign(expected),
+        utils::format_sol(sol(expected)),
+        sign(diff),
+        utils::format_sol(sol(diff))
+    )
+}
+
+/// Reclaimed minus fees, negative when the fees outweigh the rent
+fn net_lamports(reclaimed: u64, fees: u64) -> i64 {
+    reclaimed as i64 - fees as i64
+}
+
+fn net_sol(net: i64) -> f64 {
+    utils::lamports_to_sol(net.unsigned_abs()) * net.signum() as f64
+}
+
+/// `format_sol` with a leading `-` for a loss
+fn format_net(net: i64) -> String {
+    let sol = utils::format_sol(utils::lamports_to_sol(net.unsigned_abs()));
+    if net < 0 {
+        format!("-{sol}")
+    } else {
+        sol
+    }
+}
+
+fn print_fee_summary(reclaimed: u64, fees: u64) {
+    println!(
+        "  Total fees: {}",
+        utils::format_sol(utils::lamports_to_sol(fees)).dimmed()
+    );
+    let net = net_lamports(reclaimed, fees);
+    let net_str = format_net(net);
+    println!(
+        "  Net reclaimed: {}",
+        if net < 0 {
+            net_str.red()
+        } else {
+            net_str.green()
+        }
+        .bold()
+    );
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::{price, utils};
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// `amount` in `unit` (sol | lamports | usd) as lamports
+fn to_lamports(amount: f64, unit: &str, sol_price: Option<f64>) -> Result<u64> {
+    if !amount.is_finite() || amount < 0.0 {
+        anyhow::bail!("Amount must be a non-negative number");
+    }
+    let lamports = match unit {
+        "sol" => amount * LAMPORTS_PER_SOL,
+        "lamports" if amount.fract() != 0.0 => {
+            anyhow::bail!("Lamports are whole numbers: {amount}")
+        }
+        "lamports" => amount,
+        "usd" => match sol_price {
+            Some(p) => amount / p * LAMPORTS_PER_SOL,
+            None => anyhow::bail!("No SOL price available, can't convert from USD"),
+        },
+        other => anyhow::bail!("Unknown unit: {other} (use sol, lamports or usd)"),
+    };
+    Ok(lamports.round() as u64)
+}
+
+pub async fn run(amount: f64, unit: &str, json: bool) -> Result<()> {
+    let sol_price = price::sol_price().await;
+    let lamports = to_lamports(amount, unit, sol_price)?;
+    let sol = utils::lamports_to_sol(lamports);
+
+    if json {
+        utils::print_json(
+            "convert",
+            serde_json::json!({
+                "lamports": lamports,
+                "sol": sol,
+                "usd": sol_price.map(|p| sol * p),
+                "sol_price": sol_price,
+            }),
+        );
+        return Ok(());
+    }
+
+    println!();
+    println!(
+        "  {} {}",
+        "Lamports:".white().bold(),
+        lamports.to_string().cyan()
+    );
+    println!(
+        "  {} {}",
+        "SOL:     ".white().bold(),
+        utils::format_sol(sol).green()
+    );
+    match sol_price {
+        Some(p) => println!(
+            "  {} {} {}",
+            "USD:     ".white().bold(),
+            utils::format_usd(sol * p).green(),
+            format!("@ {}", utils::format_usd(p)).dimmed()
+        ),
+        None => println!(
+            "  {} {}",
+            "USD:     ".white().bold(),
+            "— (price unavailable)".dimmed()
+        ),
+    }
+    println!();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_lamports() {
+        assert_eq!(to_lamports(0.5, "sol", None).unwrap(), 500_000_000);
+        assert_eq!(
+            to_lamports(2_039_280.0, "lamports", None).unwrap(),
+            2_039_280
+        );
+        assert_eq!(
+            to_lamports(150.0, "usd", Some(150.0)).unwrap(),
+            1_000_000_000
+        );
+
+        assert!(to_lamports(1.5, "lamports", None).is_err());
+        assert!(to_lamports(10.0, "usd", None).is_err());
+        assert!(to_lamports(-1.0, "sol", None).is_err());
+    }
+}
@@ -3,71 +3,156 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
 use solana_sdk::{
-    instruction::{AccountMeta, Instruction},
-    pubkey::Pubkey,
-    signature::Keypair,
-    signer::Signer,
-    transaction::Transaction,
+    instruction::Instruction, pubkey::Pubkey, signer::Signer, transaction::Transaction,
 };
-
-#[allow(deprecated)]
-use solana_sdk::system_program;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::time::Duration;
 
 use crate::solanapay;
-use crate::utils;
+use crate::{token, utils};
+
+/// Base delay between send retries, doubled each attempt
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_JITTER_MS: u64 = 250;
+
+/// Exponential backoff for retry `attempt` (1-based) plus jitter
+fn retry_delay(attempt: u32, jitter_ms: u64) -> Duration {
+    RETRY_BACKOFF * 2u32.pow(attempt - 1) + Duration::from_millis(jitter_ms)
+}
 
-/// Associated Token Program ID
-const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+/// Cheap jitter source, no need for a rand dependency
+fn jitter_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64 % MAX_JITTER_MS)
+        .unwrap_or(0)
+}
+
+/// `--dry-run`: show what would be sent
+fn print_plan(
+    ix: &Instruction,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    mint_name: &str,
+    ata: &Pubkey,
+    exists: bool,
+    json: bool,
+) {
+    if json {
+        let accounts: Vec<serde_json::Value> = ix
+            .accounts
+            .iter()
+            .map(|a| {
+                serde_json::json!({
+                    "pubkey": a.pubkey.to_string(),
+                    "signer": a.is_signer,
+                    "writable": a.is_writable,
+                })
+            })
+            .collect();
+        utils::print_json(
+            "create-ata",
+            serde_json::json!({
+                "dry_run": true,
+                "action": if exists { "none" } else { "create" },
+                "wallet": wallet.to_string(),
+                "mint": mint.to_string(),
+                "mint_name": mint_name,
+                "ata": ata.to_string(),
+                "exists": exists,
+                "instruction": {
+                    "program_id": ix.program_id.to_string(),
+                    "accounts": accounts,
+                    "data": bs58::encode(&ix.data).into_string(),
+                },
+            }),
+        );
+        return;
+    }
 
-/// Well-known token mints for testing (devnet/mainnet)
-const TEST_MINTS: &[(&str, &str)] = &[
-    ("USDC", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
-    ("USDT", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
-    ("RAY", "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R"),
-];
+    println!("\n{} Dry run — nothing will be sent\n", "🔍".yellow());
+    println!("   Mint:    {} ({})", mint_name.cyan(), mint);
+    println!("   ATA:     {}", ata.to_string().cyan());
+    if exists {
+        println!("   Status:  {}", "already exists, nothing to do".yellow());
+        println!();
+        return;
+    }
+    println!("   Status:  {}", "missing, would be created".green());
+    println!("\n   Instruction → {}", ix.program_id.to_string().dimmed());
+    for a in &ix.accounts {
+        let mut flags = Vec::new();
+        if a.is_signer {
+            flags.push("signer");
+        }
+        if a.is_writable {
+            flags.push("writable");
+        }
+        println!("     {} {}", a.pubkey, flags.join(", ").dimmed());
+    }
+    println!();
+}
 
-/// Get ATA address for wallet and mint
+/// Get ATA address for wallet and mint (classic SPL Token)
 fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
-    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
-    let token_program = spl_token::id();
-    let seeds = &[wallet.as_ref(), token_program.as_ref(), mint.as_ref()];
-    Pubkey::find_program_address(seeds, &ata_program).0
+    spl_associated_token_account::get_associated_token_address(wallet, mint)
 }
 
-/// Create ATA instruction
+/// `CreateIdempotent` ATA instruction: succeeds if the ATA already exists,
+/// so a concurrent create can't fail our transaction
 fn create_associated_token_account_instruction(
     payer: &Pubkey,
     wallet: &Pubkey,
     mint: &Pubkey,
 ) -> Instruction {
-    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
-    let ata = get_associated_token_address(wallet, mint);
-
-    Instruction {
-        program_id: ata_program,
-        accounts: vec![
-            AccountMeta::new(*payer, true),
-            AccountMeta::new(ata, false),
-            AccountMeta::new_readonly(*wallet, false),
-            AccountMeta::new_readonly(*mint, false),
-            AccountMeta::new_readonly(system_program::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-        ],
-        data: vec![], // Create instruction has no data
-    }
+    create_associated_token_account_idempotent(payer, wallet, mint, &spl_token::id())
+}
+
+/// CLI options for `create-ata`
+pub struct CreateAtaOptions<'a> {
+    pub wallet: Option<&'a str>,
+    pub keypair: Option<&'a str>,
+    pub mint: Option<&'a str>,
+    pub connect: bool,
+    pub simulate: bool,
+    /// Print the planned action without sending anything
+    pub dry_run: bool,
+    pub max_retries: u32,
+    /// Connect mode: also print the `solana:` link under the QR
+    pub deep_link: bool,
+    /// Write the unsigned transaction here instead of sending
+    pub dump_tx: Option<&'a str>,
+    /// Durable nonce account for `dump_tx`
+    pub nonce: Option<Pubkey>,
+    pub json: bool,
 }
 
-pub async fn run(
-    rpc_url: &str,
-    wallet_str: Option<&str>,
-    keypair_path: Option<&str>,
-    mint_str: Option<&str>,
-    connect: bool,
-) -> Result<()> {
+pub async fn run(rpc_url: &str, opts: CreateAtaOptions<'_>) -> Result<()> {
+    let CreateAtaOptions {
+        wallet: wallet_str,
+        keypair: keypair_path,
+        mint: mint_str,
+        connect,
+        simulate,
+        dry_run,
+        max_retries,
+        deep_link,
+        dump_tx,
+        nonce,
+        json,
+    } = opts;
+
+    if simulate && connect {
+        anyhow::bail!("--simulate needs a local keypair, it can't be used with --connect");
+    }
+    if dry_run && connect {
+        anyhow::bail!("--dry-run is for keypair mode, it can't be used with --connect");
+    }
+
     // ── Connect Flow ────────────────────────────────────────────────────────
     let wallet = if connect && wallet_str.is_none() {
         // use shared logic
-        crate::solanapay::connect_wallet().await?
+        crate::solanapay::connect_wallet(deep_link, json).await?
     } else {
         let w = wallet_str.ok_or_else(|| anyhow::anyhow!("Wallet address required"))?;
         utils::parse_pubkey(w)?
@@ -76,39 +161,75 @@ pub async fn run(
     let client = crate::rpc::client(rpc_url);
 
     // mint setup
-    let mint: Pubkey = if let Some(m) = mint_str {
-        utils::parse_pubkey(m)?
-    } else {
-        // USDC default
-        TEST_MINTS[0].1.parse().unwrap()
-    };
-
-    let mint_name = TEST_MINTS
-        .iter()
-        .find(|(_, addr)| addr.parse::<Pubkey>().ok() == Some(mint))
-        .map(|(name, _)| *name)
-        .unwrap_or("Unknown");
+    // USDC default
+    let mint = token::resolve_mint(mint_str.unwrap_or("USDC"))?;
+    let mint_name = token::symbol(&mint).unwrap_or("Unknown");
 
     // derive ata
     let ata = get_associated_token_address(&wallet, &mint);
 
+    let exists = client.get_account(&ata).is_ok();
+
+    if dry_run {
+        // the keypair must match the wallet, so the wallet is the payer
+        let ix = create_associated_token_account_instruction(&wallet, &wallet, &mint);
+        print_plan(&ix, &wallet, &mint, mint_name, &ata, exists, json);
+        return Ok(());
+    }
+
     // check exist
-    if client.get_account(&ata).is_ok() {
-        println!(
-            "{}",
-            format!("✓ ATA already exists: {}", utils::short_key(&ata)).yellow()
-        );
+    if exists {
+        if json {
+            utils::print_json(
+                "create-ata",
+                serde_json::json!({
+                    "status": "exists",
+                    "wallet": wallet.to_string(),
+                    "mint": mint.to_string(),
+                    "ata": ata.to_string(),
+                }),
+            );
+        } else {
+            println!(
+                "{}",
+                format!("✓ ATA already exists: {}", utils::short_key(&ata)).yellow()
+            );
+        }
         return Ok(());
     }
 
-    println!(
-        "📝 Creating ATA for {} ({})...",
-        mint_name.cyan(),
-        utils::short_key(&mint)
+    // --connect / --dump-tx print JSON after this
+    utils::print_status(
+        format!(
+            "📝 Creating ATA for {} ({})...",
+            mint_name.cyan(),
+            utils::short_key(&mint)
+        ),
+        json,
     );
 
     // ── Execution ───────────────────────────────────────────────────────────
 
+    if let Some(dir) = dump_tx {
+        let ix = create_associated_token_account_instruction(&wallet, &wallet, &mint);
+        let tx = match nonce {
+            Some(account) => {
+                let nonce = crate::rpc::DurableNonce::fetch(&client, &account)?;
+                let mut tx = Transaction::new_with_payer(&nonce.instructions(&[ix]), Some(&wallet));
+                tx.message.recent_blockhash = nonce.blockhash;
+                tx
+            }
+            None => {
+                let mut tx = Transaction::new_with_payer(&[ix], Some(&wallet));
+                tx.message.recent_blockhash = client.get_latest_blockhash()?;
+                tx
+            }
+        };
+        let files = utils::dump_transactions(dir, "create-ata", &[tx])?;
+        utils::print_dumped("create-ata", &files, nonce.is_some(), json);
+        return Ok(());
+    }
+
     if connect {
         // SOLANA PAY MODE
         let ix = create_associated_token_account_instruction(&wallet, &wallet, &mint);
@@ -117,7 +238,10 @@ pub async fn run(
         let mut tx = Transaction::new_with_payer(&[ix], Some(&wallet));
         tx.message.recent_blockhash = recent_hash;
 
-        println!("\n{}", "📱 Preparing transaction...".cyan().bold());
+        utils::print_status(
+            format!("\n{}", "📱 Preparing transaction...".cyan().bold()),
+            json,
+        );
 
         // upload to relay
         let solana_pay_url = solanapay::upload_transactions(
@@ -128,9 +252,21 @@ pub async fn run(
         )
         .await?;
 
-        println!("{}", "✓ Uploaded successfully".green());
-        solanapay::display_qr(&solana_pay_url)?;
-
+        utils::print_status("✓ Uploaded successfully".green(), json);
+        solanapay::display_qr(&solana_pay_url, deep_link, json);
+
+        if json {
+            utils::print_json(
+                "create-ata",
+                serde_json::json!({
+                    "status": "uploaded",
+                    "wallet": wallet.to_string(),
+                    "mint": mint.to_string(),
+                    "ata": ata.to_string(),
+                }),
+            );
+            return Ok(());
+        }
         println!(
             "\n{}",
             "Scan QR with your wallet to sign and send.".dimmed()
@@ -144,31 +280,77 @@ pub async fn run(
     }
 
     // KEYPAIR MODE
-    let keypair = if let Some(path_or_key) = keypair_path {
-        if std::path::Path::new(path_or_key).exists() {
-            solana_sdk::signature::read_keypair_file(path_or_key)
-                .map_err(|e| anyhow::anyhow!("Failed keypair file: {}", e))?
-        } else {
-            let bytes = bs58::decode(path_or_key)
-                .into_vec()
-                .map_err(|_| anyhow::anyhow!("Invalid keypair"))?;
-            Keypair::try_from(bytes.as_slice())
-                .map_err(|e| anyhow::anyhow!("Invalid bytes: {}", e))?
-        }
-    } else {
-        utils::load_keypair(None)?
+    let keypair = match keypair_path {
+        Some(arg) => utils::parse_keypair_arg(arg)?,
+        None => utils::load_keypair(None)?,
     };
 
     utils::verify_keypair(&keypair, &wallet)?;
 
     let ix = create_associated_token_account_instruction(&keypair.pubkey(), &wallet, &mint);
-    let lh = client.get_latest_blockhash()?;
+    let sign = |lh| {
+        Transaction::new_signed_with_payer(
+            std::slice::from_ref(&ix),
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            lh,
+        )
+    };
+
+    if simulate {
+        let tx = sign(client.get_latest_blockhash()?);
+        let sim = client
+            .simulate_transaction(&tx)
+            .context("Failed to simulate transaction")?
+            .value;
 
-    let tx = Transaction::new_signed_with_payer(&[ix], Some(&keypair.pubkey()), &[&keypair], lh);
+        println!();
+        for log in sim.logs.unwrap_or_default() {
+            println!("   {}", log.dimmed());
+        }
+        println!();
+
+        match sim.err {
+            None => println!(
+                "{} Simulation succeeded{} — nothing was sent",
+                "✅".green(),
+                sim.units_consumed
+                    .map(|u| format!(" ({u} CU)"))
+                    .unwrap_or_default()
+            ),
+            Some(e) => {
+                println!("{} Simulation failed: {}", "❌".red(), e.to_string().red());
+                anyhow::bail!("ATA creation would fail");
+            }
+        }
+        return Ok(());
+    }
 
-    let sig = client
-        .send_and_confirm_transaction(&tx)
-        .context("Failed to create ATA")?;
+    // retry transient failures, re-signing with a fresh blockhash each time.
+    // CreateIdempotent already covers a concurrent create, so any program
+    // error (e.g. custom 0, an account at the ATA address with the wrong
+    // owner) is fatal
+    let mut attempt = 0;
+    let sig = loop {
+        let tx = sign(client.get_latest_blockhash()?);
+        match client.send_and_confirm_transaction(&tx) {
+            Ok(sig) => break sig,
+            Err(e) if attempt < max_retries && crate::rpc::is_retriable(&e) => {
+                attempt += 1;
+                let delay = retry_delay(attempt, jitter_ms());
+                println!(
+                    "  {} Send failed ({}), retry {}/{} in {}ms",
+                    "⚠".yellow(),
+                    e,
+                    attempt,
+                    max_retries,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e).context("Failed to create ATA"),
+        }
+    };
 
     println!("{}", "✅ ATA created successfully!".green());
     println!("   Address: {}", utils::short_key(&ata).cyan());
@@ -228,7 +410,14 @@ mod tests {
         let ix = create_associated_token_account_instruction(&payer, &wallet, &mint);
 
         // Verify program ID
-        assert_eq!(ix.program_id.to_string(), ASSOCIATED_TOKEN_PROGRAM_ID);
+        assert_eq!(
+            ix.program_id.to_string(),
+            "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+        );
+        assert_eq!(
+            ix,
+            create_associated_token_account_idempotent(&payer, &wallet, &mint, &spl_token::id())
+        );
 
         // Verify account count (6 accounts)
         assert_eq!(ix.accounts.len(), 6);
@@ -242,18 +431,41 @@ mod tests {
         assert!(!ix.accounts[2].is_signer);
         assert_eq!(ix.accounts[2].pubkey, wallet);
 
-        // Fourth account is mint
+        // Second account is the ATA itself, fourth the mint
+        assert_eq!(
+            ix.accounts[1].pubkey,
+            get_associated_token_address(&wallet, &mint)
+        );
         assert_eq!(ix.accounts[3].pubkey, mint);
 
-        // Data should be empty for create instruction
-        assert!(ix.data.is_empty());
+        // AssociatedTokenAccountInstruction::CreateIdempotent
+        assert_eq!(ix.data, vec![1]);
     }
 
     #[test]
-    fn test_test_mints_are_valid() {
-        for (name, addr) in TEST_MINTS {
-            let pk = Pubkey::from_str(addr);
-            assert!(pk.is_ok(), "Invalid mint address for {}: {}", name, addr);
-        }
+    fn test_send_errors_retried() {
+        use solana_client::client_error::{ClientError, ClientErrorKind};
+        use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+        // AssociatedTokenAccountError::InvalidOwner, not a lost race
+        let invalid_owner = ClientError::from(ClientErrorKind::TransactionError(
+            TransactionError::InstructionError(0, InstructionError::Custom(0)),
+        ));
+        assert!(!crate::rpc::is_retriable(&invalid_owner));
+
+        let expired = ClientError::from(ClientErrorKind::Custom("Blockhash not found".into()));
+        assert!(crate::rpc::is_retriable(&expired));
+
+        let funds = ClientError::from(ClientErrorKind::TransactionError(
+            TransactionError::InsufficientFundsForFee,
+        ));
+        assert!(!crate::rpc::is_retriable(&funds));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1, 0), Duration::from_millis(500));
+        assert_eq!(retry_delay(3, 100), Duration::from_millis(2100));
+        assert!(jitter_ms() < MAX_JITTER_MS);
     }
 }
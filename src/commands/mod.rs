@@ -1,7 +1,12 @@
+pub mod accounts;
+pub mod activity;
 pub mod clean;
+pub mod convert;
 pub mod create_ata;
 pub mod monitor;
 pub mod portfolio;
 pub mod rent;
 pub mod rpc_bench;
 pub mod scan;
+pub mod send;
+pub mod submit;
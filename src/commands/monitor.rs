@@ -1,20 +1,161 @@
 use crate::utils;
 use anyhow::{Context, Result};
 use colored::Colorize;
-use solana_client::rpc_config::RpcTransactionConfig;
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::UiTransactionEncoding;
 use std::collections::HashSet;
+use tokio::sync::Semaphore;
 
-pub async fn run(rpc_url: &str, wallet_str: &str, interval: u64) -> Result<()> {
+/// Max in-flight getTransaction calls per poll
+const DETAIL_CONCURRENCY: usize = 8;
+
+#[derive(serde::Serialize)]
+pub(crate) struct TxEvent {
+    pub signature: String,
+    pub block_time: Option<i64>,
+    pub success: bool,
+    /// The wallet's SOL change, `None` when the tx couldn't be fetched
+    pub sol_change: Option<f64>,
+    pub memo: Option<String>,
+    #[serde(skip)]
+    pub sig: Signature,
+}
+
+impl TxEvent {
+    pub(crate) fn new(
+        sig: Signature,
+        info: &RpcConfirmedTransactionStatusWithSignature,
+        sol_change: Option<f64>,
+    ) -> Self {
+        Self {
+            signature: info.signature.clone(),
+            block_time: info.block_time,
+            success: info.err.is_none(),
+            sol_change,
+            memo: info.memo.clone(),
+            sig,
+        }
+    }
+}
+
+/// `--link-style`: how the explorer URL is shown in text mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LinkStyle {
+    Plain,
+    Markdown,
+    None,
+}
+
+impl LinkStyle {
+    fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "plain" => Self::Plain,
+            "markdown" => Self::Markdown,
+            "none" => Self::None,
+            _ => anyhow::bail!("Unknown link style: {s} (use plain, markdown or none)"),
+        })
+    }
+}
+
+fn tx_link(signature: &str, style: LinkStyle) -> String {
+    let url = format!("https://solscan.io/tx/{signature}");
+    match style {
+        LinkStyle::Plain => url,
+        LinkStyle::Markdown => format!("[{signature}]({url})"),
+        LinkStyle::None => String::new(),
+    }
+}
+
+/// Writes events as text, JSONL, or a single JSON array (`--json-array`)
+struct Printer {
+    json: bool,
+    array: bool,
+    link: LinkStyle,
+    count: usize,
+}
+
+impl Printer {
+    fn new(json: bool, array: bool, link: LinkStyle) -> Self {
+        Self {
+            json: json || array,
+            array,
+            link,
+            count: 0,
+        }
+    }
+
+    fn begin(&self) {
+        if self.array {
+            println!("[");
+        }
+    }
+
+    fn end(&self) {
+        if self.array {
+            println!("]");
+        }
+    }
+
+    fn event(&mut self, ev: &TxEvent, with_date: bool) {
+        if self.array {
+            let sep = if self.count == 0 { "" } else { "," };
+            println!(
+                "{sep}{}",
+                utils::with_schema("monitor", serde_json::json!(ev))
+            );
+        } else {
+            print_event(ev, self.json, with_date, self.link);
+        }
+        self.count += 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    rpc_url: &str,
+    wallet_str: &str,
+    interval: u64,
+    since: Option<&str>,
+    until: Option<&str>,
+    once: bool,
+    limit: usize,
+    json_array: bool,
+    link_style: &str,
+    programs: &[Pubkey],
+    json: bool,
+) -> Result<()> {
     let wallet = utils::parse_pubkey(wallet_str)?;
+    let programs: HashSet<String> = programs.iter().map(|p| p.to_string()).collect();
+    let since = since.map(utils::parse_time_bound).transpose()?;
+    let until = until.map(utils::parse_time_bound).transpose()?;
+    let mut printer = Printer::new(json, json_array, LinkStyle::parse(link_style)?);
+    let json = printer.json;
 
-    println!(
-        "\n{} Monitoring transactions for {}",
-        "📡".bold(),
-        utils::short_key(&wallet).cyan()
-    );
-    println!("  {} Press Ctrl+C to stop\n", "ℹ".dimmed());
+    if once {
+        return run_once(
+            rpc_url,
+            &wallet,
+            limit,
+            since,
+            until,
+            &programs,
+            &mut printer,
+        )
+        .await;
+    }
+
+    if !json {
+        println!(
+            "\n{} Monitoring transactions for {}",
+            "📡".bold(),
+            utils::short_key(&wallet).cyan()
+        );
+        println!("  {} Press Ctrl+C to stop\n", "ℹ".dimmed());
+    }
 
     let mut seen: HashSet<Signature> = HashSet::new();
     let delay = std::time::Duration::from_secs(interval);
@@ -23,115 +164,306 @@ pub async fn run(rpc_url: &str, wallet_str: &str, interval: u64) -> Result<()> {
     {
         let client = crate::rpc::client(rpc_url);
         let w = wallet;
-        let initial = tokio::task::spawn_blocking(move || client.get_signatures_for_address(&w))
-            .await?
-            .context("Failed initial fetch")?;
+        let initial = tokio::task::spawn_blocking(move || {
+            client.get_signatures_for_address_with_config(&w, signatures_config(None))
+        })
+        .await?
+        .context("Failed initial fetch")?;
 
         for info in &initial {
             if let Ok(sig) = utils::parse_signature(&info.signature) {
                 seen.insert(sig);
             }
         }
-        println!(
-            "  {} Loaded {} existing transactions, watching for new…\n",
-            "✓".green(),
-            seen.len()
-        );
+        if !json {
+            println!(
+                "  {} Loaded {} existing transactions, watching for new…\n",
+                "✓".green(),
+                seen.len()
+            );
+        }
     }
 
+    printer.begin();
+
+    // Ctrl+C ends the loop cleanly so the JSON array gets closed
+    let stop = tokio::signal::ctrl_c();
+    tokio::pin!(stop);
+
     loop {
-        let client = crate::rpc::client(rpc_url);
-        let w = wallet;
+        let poll = async {
+            let client = crate::rpc::client(rpc_url);
+            let w = wallet;
 
-        // fetch signatures
-        let sigs = tokio::task::spawn_blocking(move || client.get_signatures_for_address(&w))
+            // fetch signatures
+            let sigs = tokio::task::spawn_blocking(move || {
+                client.get_signatures_for_address_with_config(&w, signatures_config(None))
+            })
             .await?
             .context("RPC error")?;
 
-        // process new ones (reverse to show oldest first)
-        for info in sigs.iter().rev() {
-            let sig = match utils::parse_signature(&info.signature) {
-                Ok(s) => s,
-                Err(_) => continue,
-            };
+            // collect new ones (reverse to show oldest first)
+            let mut pending = Vec::new();
+            for info in sigs.iter().rev() {
+                let sig = match utils::parse_signature(&info.signature) {
+                    Ok(s) => s,
+                    Err(_) => continue,
+                };
+
+                // dedup before fetching so nothing is requested twice
+                if !seen.insert(sig) {
+                    continue;
+                }
+
+                // outside the requested window: remember it, but don't print
+                if !in_window(info.block_time, since, until) {
+                    continue;
+                }
 
-            if seen.contains(&sig) {
-                continue;
+                pending.push((sig, info));
             }
-            seen.insert(sig);
 
-            // fetch details for new tx
-            let client = crate::rpc::client(rpc_url);
-            let config = RpcTransactionConfig {
-                encoding: Some(UiTransactionEncoding::JsonParsed),
-                commitment: Some(CommitmentConfig::confirmed()),
-                max_supported_transaction_version: Some(0),
-            };
-
-            let s_clone = sig;
-            let tx_result = tokio::task::spawn_blocking(move || {
-                client.get_transaction_with_config(&s_clone, config)
-            })
-            .await?;
+            for ev in fetch_events(rpc_url, &wallet, pending, &programs).await? {
+                printer.event(&ev, false);
+            }
 
-            let time = if let Some(bt) = info.block_time {
-                chrono::DateTime::from_timestamp(bt, 0)
-                    .map(|dt| dt.format("%H:%M:%S").to_string())
-                    .unwrap_or_else(|| "?".into())
-            } else {
-                "?".into()
-            };
-
-            let status = if info.err.is_some() {
-                "FAIL".red().bold()
-            } else {
-                "OK".green().bold()
-            };
-
-            // try to guess balance change
-            let change = tx_result.ok().and_then(|tx| {
-                if let Some(meta) = tx.transaction.meta {
-                    estimate_balance_change(&meta.pre_balances, &meta.post_balances)
-                } else {
-                    None
-                }
-            });
+            tokio::time::sleep(delay).await;
+            anyhow::Ok(())
+        };
 
-            let change_str = match change {
-                Some(d) if d > 0.0 => format!("+{d:.6} SOL").green().to_string(),
-                Some(d) if d < 0.0 => format!("{d:.6} SOL").red().to_string(),
-                _ => String::new(),
-            };
+        tokio::select! {
+            _ = &mut stop => break,
+            res = poll => res?,
+        }
+    }
 
-            let memo = info
-                .memo
-                .as_ref()
-                .map(|m| format!(" memo:{}", m.dimmed()))
-                .unwrap_or_default();
+    printer.end();
+    Ok(())
+}
 
-            println!(
-                "  {} [{}] {} {} {} {}",
-                time.dimmed(),
-                status,
-                utils::short_sig(&sig).white(),
-                change_str,
-                memo,
-                format!("https://solscan.io/tx/{sig}").dimmed(),
-            );
+/// One-shot report: print the latest `limit` txs and exit
+async fn run_once(
+    rpc_url: &str,
+    wallet: &Pubkey,
+    limit: usize,
+    since: Option<i64>,
+    until: Option<i64>,
+    programs: &HashSet<String>,
+    printer: &mut Printer,
+) -> Result<()> {
+    let json = printer.json;
+    let client = crate::rpc::client(rpc_url);
+    let w = *wallet;
+    let config = signatures_config(Some(limit.clamp(1, 1000)));
+
+    let sigs = tokio::task::spawn_blocking(move || {
+        client.get_signatures_for_address_with_config(&w, config)
+    })
+    .await?
+    .context("RPC error")?;
+
+    if !json {
+        println!(
+            "\n{} Recent transactions for {}\n",
+            "📡".bold(),
+            utils::short_key(wallet).cyan()
+        );
+    }
+
+    let pending: Vec<_> = sigs
+        .iter()
+        .rev()
+        .filter(|info| in_window(info.block_time, since, until))
+        .filter_map(|info| Some((utils::parse_signature(&info.signature).ok()?, info)))
+        .collect();
+
+    let events = fetch_events(rpc_url, wallet, pending, programs).await?;
+    printer.begin();
+    for ev in &events {
+        printer.event(ev, true);
+    }
+    printer.end();
+
+    if !json {
+        if events.is_empty() {
+            println!("  {}", "No transactions found".dimmed());
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// What we read from a fetched transaction
+struct TxDetails {
+    sol_change: Option<f64>,
+    /// Programs invoked, inner instructions included
+    programs: HashSet<String>,
+}
+
+/// Fetch details for all pending txs concurrently, keeping their order.
+/// With `programs` set, only txs invoking one of them are kept.
+pub(crate) async fn fetch_events(
+    rpc_url: &str,
+    wallet: &Pubkey,
+    pending: Vec<(Signature, &RpcConfirmedTransactionStatusWithSignature)>,
+    programs: &HashSet<String>,
+) -> Result<Vec<TxEvent>> {
+    let sem = Semaphore::new(DETAIL_CONCURRENCY);
+    let sem = &sem;
+
+    let details = futures::future::join_all(pending.iter().map(|(sig, _)| {
+        let sig = *sig;
+        async move {
+            let _permit = sem.acquire().await?;
+            fetch_details(rpc_url, sig, wallet).await
         }
+    }))
+    .await;
 
-        tokio::time::sleep(delay).await;
+    let mut events = Vec::new();
+    for ((sig, info), details) in pending.into_iter().zip(details) {
+        let details = details?;
+        if !programs.is_empty()
+            && details
+                .as_ref()
+                .is_none_or(|d| d.programs.is_disjoint(programs))
+        {
+            continue;
+        }
+        let change = details.and_then(|d| d.sol_change);
+        events.push(TxEvent::new(sig, info, change));
+    }
+    Ok(events)
+}
+
+/// `--commitment`, but never `processed`: getSignaturesForAddress rejects it
+pub(crate) fn signatures_config(limit: Option<usize>) -> GetConfirmedSignaturesForAddress2Config {
+    GetConfirmedSignaturesForAddress2Config {
+        limit,
+        commitment: Some(crate::rpc::commitment_at_least_confirmed()),
+        ..Default::default()
     }
 }
 
-fn estimate_balance_change(pre: &[u64], post: &[u64]) -> Option<f64> {
-    if !pre.is_empty() && !post.is_empty() {
-        let diff = post[0] as i64 - pre[0] as i64;
-        if diff != 0 {
-            return Some(diff as f64 / 1e9);
+/// Fetch a tx, read the wallet's SOL change and list the programs it calls
+async fn fetch_details(
+    rpc_url: &str,
+    sig: Signature,
+    wallet: &Pubkey,
+) -> Result<Option<TxDetails>> {
+    let client = crate::rpc::client(rpc_url);
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::JsonParsed),
+        commitment: Some(crate::rpc::commitment_at_least_confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+
+    let tx_result =
+        tokio::task::spawn_blocking(move || client.get_transaction_with_config(&sig, config))
+            .await?;
+
+    Ok(tx_result.ok().map(|tx| {
+        let value = serde_json::to_value(&tx.transaction).unwrap_or_default();
+        let programs = program_ids(&value);
+        let sol_change = tx.transaction.meta.and_then(|meta| {
+            let index = account_index(&value, &wallet.to_string())?;
+            balance_change(&meta.pre_balances, &meta.post_balances, index)
+        });
+        TxDetails {
+            sol_change,
+            programs,
+        }
+    }))
+}
+
+/// Every `programId` in a jsonParsed transaction, outer and inner instructions
+fn program_ids(value: &serde_json::Value) -> HashSet<String> {
+    let mut ids = HashSet::new();
+    let mut stack = vec![value];
+    while let Some(v) = stack.pop() {
+        match v {
+            serde_json::Value::Object(map) => {
+                if let Some(id) = map.get("programId").and_then(|id| id.as_str()) {
+                    ids.insert(id.to_string());
+                }
+                stack.extend(map.values());
+            }
+            serde_json::Value::Array(items) => stack.extend(items),
+            _ => {}
         }
     }
-    None
+    ids
+}
+
+/// One line per tx (JSONL in json mode)
+fn print_event(ev: &TxEvent, json: bool, with_date: bool, link: LinkStyle) {
+    if json {
+        utils::print_json("monitor", serde_json::json!(ev));
+        return;
+    }
+
+    let fmt = if with_date {
+        "%Y-%m-%d %H:%M:%S"
+    } else {
+        "%H:%M:%S"
+    };
+    let time = ev
+        .block_time
+        .and_then(|bt| chrono::DateTime::from_timestamp(bt, 0))
+        .map(|dt| dt.format(fmt).to_string())
+        .unwrap_or_else(|| "?".into());
+
+    let status = if ev.success {
+        "OK".green().bold()
+    } else {
+        "FAIL".red().bold()
+    };
+
+    let change_str = match ev.sol_change {
+        Some(d) if d > 0.0 => format!("+{d:.6} SOL").green().to_string(),
+        Some(d) if d < 0.0 => format!("{d:.6} SOL").red().to_string(),
+        _ => String::new(),
+    };
+
+    let memo = ev
+        .memo
+        .as_ref()
+        .map(|m| format!(" memo:{}", m.dimmed()))
+        .unwrap_or_default();
+
+    println!(
+        "  {} [{}] {} {} {} {}",
+        time.dimmed(),
+        status,
+        utils::short_sig(&ev.sig).white(),
+        change_str,
+        memo,
+        tx_link(&ev.signature, link).dimmed(),
+    );
+}
+
+/// Unknown block times are kept, we can't tell where they fall
+fn in_window(block_time: Option<i64>, since: Option<i64>, until: Option<i64>) -> bool {
+    let Some(bt) = block_time else {
+        return true;
+    };
+    since.is_none_or(|s| bt >= s) && until.is_none_or(|u| bt <= u)
+}
+
+/// Position of `wallet` in the tx's account keys, the order balances use.
+/// jsonParsed lists lookup table accounts too; plain json keys are strings.
+fn account_index(tx: &serde_json::Value, wallet: &str) -> Option<usize> {
+    tx["transaction"]["message"]["accountKeys"]
+        .as_array()?
+        .iter()
+        .position(|k| k["pubkey"].as_str().or(k.as_str()) == Some(wallet))
+}
+
+/// SOL change of the account at `index` (not the fee payer's), 0 included
+fn balance_change(pre: &[u64], post: &[u64], index: usize) -> Option<f64> {
+    let diff = *post.get(index)? as i64 - *pre.get(index)? as i64;
+    Some(diff as f64 / 1e9)
 }
 
 #[cfg(test)]
@@ -139,26 +471,88 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_estimate_balance_change() {
-        // Increase
-        assert_eq!(
-            estimate_balance_change(&[1_000_000_000], &[2_500_000_000]),
-            Some(1.5)
-        );
+    fn test_balance_change() {
+        // the sender pays, the recipient at index 1 gains
+        let pre = [5_000_000_000, 1_000_000_000];
+        let post = [3_499_995_000, 2_500_000_000];
+        assert_eq!(balance_change(&pre, &post, 1), Some(1.5));
+        assert_eq!(balance_change(&pre, &post, 0), Some(-1.500005));
 
-        // Decrease
+        // touched but unchanged is a known zero
+        assert_eq!(balance_change(&[7], &[7], 0), Some(0.0));
+
+        // Out of range
+        assert_eq!(balance_change(&[], &[], 0), None);
+    }
+
+    #[test]
+    fn test_account_index() {
+        let parsed = serde_json::json!({
+            "transaction": { "message": { "accountKeys": [
+                { "pubkey": "Payer", "signer": true, "writable": true },
+                { "pubkey": "Wallet", "signer": false, "writable": true }
+            ] } }
+        });
+        assert_eq!(account_index(&parsed, "Wallet"), Some(1));
+        assert_eq!(account_index(&parsed, "Other"), None);
+
+        let plain = serde_json::json!({
+            "transaction": { "message": { "accountKeys": ["Payer", "Wallet"] } }
+        });
+        assert_eq!(account_index(&plain, "Payer"), Some(0));
+    }
+
+    #[test]
+    fn test_program_ids() {
+        let tx = serde_json::json!({
+            "transaction": {
+                "message": {
+                    "instructions": [
+                        { "programId": "ComputeBudget111111111111111111111111111111", "data": "3" },
+                        { "programId": "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "accounts": [] }
+                    ]
+                }
+            },
+            "meta": {
+                "innerInstructions": [{
+                    "index": 1,
+                    "instructions": [
+                        { "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", "parsed": {} }
+                    ]
+                }]
+            }
+        });
+
+        let ids = program_ids(&tx);
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"));
+        assert!(ids.contains("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"));
+        assert!(program_ids(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_tx_link() {
         assert_eq!(
-            estimate_balance_change(&[2_000_000_000], &[1_000_000_000]),
-            Some(-1.0)
+            tx_link("abc", LinkStyle::Plain),
+            "https://solscan.io/tx/abc"
         );
-
-        // No change
         assert_eq!(
-            estimate_balance_change(&[1_000_000_000], &[1_000_000_000]),
-            None
+            tx_link("abc", LinkStyle::Markdown),
+            "[abc](https://solscan.io/tx/abc)"
         );
+        assert_eq!(tx_link("abc", LinkStyle::None), "");
+        assert!(LinkStyle::parse("html").is_err());
+    }
+
+    #[test]
+    fn test_in_window() {
+        assert!(in_window(Some(100), None, None));
+        assert!(in_window(Some(100), Some(50), Some(150)));
+        assert!(in_window(Some(100), Some(100), Some(100)));
+        assert!(!in_window(Some(100), Some(101), None));
+        assert!(!in_window(Some(100), None, Some(99)));
 
-        // Empty
-        assert_eq!(estimate_balance_change(&[], &[]), None);
+        // unknown time passes
+        assert!(in_window(None, Some(50), Some(150)));
     }
 }
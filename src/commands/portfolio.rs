@@ -1,12 +1,41 @@
-use crate::{price, utils};
+use crate::{das, price, token, utils};
 use anyhow::{Context, Result};
 use colored::Colorize;
-use solana_account_decoder::UiAccountEncoding;
+use futures::stream::{BoxStream, SelectAll, StreamExt};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
 use solana_client::{
-    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
-    rpc_filter::{Memcmp, RpcFilterType},
+    nonblocking::pubsub_client::PubsubClient, rpc_config::RpcAccountInfoConfig,
+    rpc_response::Response,
 };
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::{collections::HashSet, time::Duration};
+use tokio::sync::Semaphore;
+
+/// Portfolio section a token is listed under
+#[derive(serde::Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Group {
+    Spl,
+    Token2022,
+}
+
+impl Group {
+    const ALL: [Group; 2] = [Group::Spl, Group::Token2022];
+
+    fn label(self) -> &'static str {
+        match self {
+            Group::Spl => "SPL Tokens",
+            Group::Token2022 => "Token-2022",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Group::Spl => "spl",
+            Group::Token2022 => "token2022",
+        }
+    }
+}
 
 #[derive(serde::Serialize, Clone)]
 struct Token {
@@ -17,18 +46,203 @@ struct Token {
     balance: f64,
     price: f64,
     value: f64,
+    group: Group,
+    /// Token-2022 transfer fee, in basis points
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transfer_fee_bps: Option<u16>,
+    /// Raw amount withheld when moving the whole balance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transfer_fee_raw: Option<u64>,
+    /// `value` net of that fee (`value` stays gross)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    net_value: Option<f64>,
 }
 
-pub async fn run(
-    rpc_url: &str,
-    wallet_str: &str,
-    min_usd: f64,
-    sort: &str,
-    json: bool,
-) -> Result<()> {
+impl Token {
+    fn set_decimals(&mut self, decimals: u8) {
+        self.decimals = decimals;
+        self.balance = utils::token_amount(self.raw, decimals);
+    }
+
+    fn set_transfer_fee(&mut self, fee: token::TransferFee) {
+        self.transfer_fee_bps = Some(fee.basis_points);
+        self.transfer_fee_raw = Some(fee.calculate(self.raw));
+    }
+
+    /// Gross value, plus the net one for transfer-fee tokens
+    fn set_price(&mut self, price: f64) {
+        self.price = price;
+        self.value = self.balance * price;
+        self.net_value = self
+            .transfer_fee_raw
+            .map(|fee| utils::token_amount(self.raw.saturating_sub(fee), self.decimals) * price);
+    }
+}
+
+/// Wallets loaded at once in `--file` mode
+const BATCH_CONCURRENCY: usize = 10;
+
+/// Anything above this is a misparsed mint, not real decimals
+const MAX_DECIMALS: u8 = 18;
+
+/// Exit codes for `--alert-below` / `--alert-above`
+const EXIT_ALERT_BELOW: i32 = 10;
+const EXIT_ALERT_ABOVE: i32 = 11;
+
+/// CLI options for `portfolio`
+pub struct PortfolioOptions<'a> {
+    pub wallet: Option<&'a str>,
+    /// Wallet list (one public key per line) to aggregate instead
+    pub file: Option<&'a str>,
+    pub min_usd: f64,
+    /// Hide tokens with a smaller human balance, priced or not
+    pub min_balance: f64,
+    pub sort: &'a str,
+    /// Flip the `sort` order
+    pub reverse: bool,
+    pub hide_unpriced: bool,
+    pub include_zero_value: bool,
+    /// Show only the first N tokens after sorting (display only)
+    pub top: Option<usize>,
+    /// Mints dropped before pricing, from display and totals alike
+    pub exclude: &'a [Pubkey],
+    /// Keep running, reprint totals whenever a balance changes
+    pub watch: bool,
+    /// Poll period for `watch` when the RPC has no WebSocket endpoint
+    pub watch_interval: u64,
+    pub with_cnfts: bool,
+    /// Count rent locked in closeable accounts towards the total
+    pub with_rent: bool,
+    pub summary: bool,
+    /// Exit with `EXIT_ALERT_BELOW` if the total USD value is below this
+    pub alert_below: Option<f64>,
+    /// Exit with `EXIT_ALERT_ABOVE` if the total USD value is above this
+    pub alert_above: Option<f64>,
+    pub json: bool,
+}
+
+/// Total value crossed an alert threshold
+#[derive(Debug, PartialEq)]
+enum Alert {
+    Below(f64),
+    Above(f64),
+}
+
+impl Alert {
+    fn check(total: f64, below: Option<f64>, above: Option<f64>) -> Option<Self> {
+        match (below, above) {
+            (Some(b), _) if total < b => Some(Alert::Below(b)),
+            (_, Some(a)) if total > a => Some(Alert::Above(a)),
+            _ => None,
+        }
+    }
+
+    /// A total that leaves out SOL (no price, `--offline`) would trip the
+    /// alert for the wrong reason, so it's skipped with a warning instead
+    fn check_total(total: Option<f64>, below: Option<f64>, above: Option<f64>) -> Option<Self> {
+        match total {
+            Some(total) => Self::check(total, below, above),
+            None => {
+                if below.is_some() || above.is_some() {
+                    eprintln!(
+                        "{} SOL price unavailable, --alert-below/--alert-above not checked",
+                        "⚠".yellow()
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        match self {
+            Alert::Below(_) => EXIT_ALERT_BELOW,
+            Alert::Above(_) => EXIT_ALERT_ABOVE,
+        }
+    }
+
+    fn message(&self, total: f64) -> String {
+        let (dir, limit) = match self {
+            Alert::Below(l) => ("below", l),
+            Alert::Above(l) => ("above", l),
+        };
+        format!(
+            "Portfolio value {} is {dir} {}",
+            utils::format_usd(total),
+            utils::format_usd(*limit)
+        )
+    }
+}
+
+/// Report a crossed threshold on stderr; its exit code is left to main, so
+/// the `--stats` footer still prints
+fn finish(alert: Option<Alert>, total: Option<f64>) -> Result<Option<i32>> {
+    if let (Some(a), Some(total)) = (&alert, total) {
+        eprintln!("{} {}", "🚨".red(), a.message(total).red().bold());
+    }
+    Ok(alert.map(|a| a.exit_code()))
+}
+
+/// Returns the exit code to end with when an `--alert-*` threshold is crossed
+pub async fn run(rpc_url: &str, opts: PortfolioOptions<'_>) -> Result<Option<i32>> {
+    let PortfolioOptions {
+        wallet: wallet_str,
+        file,
+        min_usd,
+        min_balance,
+        sort,
+        reverse,
+        hide_unpriced,
+        include_zero_value,
+        top,
+        exclude,
+        watch,
+        watch_interval,
+        with_cnfts,
+        with_rent,
+        summary,
+        alert_below,
+        alert_above,
+        json,
+    } = opts;
+
+    if let Some(path) = file {
+        if with_cnfts || with_rent {
+            anyhow::bail!("--with-cnfts and --with-rent are not supported with --file");
+        }
+        let view = View {
+            min_usd,
+            min_balance,
+            sort,
+            reverse,
+            hide_unpriced,
+            include_zero_value,
+            top,
+            exclude,
+        };
+        return run_batch(
+            rpc_url,
+            path,
+            &view,
+            summary,
+            alert_below,
+            alert_above,
+            json,
+        )
+        .await;
+    }
+
+    let wallet_str = wallet_str.context("Wallet address required")?;
     let wallet = utils::parse_pubkey(wallet_str)?;
+    if watch {
+        return watch_wallet(rpc_url, wallet, watch_interval, json)
+            .await
+            .map(|()| None);
+    }
+    // summary prints a single line, so no progress chatter either
+    let quiet = json || summary;
 
-    if !json {
+    if !quiet {
         println!(
             "\n{} Loading portfolio for {}…\n",
             "💰".bold(),
@@ -36,7 +250,219 @@ pub async fn run(
         );
     }
 
-    // 1. fetch sol balance
+    let Holdings {
+        lamports,
+        sol,
+        mut tokens,
+        accounts,
+        reclaimable,
+        rent_locked,
+        ..
+    } = load_holdings(rpc_url, wallet).await?;
+    exclude_mints(&mut tokens, exclude);
+
+    if utils::is_unused(lamports, accounts) {
+        utils::print_unused_wallet("portfolio", &wallet, json);
+        return finish(Alert::check(0.0, alert_below, alert_above), Some(0.0));
+    }
+    let mut mints: Vec<String> = vec![price::SOL_MINT.to_string()];
+    mints.extend(tokens.iter().map(|t| t.mint.clone()));
+
+    // 5. prices
+    if !quiet {
+        println!("  Fetching prices…");
+    }
+    let prices = price::fetch_prices(&mints).await.unwrap_or_default();
+
+    // None: price unavailable, which is not the same as worth $0
+    let sol_price = prices.get(price::SOL_MINT).copied().filter(|p| *p > 0.0);
+    let sol_val = sol_price.map(|p| sol * p);
+
+    for t in &mut tokens {
+        t.set_price(prices.get(&t.mint).copied().unwrap_or(0.0));
+    }
+
+    // 6. sort
+    sort_tokens(&mut tokens, sort, reverse);
+
+    // 7. filter & sum
+    let visible: Vec<&Token> = filter_tokens(
+        &tokens,
+        min_usd,
+        min_balance,
+        hide_unpriced,
+        include_zero_value,
+    );
+    let filtered = visible.len();
+    let (visible, capped) = cap_tokens(visible, top);
+
+    let total_token_usd: f64 = tokens.iter().map(|t| t.value).sum();
+    // rent sitting in empty accounts, reclaimable with `clean`
+    let rent_sol = utils::lamports_to_sol(reclaimable);
+    let rent_usd = sol_price.map(|p| rent_sol * p);
+    let rent_val = if with_rent { rent_usd } else { Some(0.0) };
+    // None without a SOL price: SOL (and rent) can't be valued
+    let total = sol_val
+        .zip(rent_val)
+        .map(|(sol_val, rent_val)| sol_val + total_token_usd + rent_val);
+    let alert = Alert::check_total(total, alert_below, alert_above);
+
+    // optional: compressed NFTs (no token accounts, DAS only)
+    let cnfts = if with_cnfts {
+        Some(das::compressed_asset_count(rpc_url, &wallet).await)
+    } else {
+        None
+    };
+
+    // 8. output
+    if json {
+        let mut out = serde_json::json!({
+            "wallet": wallet.to_string(),
+            "sol": { "balance": sol, "price": sol_price, "value": sol_val },
+            "total_usd": total,
+        });
+        let mut groups = serde_json::json!({ "native": { "value": sol_val } });
+        for (group, count, value) in group_totals(&tokens) {
+            groups[group.key()] = serde_json::json!({ "count": count, "value": value });
+        }
+        out["groups"] = groups;
+        if with_rent {
+            // same shape as scan's `rent`, so integrators can skip the scan call
+            out["rent"] = serde_json::json!({
+                "locked_sol": utils::lamports_to_sol(rent_locked),
+                "reclaimable_sol": rent_sol,
+                "reclaimable_usd": rent_usd,
+            });
+        }
+        if summary {
+            out["token_count"] = tokens.len().into();
+        } else {
+            out["tokens"] = serde_json::json!(tokens);
+        }
+        if let Some(res) = &cnfts {
+            out["compressed_nfts"] = serde_json::json!(res.as_ref().ok().copied().flatten());
+        }
+        if let (Some(a), Some(total)) = (&alert, total) {
+            out["alert"] = a.message(total).into();
+        }
+        utils::print_json("portfolio", out);
+        return finish(alert, total);
+    }
+
+    if summary {
+        println!("{}", summary_line(sol, total, tokens.len()));
+        return finish(alert, total);
+    }
+
+    println!();
+    // SOL
+    println!(
+        "  {} {} {}",
+        "SOL".white().bold(),
+        format!("{sol:.4}").green(),
+        match sol_price {
+            Some(p) => format!(
+                "× {} = {}",
+                utils::format_usd(p),
+                utils::format_usd_opt(sol_val)
+            ),
+            None => "× — (price unavailable)".to_string(),
+        }
+        .dimmed()
+    );
+
+    println!("  {}", "─".repeat(60).dimmed());
+
+    if visible.is_empty() {
+        println!("  {}", "No tokens found".dimmed());
+    } else {
+        for (group, count, subtotal) in group_totals(&tokens) {
+            let rows: Vec<&Token> = visible
+                .iter()
+                .copied()
+                .filter(|t| t.group == group)
+                .collect();
+            if rows.is_empty() {
+                continue;
+            }
+
+            println!(
+                "  {} {}",
+                group.label().white().bold(),
+                format!("({count}) {}", utils::format_usd(subtotal)).dimmed()
+            );
+            for t in rows {
+                print_token(t);
+            }
+        }
+
+        print_capped(&capped);
+        let hidden = tokens.len() - filtered;
+        if hidden > 0 {
+            let mut reasons = Vec::new();
+            if !include_zero_value {
+                reasons.push(format!("< {}", utils::format_usd(min_usd)));
+            }
+            if min_balance > 0.0 {
+                reasons.push(format!("balance < {min_balance}"));
+            }
+            if hide_unpriced {
+                reasons.push("unpriced".to_string());
+            }
+            let reason = reasons.join(" or ");
+            println!("  {} {hidden} tokens hidden ({reason})", "…".dimmed());
+        }
+    }
+
+    println!("  {}", "─".repeat(60).dimmed());
+    if with_rent {
+        println!(
+            "  {} {} {}",
+            "Reclaimable rent:".white(),
+            utils::format_sol(rent_sol).green(),
+            format!("= {}", utils::format_usd_opt(rent_usd)).dimmed()
+        );
+    }
+    match total {
+        _ if utils::is_offline() => {
+            println!("  {} {}", "Total:".white().bold(), "— (offline)".dimmed());
+        }
+        None => println!(
+            "  {} {} {}",
+            "Total:".white().bold(),
+            utils::format_usd(total_token_usd).green().bold(),
+            "(tokens only, SOL price unavailable)".dimmed()
+        ),
+        Some(total) if total > 0.0 => println!(
+            "  {} {}",
+            "Total:".white().bold(),
+            utils::format_usd(total).green().bold()
+        ),
+        Some(_) => {}
+    }
+    if let Some(res) = &cnfts {
+        println!("  🌳 cNFTs: {}", das::describe_count(res));
+    }
+    println!("  {} {} accounts\n", "📊", accounts);
+
+    finish(alert, total)
+}
+
+/// SOL balance and non-empty token accounts of one wallet, not priced yet
+struct Holdings {
+    lamports: u64,
+    sol: f64,
+    tokens: Vec<Token>,
+    accounts: usize,
+    /// Every token account, empty ones included
+    addresses: Vec<Pubkey>,
+    /// Lamports in accounts `clean` could close
+    reclaimable: u64,
+    /// Lamports held by all token accounts
+    rent_locked: u64,
+}
+
+async fn load_holdings(rpc_url: &str, wallet: Pubkey) -> Result<Holdings> {
     // 1. fetch sol balance
     let sol_bal = tokio::task::spawn_blocking({
         let c = crate::rpc::client(rpc_url);
@@ -48,45 +474,40 @@ pub async fn run(
 
     let sol = utils::lamports_to_sol(sol_bal);
 
-    // 2. fetch token accounts
-    let config = RpcProgramAccountsConfig {
-        filters: Some(vec![
-            RpcFilterType::DataSize(165),
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, wallet.to_bytes().to_vec())),
-        ]),
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            commitment: Some(CommitmentConfig::confirmed()),
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    let accounts = tokio::task::spawn_blocking({
-        let c = crate::rpc::client(rpc_url);
-        move || c.get_program_accounts_with_config(&spl_token::id(), config)
-    })
-    .await?
-    .context("Failed to get token accounts")?;
+    // 2. fetch token accounts (classic SPL + Token-2022)
+    let t22: Pubkey = crate::rpc::TOKEN_2022_PROGRAM_ID.parse().unwrap();
+    let mut accounts = Vec::new();
+    for (program, group) in [(spl_token::id(), Group::Spl), (t22, Group::Token2022)] {
+        let accs = tokio::task::spawn_blocking({
+            let c = crate::rpc::client(rpc_url);
+            move || crate::rpc::get_token_accounts(&c, &wallet, &program)
+        })
+        .await?
+        .context("Failed to get token accounts")?;
+        accounts.extend(accs.into_iter().map(|(addr, acc)| (addr, acc, group)));
+    }
 
     // 3. parse tokens
     let mut tokens: Vec<Token> = Vec::new();
-    let mut mints: Vec<String> = vec![price::SOL_MINT.to_string()];
-
-    for (addr, acc) in &accounts {
-        if acc.data.len() < 72 {
-            continue;
-        } // ignore malformed
+    // rent the way `scan` reports it: SPL accounts, default ignore list
+    let spl: Vec<(Pubkey, Account)> = accounts
+        .iter()
+        .filter(|(_, _, group)| *group == Group::Spl)
+        .map(|(addr, acc, _)| (*addr, acc.clone()))
+        .collect();
+    let (rent_locked, reclaimable) = super::scan::rent_totals(&spl, &token::ignored_mints(None)?);
 
-        let mint = Pubkey::try_from(&acc.data[0..32]).unwrap_or_default();
-        let amount = u64::from_le_bytes(acc.data[64..72].try_into().unwrap());
+    for (addr, acc, group) in &accounts {
+        let Some(tok) = token::parse_account(acc) else {
+            continue; // ignore malformed
+        };
+        let amount = tok.amount;
 
         if amount == 0 {
             continue;
         }
 
-        let mint_str = mint.to_string();
-        mints.push(mint_str.clone());
+        let mint_str = tok.mint.to_string();
 
         tokens.push(Token {
             mint: mint_str,
@@ -96,6 +517,10 @@ pub async fn run(
             balance: 0.0,
             price: 0.0,
             value: 0.0,
+            group: *group,
+            transfer_fee_bps: None,
+            transfer_fee_raw: None,
+            net_value: None,
         });
     }
 
@@ -114,151 +539,658 @@ pub async fn run(
             .await?
             .context("Failed to get mint info")?;
 
+            let mut unresolved = Vec::new();
             for (i, m_acc) in mint_accs.iter().enumerate() {
-                if let Some(acc) = m_acc {
-                    if acc.data.len() > 44 {
-                        tokens[i].decimals = acc.data[44];
-                        tokens[i].balance = utils::token_amount(tokens[i].raw, tokens[i].decimals);
+                match m_acc.as_ref().and_then(|acc| mint_decimals(&acc.data)) {
+                    Some(d) => tokens[i].set_decimals(d),
+                    None => unresolved.push(i),
+                }
+            }
+
+            // missing or implausible: let the RPC parse the mint for us
+            for i in unresolved {
+                let c = crate::rpc::client(rpc_url);
+                let mint = pks[i];
+                if let Ok(supply) =
+                    tokio::task::spawn_blocking(move || c.get_token_supply(&mint)).await?
+                {
+                    tokens[i].set_decimals(supply.decimals);
+                }
+            }
+
+            // Token-2022 transfer fees, from the same mint accounts
+            let fees: Vec<Option<token::TransferFeeConfig>> = mint_accs
+                .iter()
+                .zip(&tokens)
+                .map(|(m_acc, t)| {
+                    let acc = m_acc.as_ref().filter(|_| t.group == Group::Token2022)?;
+                    token::transfer_fee_config(&acc.data)
+                })
+                .collect();
+            if fees.iter().any(Option::is_some) {
+                let epoch = tokio::task::spawn_blocking({
+                    let c = crate::rpc::client(rpc_url);
+                    move || c.get_epoch_info()
+                })
+                .await?
+                .context("Failed to get epoch")?
+                .epoch;
+                for (t, cfg) in tokens.iter_mut().zip(fees) {
+                    if let Some(cfg) = cfg {
+                        t.set_transfer_fee(cfg.at(epoch));
                     }
                 }
             }
         }
     }
 
-    // 5. prices
+    Ok(Holdings {
+        lamports: sol_bal,
+        sol,
+        tokens,
+        accounts: accounts.len(),
+        addresses: accounts.iter().map(|(addr, _, _)| *addr).collect(),
+        reclaimable,
+        rent_locked,
+    })
+}
+
+/// Totals printed by `--watch`
+#[derive(PartialEq)]
+struct Snapshot {
+    sol: f64,
+    /// None without a SOL price
+    total: Option<f64>,
+    tokens: usize,
+}
+
+/// Priced totals, plus the wallet and token accounts to subscribe to
+async fn snapshot(rpc_url: &str, wallet: Pubkey) -> Result<(Snapshot, Vec<Pubkey>)> {
+    let h = load_holdings(rpc_url, wallet).await?;
+    let mut mints: Vec<String> = vec![price::SOL_MINT.to_string()];
+    mints.extend(h.tokens.iter().map(|t| t.mint.clone()));
+    let prices = price::fetch_prices(&mints).await.unwrap_or_default();
+    let sol_price = prices.get(price::SOL_MINT).copied().filter(|p| *p > 0.0);
+    let token_value: f64 = h
+        .tokens
+        .iter()
+        .map(|t| t.balance * prices.get(&t.mint).copied().unwrap_or(0.0))
+        .sum();
+
+    let mut watched = vec![wallet];
+    watched.extend(h.addresses);
+    let snap = Snapshot {
+        sol: h.sol,
+        total: sol_price.map(|p| h.sol * p + token_value),
+        tokens: h.tokens.len(),
+    };
+    Ok((snap, watched))
+}
+
+fn print_snapshot(s: &Snapshot, json: bool) {
+    let time = chrono::Local::now().format("%H:%M:%S").to_string();
+    if json {
+        utils::print_json(
+            "portfolio",
+            serde_json::json!({
+                "time": time,
+                "sol": s.sol,
+                "total_usd": s.total,
+                "token_count": s.tokens,
+            }),
+        );
+    } else {
+        println!(
+            "  {} {}",
+            time.dimmed(),
+            summary_line(s.sol, s.total, s.tokens)
+        );
+    }
+}
+
+/// `--watch`: subscribe to the wallet and its token accounts and reprint the
+/// totals when something changes; polls every `interval` seconds instead if
+/// the RPC has no WebSocket endpoint
+async fn watch_wallet(rpc_url: &str, wallet: Pubkey, interval: u64, json: bool) -> Result<()> {
     if !json {
-        println!("  Fetching prices…");
+        println!(
+            "\n{} Watching {} (Ctrl+C to stop)\n",
+            "💰".bold(),
+            utils::short_key(&wallet).cyan()
+        );
     }
-    let prices = price::fetch_prices(&mints).await.unwrap_or_default();
+    let (mut last, watched) = snapshot(rpc_url, wallet).await?;
+    print_snapshot(&last, json);
 
-    let sol_price = prices.get(price::SOL_MINT).copied().unwrap_or(0.0);
-    let sol_val = sol * sol_price;
+    let ws = crate::rpc::ws_url(rpc_url);
+    let pubsub = match PubsubClient::new(&ws).await {
+        Ok(c) => Some(c),
+        Err(e) => {
+            polling_notice(&format!("No WebSocket at {ws} ({e})"), interval, json);
+            None
+        }
+    };
+    let mut subs = match &pubsub {
+        Some(client) => {
+            let mut subs = Subscriptions::new(client);
+            match subs.add(&watched).await {
+                Ok(()) => Some(subs),
+                Err(e) => {
+                    polling_notice(&format!("{e:#}"), interval, json);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
 
-    for t in &mut tokens {
-        t.price = prices.get(&t.mint).copied().unwrap_or(0.0);
-        t.value = t.balance * t.price;
+    let stop = tokio::signal::ctrl_c();
+    tokio::pin!(stop);
+
+    loop {
+        let woke = {
+            let wait = async {
+                match subs.as_mut() {
+                    Some(s) => s.changed().await,
+                    None => {
+                        tokio::time::sleep(Duration::from_secs(interval)).await;
+                        Ok(())
+                    }
+                }
+            };
+            tokio::select! {
+                _ = &mut stop => break,
+                res = wait => res,
+            }
+        };
+        if let Err(e) = woke {
+            polling_notice(&format!("{e:#}"), interval, json);
+            subs = None;
+            continue;
+        }
+
+        let (snap, accounts) = tokio::select! {
+            _ = &mut stop => break,
+            res = snapshot(rpc_url, wallet) => res?,
+        };
+        // prices move too, only print when the totals do
+        if snap != last {
+            print_snapshot(&snap, json);
+            last = snap;
+        }
+        // new token accounts get a subscription, the others stay open
+        if let Some(s) = subs.as_mut() {
+            if let Err(e) = s.add(&accounts).await {
+                polling_notice(&format!("{e:#}"), interval, json);
+                subs = None;
+            }
+        }
     }
+    Ok(())
+}
 
-    // 6. sort
-    sort_tokens(&mut tokens, sort);
+/// `--watch` without (or after losing) the WebSocket
+fn polling_notice(reason: &str, interval: u64, json: bool) {
+    if !json {
+        println!("  {} {reason}, polling every {interval}s", "⚠".yellow());
+    }
+}
 
-    // 7. filter & sum
-    let visible: Vec<&Token> = filter_tokens(&tokens, min_usd);
+/// `accountSubscribe` streams for `--watch`, open for the whole run
+struct Subscriptions<'a> {
+    client: &'a PubsubClient,
+    streams: SelectAll<BoxStream<'a, Response<UiAccount>>>,
+    subscribed: HashSet<Pubkey>,
+}
 
-    let total_token_usd: f64 = tokens.iter().map(|t| t.value).sum();
-    let total = sol_val + total_token_usd;
+impl<'a> Subscriptions<'a> {
+    fn new(client: &'a PubsubClient) -> Self {
+        Self {
+            client,
+            streams: SelectAll::new(),
+            subscribed: HashSet::new(),
+        }
+    }
 
-    // 8. output
-    if json {
+    /// Subscribe to the accounts not watched yet
+    async fn add(&mut self, accounts: &[Pubkey]) -> Result<()> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(crate::rpc::commitment()),
+            ..Default::default()
+        };
+        for account in accounts {
+            if self.subscribed.contains(account) {
+                continue;
+            }
+            let (stream, _unsubscribe) = self
+                .client
+                .account_subscribe(account, Some(config.clone()))
+                .await
+                .context("accountSubscribe failed")?;
+            self.streams.push(stream);
+            self.subscribed.insert(*account);
+        }
+        Ok(())
+    }
+
+    /// Next notification on any account; an error once the socket is gone
+    async fn changed(&mut self) -> Result<()> {
+        self.streams
+            .next()
+            .await
+            .map(|_| ())
+            .context("WebSocket subscription closed")
+    }
+}
+
+/// Sorting and filtering of the token list
+struct View<'a> {
+    min_usd: f64,
+    min_balance: f64,
+    sort: &'a str,
+    reverse: bool,
+    hide_unpriced: bool,
+    include_zero_value: bool,
+    top: Option<usize>,
+    exclude: &'a [Pubkey],
+}
+
+/// Per-wallet subtotal in `--file` mode
+struct WalletTotal {
+    wallet: Pubkey,
+    sol: f64,
+    token_value: f64,
+    tokens: usize,
+}
+
+/// `--file`: one portfolio across many wallets, priced in a single pass
+async fn run_batch(
+    rpc_url: &str,
+    path: &str,
+    view: &View<'_>,
+    summary: bool,
+    alert_below: Option<f64>,
+    alert_above: Option<f64>,
+    json: bool,
+) -> Result<Option<i32>> {
+    let wallets = read_wallet_file(path)?;
+    let quiet = json || summary;
+
+    if !quiet {
         println!(
-            "{}",
-            serde_json::json!({
-                "wallet": wallet_str,
-                "sol": { "balance": sol, "price": sol_price, "value": sol_val },
-                "tokens": tokens,
-                "total_usd": total,
-            })
+            "\n{} Loading portfolio for {} wallets from {}…\n",
+            "💰".bold(),
+            wallets.len().to_string().cyan(),
+            path.dimmed()
         );
-        return Ok(());
     }
 
-    println!();
-    // SOL
-    println!(
-        "  {} {} {}",
-        "SOL".white().bold(),
-        format!("{sol:.4}").green(),
-        if sol_price > 0.0 {
-            format!(
-                "× {} = {}",
-                utils::format_usd(sol_price),
-                utils::format_usd(sol_val)
-            )
-            .dimmed()
-            .to_string()
+    let sem = Semaphore::new(BATCH_CONCURRENCY);
+    let sem = &sem;
+    let results = futures::future::join_all(wallets.iter().map(|w| async move {
+        let _permit = sem.acquire().await?;
+        let mut h = load_holdings(rpc_url, *w).await?;
+        exclude_mints(&mut h.tokens, view.exclude);
+        Ok::<_, anyhow::Error>(h)
+    }))
+    .await;
+
+    // one price request for the combined mint set
+    let mut mints: Vec<String> = vec![price::SOL_MINT.to_string()];
+    for h in results.iter().flatten() {
+        mints.extend(h.tokens.iter().map(|t| t.mint.clone()));
+    }
+    mints.sort();
+    mints.dedup();
+    if !quiet {
+        println!("  Fetching prices for {} mints…", mints.len());
+    }
+    let prices = price::fetch_prices(&mints).await.unwrap_or_default();
+    let sol_usd = prices.get(price::SOL_MINT).copied().filter(|p| *p > 0.0);
+
+    let mut totals = Vec::new();
+    let mut all_tokens = Vec::new();
+    for (wallet, res) in wallets.iter().zip(results) {
+        let Ok(mut h) = res else { continue };
+        for t in &mut h.tokens {
+            t.set_price(prices.get(&t.mint).copied().unwrap_or(0.0));
+        }
+        totals.push(WalletTotal {
+            wallet: *wallet,
+            sol: h.sol,
+            token_value: h.tokens.iter().map(|t| t.value).sum(),
+            tokens: h.tokens.len(),
+        });
+        all_tokens.extend(h.tokens);
+    }
+    let failed = wallets.len() - totals.len();
+
+    let mut tokens = merge_tokens(&all_tokens);
+    sort_tokens(&mut tokens, view.sort, view.reverse);
+
+    let sol: f64 = totals.iter().map(|w| w.sol).sum();
+    let sol_val = sol_usd.map(|p| sol * p);
+    let token_usd: f64 = tokens.iter().map(|t| t.value).sum();
+    let total = sol_val.map(|v| v + token_usd);
+    let alert = Alert::check_total(total, alert_below, alert_above);
+
+    if json {
+        let per_wallet: Vec<serde_json::Value> = totals
+            .iter()
+            .map(|w| {
+                let sol_value = sol_usd.map(|p| w.sol * p);
+                serde_json::json!({
+                    "wallet": w.wallet.to_string(),
+                    "sol": w.sol,
+                    "sol_value": sol_value,
+                    "token_value": w.token_value,
+                    "token_count": w.tokens,
+                    "total_usd": sol_value.map(|v| v + w.token_value),
+                })
+            })
+            .collect();
+        let mut out = serde_json::json!({
+            "wallets": per_wallet,
+            "aggregate": {
+                "wallets": wallets.len(),
+                "failed": failed,
+                "sol": { "balance": sol, "price": sol_usd, "value": sol_val },
+                "total_usd": total,
+            },
+        });
+        if summary {
+            out["aggregate"]["token_count"] = tokens.len().into();
         } else {
-            "".into()
+            let list: Vec<serde_json::Value> = tokens
+                .iter()
+                .map(|t| {
+                    let mut row = serde_json::json!({
+                        "mint": t.mint,
+                        "balance": t.balance,
+                        "price": t.price,
+                        "value": t.value,
+                        "group": t.group,
+                    });
+                    if let Some(net) = t.net_value {
+                        row["transfer_fee_bps"] = t.transfer_fee_bps.into();
+                        row["net_value"] = net.into();
+                    }
+                    row
+                })
+                .collect();
+            out["aggregate"]["tokens"] = list.into();
+        }
+        if let (Some(a), Some(total)) = (&alert, total) {
+            out["alert"] = a.message(total).into();
         }
+        utils::print_json("portfolio", out);
+        return finish(alert, total);
+    }
+
+    if summary {
+        println!("{}", summary_line(sol, total, tokens.len()));
+        return finish(alert, total);
+    }
+
+    println!();
+    for w in &totals {
+        let total_usd = sol_usd.map(|p| w.sol * p + w.token_value);
+        println!(
+            "  {} {} {} {}",
+            utils::short_key(&w.wallet).cyan(),
+            format!("{:.4} SOL", w.sol).green(),
+            format!("{} tokens", w.tokens).dimmed(),
+            utils::format_usd_opt(total_usd).white().bold()
+        );
+    }
+    if failed > 0 {
+        println!("  {} {failed} wallets failed to load", "⚠".yellow());
+    }
+
+    println!("  {}", "─".repeat(60).dimmed());
+    let visible = filter_tokens(
+        &tokens,
+        view.min_usd,
+        view.min_balance,
+        view.hide_unpriced,
+        view.include_zero_value,
     );
+    let filtered = visible.len();
+    let (visible, capped) = cap_tokens(visible, view.top);
+    for t in &visible {
+        print_token(t);
+    }
+    print_capped(&capped);
+    let hidden = tokens.len() - filtered;
+    if hidden > 0 {
+        println!("  {} {hidden} tokens hidden", "…".dimmed());
+    }
 
     println!("  {}", "─".repeat(60).dimmed());
+    match total {
+        Some(total) => println!(
+            "  {} {} ({:.4} SOL = {})",
+            "Grand total:".white().bold(),
+            utils::format_usd(total).green().bold(),
+            sol,
+            utils::format_usd_opt(sol_val)
+        ),
+        None => println!(
+            "  {} {} {}",
+            "Grand total:".white().bold(),
+            utils::format_usd(token_usd).green().bold(),
+            format!("(tokens only, {sol:.4} SOL unpriced)").dimmed()
+        ),
+    }
+    println!();
 
-    if visible.is_empty() {
-        println!("  {}", "No tokens found".dimmed());
-    } else {
-        for t in &visible {
-            let short = format!("{}…{}", &t.mint[..6], &t.mint[t.mint.len() - 4..]);
-            let p_str = if t.price > 0.0 {
-                format!(
-                    "× {} = {}",
-                    utils::format_usd(t.price),
-                    utils::format_usd(t.value)
-                )
-                .dimmed()
-                .to_string()
-            } else {
-                "(no price)".dimmed().to_string()
-            };
+    finish(alert, total)
+}
 
-            let b_str = if t.balance < 0.001 {
-                format!("{:.9}", t.balance)
-            } else if t.balance < 1.0 {
-                format!("{:.6}", t.balance)
-            } else if t.balance < 100_000.0 {
-                format!("{:.2}", t.balance)
-            } else {
-                format!("{:.0}", t.balance)
-            };
+/// Public keys, one per line (`#` comments, extra CSV columns ignored)
+fn read_wallet_file(path: &str) -> Result<Vec<Pubkey>> {
+    let text = std::fs::read_to_string(path).context(format!("Failed to open file: {path}"))?;
+    let wallets = parse_wallet_list(&text);
+    if wallets.is_empty() {
+        anyhow::bail!("No valid wallets found in {path}");
+    }
+    Ok(wallets)
+}
 
-            println!("  {} {} {}", short.white(), b_str.green(), p_str);
+fn parse_wallet_list(text: &str) -> Vec<Pubkey> {
+    let mut wallets: Vec<Pubkey> = Vec::new();
+    for (line_num, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
         }
-
-        let hidden = tokens.len() - visible.len();
-        if hidden > 0 {
-            println!(
-                "  {} {hidden} small tokens hidden (< {})",
-                "…".dimmed(),
-                utils::format_usd(min_usd)
-            );
+        let first = line.split(',').next().unwrap_or_default().trim();
+        match utils::parse_pubkey(first) {
+            Ok(pk) if !wallets.contains(&pk) => wallets.push(pk),
+            Ok(_) => {}
+            Err(e) => eprintln!("{}", format!("⚠ Line {}: {e}", line_num + 1).yellow()),
         }
     }
+    wallets
+}
 
-    println!("  {}", "─".repeat(60).dimmed());
-    if total > 0.0 {
-        println!(
-            "  {} {}",
-            "Total:".white().bold(),
-            utils::format_usd(total).green().bold()
-        );
+/// Combine holdings of the same mint across wallets
+fn merge_tokens(tokens: &[Token]) -> Vec<Token> {
+    let mut merged: Vec<Token> = Vec::new();
+    for t in tokens {
+        match merged.iter_mut().find(|m| m.mint == t.mint) {
+            Some(m) => {
+                m.raw = m.raw.saturating_add(t.raw);
+                m.balance += t.balance;
+                m.value += t.value;
+                if let (Some(a), Some(b)) = (&mut m.transfer_fee_raw, t.transfer_fee_raw) {
+                    *a = a.saturating_add(b);
+                }
+                if let (Some(a), Some(b)) = (&mut m.net_value, t.net_value) {
+                    *a += b;
+                }
+            }
+            None => merged.push(Token {
+                account: String::new(),
+                ..t.clone()
+            }),
+        }
     }
-    println!("  {} {} accounts\n", "📊", accounts.len());
+    merged
+}
 
-    Ok(())
+/// Decimals byte of a mint account, `None` if it looks wrong
+fn mint_decimals(data: &[u8]) -> Option<u8> {
+    data.get(44).copied().filter(|d| *d <= MAX_DECIMALS)
+}
+
+fn print_token(t: &Token) {
+    let short = format!("{}…{}", &t.mint[..6], &t.mint[t.mint.len() - 4..]);
+    let p_str = if t.price > 0.0 {
+        format!(
+            "× {} = {}",
+            utils::format_usd(t.price),
+            utils::format_usd(t.value)
+        )
+        .dimmed()
+        .to_string()
+    } else {
+        "(no price)".dimmed().to_string()
+    };
+    let fee_str = match (t.transfer_fee_bps, t.net_value) {
+        (Some(bps), Some(net)) if bps > 0 && t.price > 0.0 => format!(
+            " · net {} after {}% fee",
+            utils::format_usd(net),
+            bps as f64 / 100.0
+        )
+        .yellow()
+        .to_string(),
+        _ => String::new(),
+    };
+
+    let b_str = if t.balance < 0.001 {
+        format!("{:.9}", t.balance)
+    } else if t.balance < 1.0 {
+        format!("{:.6}", t.balance)
+    } else if t.balance < 100_000.0 {
+        format!("{:.2}", t.balance)
+    } else {
+        format!("{:.0}", t.balance)
+    };
+
+    println!(
+        "    {} {} {}{}",
+        short.white(),
+        b_str.green(),
+        p_str,
+        fee_str
+    );
 }
 
-fn sort_tokens(tokens: &mut [Token], sort_by: &str) {
-    match sort_by {
-        "name" => tokens.sort_by(|a, b| a.mint.cmp(&b.mint)),
-        "balance" => tokens.sort_by(|a, b| {
-            b.balance
-                .partial_cmp(&a.balance)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        }),
-        _ => tokens.sort_by(|a, b| {
-            b.value
-                .partial_cmp(&a.value)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        }),
+/// Token count and USD subtotal per non-empty group
+fn group_totals(tokens: &[Token]) -> Vec<(Group, usize, f64)> {
+    Group::ALL
+        .into_iter()
+        .map(|g| {
+            let members = tokens.iter().filter(|t| t.group == g);
+            (g, members.clone().count(), members.map(|t| t.value).sum())
+        })
+        .filter(|(_, count, _)| *count > 0)
+        .collect()
+}
+
+/// `12.3400 SOL | $1,234.56 total | 7 tokens`, `—` for the total without
+/// a SOL price
+fn summary_line(sol: f64, total_usd: Option<f64>, tokens: usize) -> String {
+    format!(
+        "{sol:.4} SOL | {} total | {tokens} tokens",
+        utils::format_usd_opt(total_usd)
+    )
+}
+
+/// Value / balance descending, name ascending; `reverse` flips either
+fn sort_tokens(tokens: &mut [Token], sort_by: &str, reverse: bool) {
+    let cmp = |a: &Token, b: &Token| match sort_by {
+        "name" => a.mint.cmp(&b.mint),
+        "balance" => b
+            .balance
+            .partial_cmp(&a.balance)
+            .unwrap_or(std::cmp::Ordering::Equal),
+        _ => b
+            .value
+            .partial_cmp(&a.value)
+            .unwrap_or(std::cmp::Ordering::Equal),
+    };
+    if reverse {
+        tokens.sort_by(|a, b| cmp(a, b).reverse());
+    } else {
+        tokens.sort_by(cmp);
     }
 }
 
-fn filter_tokens(tokens: &[Token], min_usd: f64) -> Vec<&Token> {
+/// Visible tokens:
+/// - `hide_unpriced` always drops tokens without a price
+/// - `min_balance` always drops smaller balances, priced or not
+/// - `include_zero_value` ignores `min_usd` entirely
+/// - otherwise keep tokens worth >= `min_usd`, plus unpriced ones with a balance
+fn filter_tokens(
+    tokens: &[Token],
+    min_usd: f64,
+    min_balance: f64,
+    hide_unpriced: bool,
+    include_zero_value: bool,
+) -> Vec<&Token> {
     tokens
         .iter()
-        .filter(|t| t.value >= min_usd || (t.price == 0.0 && t.balance > 0.0))
+        .filter(|t| {
+            let unpriced = t.price == 0.0;
+            if hide_unpriced && unpriced {
+                return false;
+            }
+            if t.balance < min_balance {
+                return false;
+            }
+            if include_zero_value {
+                return true;
+            }
+            t.value >= min_usd || (unpriced && t.balance > 0.0)
+        })
         .collect()
 }
 
+/// `--exclude`: drop the listed mints, so they count nowhere
+fn exclude_mints(tokens: &mut Vec<Token>, exclude: &[Pubkey]) {
+    if exclude.is_empty() {
+        return;
+    }
+    let exclude: Vec<String> = exclude.iter().map(Pubkey::to_string).collect();
+    tokens.retain(|t| !exclude.contains(&t.mint));
+}
+
+/// `--top`: the first `n` of an already sorted list, the rest as the tail
+fn cap_tokens(visible: Vec<&Token>, top: Option<usize>) -> (Vec<&Token>, Vec<&Token>) {
+    let mut head = visible;
+    let tail = match top {
+        Some(n) if n < head.len() => head.split_off(n),
+        _ => Vec::new(),
+    };
+    (head, tail)
+}
+
+fn print_capped(capped: &[&Token]) {
+    if capped.is_empty() {
+        return;
+    }
+    let value: f64 = capped.iter().map(|t| t.value).sum();
+    println!(
+        "  {} and {} more tokens worth {}",
+        "…".dimmed(),
+        capped.len(),
+        utils::format_usd(value)
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,9 +1204,33 @@ mod tests {
             balance,
             price,
             value,
+            group: Group::Spl,
+            transfer_fee_bps: None,
+            transfer_fee_raw: None,
+            net_value: None,
         }
     }
 
+    #[test]
+    fn test_set_price_net_of_transfer_fee() {
+        let mut t = mock_token("A", 0.0, 0.0, 0.0);
+        t.raw = 10_000_000;
+        t.set_decimals(6);
+        t.set_price(2.0);
+        assert_eq!(t.value, 20.0);
+        assert_eq!(t.net_value, None);
+
+        // 1% fee on 10 tokens = 0.1 withheld
+        t.set_transfer_fee(token::TransferFee {
+            epoch: 0,
+            maximum_fee: u64::MAX,
+            basis_points: 100,
+        });
+        t.set_price(2.0);
+        assert_eq!(t.value, 20.0);
+        assert!((t.net_value.unwrap() - 19.8).abs() < 1e-9);
+    }
+
     #[test]
     fn test_sort_tokens() {
         let mut tokens = vec![
@@ -284,24 +1240,46 @@ mod tests {
         ];
 
         // Default (Value desc)
-        sort_tokens(&mut tokens, "default");
+        sort_tokens(&mut tokens, "default", false);
         assert_eq!(tokens[0].mint, "B"); // 50
         assert_eq!(tokens[1].mint, "A"); // 10
         assert_eq!(tokens[2].mint, "C"); // 5
 
         // Balance desc
-        sort_tokens(&mut tokens, "balance");
+        sort_tokens(&mut tokens, "balance", false);
         assert_eq!(tokens[0].mint, "C"); // 20
         assert_eq!(tokens[1].mint, "A"); // 10
         assert_eq!(tokens[2].mint, "B"); // 5
 
         // Name asc
-        sort_tokens(&mut tokens, "name");
+        sort_tokens(&mut tokens, "name", false);
         assert_eq!(tokens[0].mint, "A");
         assert_eq!(tokens[1].mint, "B");
         assert_eq!(tokens[2].mint, "C");
     }
 
+    #[test]
+    fn test_sort_tokens_reverse() {
+        let mut tokens = vec![
+            mock_token("A", 10.0, 10.0, 1.0),
+            mock_token("B", 5.0, 50.0, 10.0),
+            mock_token("C", 20.0, 5.0, 0.25),
+        ];
+        let mints = |t: &[Token]| t.iter().map(|t| t.mint.clone()).collect::<Vec<_>>();
+
+        // Value asc: dust first
+        sort_tokens(&mut tokens, "value", true);
+        assert_eq!(mints(&tokens), ["C", "A", "B"]);
+
+        // Balance asc
+        sort_tokens(&mut tokens, "balance", true);
+        assert_eq!(mints(&tokens), ["B", "A", "C"]);
+
+        // Name desc
+        sort_tokens(&mut tokens, "name", true);
+        assert_eq!(mints(&tokens), ["C", "B", "A"]);
+    }
+
     #[test]
     fn test_filter_tokens() {
         let tokens = vec![
@@ -316,9 +1294,194 @@ mod tests {
         // LowVal (0.5 < 1) -> Drop
         // NoPrice (0 val, but bal > 0) -> Keep
         // Dust (0 val, 0 bal) -> Drop
-        let visible = filter_tokens(&tokens, 1.0);
+        let visible = filter_tokens(&tokens, 1.0, 0.0, false, false);
         assert_eq!(visible.len(), 2);
         assert_eq!(visible[0].mint, "HighVal");
         assert_eq!(visible[1].mint, "NoPrice");
     }
+
+    #[test]
+    fn test_filter_tokens_hide_unpriced() {
+        let tokens = vec![
+            mock_token("HighVal", 10.0, 100.0, 10.0),
+            mock_token("LowVal", 10.0, 0.5, 0.05),
+            mock_token("NoPrice", 10.0, 0.0, 0.0),
+        ];
+
+        // min_usd still applies to priced tokens
+        let visible = filter_tokens(&tokens, 1.0, 0.0, true, false);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].mint, "HighVal");
+
+        // with include_zero_value: every priced token, no unpriced ones
+        let visible = filter_tokens(&tokens, 1.0, 0.0, true, true);
+        assert_eq!(visible.len(), 2);
+        assert_eq!(visible[0].mint, "HighVal");
+        assert_eq!(visible[1].mint, "LowVal");
+    }
+
+    #[test]
+    fn test_exclude_mints() {
+        let usdc = token::resolve_mint("USDC").unwrap();
+        let keep = Pubkey::new_unique();
+        let mut tokens = vec![
+            mock_token(&usdc.to_string(), 10.0, 1.0, 10.0),
+            mock_token(&keep.to_string(), 5.0, 2.0, 10.0),
+        ];
+
+        exclude_mints(&mut tokens, &[]);
+        assert_eq!(tokens.len(), 2);
+
+        exclude_mints(&mut tokens, &[usdc]);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].mint, keep.to_string());
+    }
+
+    #[test]
+    fn test_filter_tokens_include_zero_value() {
+        let tokens = vec![
+            mock_token("HighVal", 10.0, 100.0, 10.0),
+            mock_token("LowVal", 10.0, 0.5, 0.05),
+            mock_token("NoPrice", 10.0, 0.0, 0.0),
+            mock_token("Dust", 0.0, 0.0, 0.0),
+        ];
+
+        // min_usd is ignored, everything shows
+        let visible = filter_tokens(&tokens, 1.0, 0.0, false, true);
+        assert_eq!(visible.len(), 4);
+    }
+
+    #[test]
+    fn test_filter_tokens_min_balance() {
+        let tokens = vec![
+            mock_token("HighVal", 10.0, 100.0, 10.0),
+            mock_token("Spam", 0.5, 0.0, 0.0),
+            mock_token("NoPrice", 10.0, 0.0, 0.0),
+            mock_token("TinyButPricey", 0.01, 50.0, 5000.0),
+        ];
+
+        // unpriced spam below 1 unit is gone, regardless of price
+        let visible = filter_tokens(&tokens, 1.0, 1.0, false, false);
+        let mints: Vec<&str> = visible.iter().map(|t| t.mint.as_str()).collect();
+        assert_eq!(mints, vec!["HighVal", "NoPrice"]);
+
+        // still applies with --include-zero-value
+        assert_eq!(filter_tokens(&tokens, 1.0, 1.0, false, true).len(), 2);
+    }
+
+    #[test]
+    fn test_group_totals() {
+        let mut t22 = mock_token("T22", 1.0, 7.5, 7.5);
+        t22.group = Group::Token2022;
+        let tokens = vec![
+            mock_token("A", 1.0, 10.0, 10.0),
+            t22,
+            mock_token("B", 1.0, 2.5, 2.5),
+        ];
+
+        let totals = group_totals(&tokens);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0], (Group::Spl, 2, 12.5));
+        assert_eq!(totals[1], (Group::Token2022, 1, 7.5));
+
+        // empty groups are left out
+        assert!(group_totals(&tokens[..1])
+            .iter()
+            .all(|(g, _, _)| *g == Group::Spl));
+    }
+
+    #[test]
+    fn test_mint_decimals() {
+        let mut data = vec![0u8; 82];
+        data[44] = 6;
+        assert_eq!(mint_decimals(&data), Some(6));
+
+        data[44] = 200;
+        assert_eq!(mint_decimals(&data), None);
+        assert_eq!(mint_decimals(&data[..40]), None);
+    }
+
+    #[test]
+    fn test_alert_check() {
+        assert_eq!(
+            Alert::check(500.0, Some(1000.0), Some(5000.0)),
+            Some(Alert::Below(1000.0))
+        );
+        assert_eq!(
+            Alert::check(6000.0, Some(1000.0), Some(5000.0)),
+            Some(Alert::Above(5000.0))
+        );
+        assert_eq!(Alert::check(2000.0, Some(1000.0), Some(5000.0)), None);
+        assert_eq!(Alert::check(1000.0, Some(1000.0), None), None);
+        assert_eq!(Alert::check(1e9, None, None), None);
+        // no SOL price: not checked rather than alerting on a partial total
+        assert_eq!(Alert::check_total(None, Some(1000.0), None), None);
+        assert_eq!(
+            Alert::check_total(Some(500.0), Some(1000.0), None),
+            Some(Alert::Below(1000.0))
+        );
+
+        assert_ne!(Alert::Below(1.0).exit_code(), Alert::Above(1.0).exit_code());
+    }
+
+    #[test]
+    fn test_merge_tokens() {
+        let mut a2 = mock_token("A", 2.0, 4.0, 2.0);
+        a2.raw = 2;
+        let mut a1 = mock_token("A", 1.0, 2.0, 2.0);
+        a1.raw = 1;
+        let merged = merge_tokens(&[a1, mock_token("B", 5.0, 0.0, 0.0), a2]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].mint, "A");
+        assert_eq!(merged[0].raw, 3);
+        assert_eq!(merged[0].balance, 3.0);
+        assert_eq!(merged[0].value, 6.0);
+        assert_eq!(merged[1].mint, "B");
+    }
+
+    #[test]
+    fn test_parse_wallet_list() {
+        let a = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+        let b = "11111111111111111111111111111111";
+        let text = format!("# wallets\n{a}\n\n  {b} \nnot-a-key\n{a},extra\n");
+
+        let wallets = parse_wallet_list(&text);
+        assert_eq!(wallets, vec![a.parse().unwrap(), b.parse().unwrap()]);
+    }
+
+    #[test]
+    fn test_cap_tokens() {
+        let tokens = [
+            mock_token("A", 1.0, 30.0, 30.0),
+            mock_token("B", 1.0, 20.0, 20.0),
+            mock_token("C", 1.0, 10.0, 10.0),
+        ];
+        let all: Vec<&Token> = tokens.iter().collect();
+
+        let (head, tail) = cap_tokens(all.clone(), Some(1));
+        assert_eq!(head.len(), 1);
+        assert_eq!(head[0].mint, "A");
+        assert_eq!(tail.iter().map(|t| t.value).sum::<f64>(), 30.0);
+
+        let (head, tail) = cap_tokens(all.clone(), Some(5));
+        assert_eq!(head.len(), 3);
+        assert!(tail.is_empty());
+
+        let (head, tail) = cap_tokens(all, None);
+        assert_eq!(head.len(), 3);
+        assert!(tail.is_empty());
+    }
+
+    #[test]
+    fn test_summary_line() {
+        assert_eq!(
+            summary_line(12.34, Some(1234.5), 7),
+            "12.3400 SOL | $1,234.50 total | 7 tokens"
+        );
+        assert_eq!(
+            summary_line(1.0, None, 0),
+            "1.0000 SOL | — total | 0 tokens"
+        );
+    }
 }
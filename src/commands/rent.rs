@@ -1,5 +1,5 @@
 use crate::{price, utils};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
 
 struct RentEntry {
@@ -62,7 +62,22 @@ const COMMON_ACCOUNTS: &[RentEntry] = &[
     },
 ];
 
-pub async fn run(rpc_url: &str, size: Option<usize>, json: bool) -> Result<()> {
+/// `--net`: rent is a deposit, closing the account gives all of it back
+const NET_NOTE: &str =
+    "Rent is a refundable deposit, not a fee: closing the account returns all of it (net cost 0, only tx fees)";
+
+/// Rent to fund `count` accounts, `None` on overflow
+fn total_rent(per_account: u64, count: u64) -> Option<u64> {
+    per_account.checked_mul(count)
+}
+
+pub async fn run(
+    rpc_url: &str,
+    size: Option<usize>,
+    count: u64,
+    net: bool,
+    json: bool,
+) -> Result<()> {
     // 1. fetch reference rent (cost/byte)
     let rent_per_byte = tokio::task::spawn_blocking({
         let c = crate::rpc::client(rpc_url);
@@ -74,7 +89,7 @@ pub async fn run(rpc_url: &str, size: Option<usize>, json: bool) -> Result<()> {
     })
     .await??;
 
-    let sol_usd = price::sol_price().await.unwrap_or(0.0);
+    let sol_usd = price::sol_price().await;
 
     // 2. if specific size requested
     if let Some(s) = size {
@@ -85,30 +100,45 @@ pub async fn run(rpc_url: &str, size: Option<usize>, json: bool) -> Result<()> {
         .await??;
 
         let sol = utils::lamports_to_sol(lamports);
+        let total = total_rent(lamports, count).context("Total rent overflows u64")?;
+        let total_sol = utils::lamports_to_sol(total);
+        let usd = |sol: f64| utils::approx_usd(sol, sol_usd).dimmed();
 
         if json {
-            println!(
-                "{}",
-                serde_json::json!({
-                    "size_bytes": s,
-                    "lamports": lamports,
-                    "sol": sol,
-                    "usd": sol * sol_usd,
-                })
-            );
+            let mut out = serde_json::json!({
+                "size_bytes": s,
+                "lamports": lamports,
+                "sol": sol,
+                "usd": sol_usd.map(|p| sol * p),
+                "count": count,
+                "total_lamports": total,
+                "total_sol": total_sol,
+                "total_usd": sol_usd.map(|p| total_sol * p),
+            });
+            if net {
+                out["refunded_on_close_lamports"] = total.into();
+                out["net_cost_lamports"] = 0.into();
+            }
+            utils::print_json("rent", out);
         } else {
             println!(
-                "\n  Rent-exempt minimum for {} bytes: {} {}\n",
+                "\n  Rent-exempt minimum for {} bytes: {} {}",
                 s.to_string().cyan(),
                 utils::format_sol(sol).green().bold(),
-                if sol_usd > 0.0 {
-                    format!("(≈ {})", utils::format_usd(sol * sol_usd))
-                        .dimmed()
-                        .to_string()
-                } else {
-                    "".into()
-                }
+                usd(sol)
             );
+            if count > 1 {
+                println!(
+                    "  To fund {} accounts: {} {}",
+                    count.to_string().cyan(),
+                    utils::format_sol(total_sol).green().bold(),
+                    usd(total_sol)
+                );
+            }
+            if net {
+                println!("  {} {}", "ℹ".dimmed(), NET_NOTE.dimmed());
+            }
+            println!();
         }
         return Ok(());
     }
@@ -130,16 +160,16 @@ pub async fn run(rpc_url: &str, size: Option<usize>, json: bool) -> Result<()> {
                 "size": e.size,
                 "lamports": lamports,
                 "sol": sol,
-                "usd": sol * sol_usd,
+                "usd": sol_usd.map(|p| sol * p),
             }));
         }
-        println!(
-            "{}",
+        utils::print_json(
+            "rent",
             serde_json::json!({
                 "rent_per_byte": rent_per_byte,
                 "sol_price": sol_usd,
                 "accounts": entries,
-            })
+            }),
         );
         return Ok(());
     }
@@ -147,13 +177,7 @@ pub async fn run(rpc_url: &str, size: Option<usize>, json: bool) -> Result<()> {
     println!(
         "\n{} Solana Rent-Exempt Minimums {}\n",
         "🏦".bold(),
-        if sol_usd > 0.0 {
-            format!("(SOL = {})", utils::format_usd(sol_usd))
-                .dimmed()
-                .to_string()
-        } else {
-            "".into()
-        }
+        format!("(SOL = {})", utils::format_usd_opt(sol_usd)).dimmed()
     );
 
     println!(
@@ -180,12 +204,7 @@ pub async fn run(rpc_url: &str, size: Option<usize>, json: bool) -> Result<()> {
             e.name.white(),
             e.size.to_string().dimmed(),
             format!("{sol:.6}").green(),
-            if sol_usd > 0.0 {
-                utils::format_usd(sol * sol_usd)
-            } else {
-                "—".into()
-            }
-            .dimmed(),
+            utils::format_usd_opt(sol_usd.map(|p| sol * p)).dimmed(),
         );
     }
 
@@ -194,6 +213,9 @@ pub async fn run(rpc_url: &str, size: Option<usize>, json: bool) -> Result<()> {
         "ℹ".dimmed(),
         rent_per_byte
     );
+    if net {
+        println!("  {} {}\n", "ℹ".dimmed(), NET_NOTE.dimmed());
+    }
 
     Ok(())
 }
@@ -217,6 +239,13 @@ mod tests {
         assert_eq!(r, 6960.0);
     }
 
+    #[test]
+    fn test_total_rent() {
+        assert_eq!(total_rent(2_039_280, 50), Some(101_964_000));
+        assert_eq!(total_rent(2_039_280, 1), Some(2_039_280));
+        assert_eq!(total_rent(u64::MAX, 2), None);
+    }
+
     #[test]
     fn test_calculate_rent_zero_bytes() {
         let r = calculate_rent_per_byte(100, 100, 0);
@@ -1,15 +1,112 @@
 use anyhow::Result;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{HashMap, VecDeque};
 use std::time::{Duration, Instant};
 
-use crate::rpc;
+use crate::{rpc, utils};
+
+const HISTOGRAM_BINS: usize = 10;
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// `--watch`: cycles averaged per endpoint, so one slow run doesn't dominate
+const ROLLING_WINDOW: usize = 10;
+
+/// Known RPC providers by domain, for `--group-by-provider`
+const PROVIDERS: &[(&str, &str)] = &[
+    ("helius-rpc.com", "Helius"),
+    ("helius.xyz", "Helius"),
+    ("quiknode.pro", "QuickNode"),
+    ("alchemy.com", "Alchemy"),
+    ("rpcpool.com", "Triton"),
+    ("ankr.com", "Ankr"),
+    ("chainstack.com", "Chainstack"),
+    ("syndica.io", "Syndica"),
+    ("solana.com", "Solana Foundation"),
+];
+
+/// Exit codes for `--min-success` / `--max-avg`
+const EXIT_MIN_SUCCESS: i32 = 12;
+const EXIT_MAX_AVG: i32 = 13;
+
+/// CI health gate: thresholds the best endpoint has to meet
+#[derive(Default)]
+pub struct HealthGate {
+    /// Highest success rate must reach this, in percent
+    pub min_success: Option<f64>,
+    /// Fastest average latency must stay at or under this, in ms
+    pub max_avg: Option<f64>,
+}
+
+impl HealthGate {
+    fn check(&self, results: &[BenchResult]) -> Vec<GateFailure> {
+        let mut failed = Vec::new();
+        if let Some(min) = self.min_success {
+            let best = results.iter().map(|r| r.success_rate()).fold(0.0, f64::max);
+            if best < min {
+                failed.push(GateFailure::Success { best, min });
+            }
+        }
+        if let Some(max) = self.max_avg {
+            // results are sorted, fastest first; no answers means no average
+            let fastest = results.first().map(|r| r.avg()).filter(|a| *a != f64::MAX);
+            if fastest.is_none_or(|a| a > max) {
+                failed.push(GateFailure::Latency { fastest, max });
+            }
+        }
+        failed
+    }
+}
+
+/// A threshold the best endpoint missed
+#[derive(Debug, PartialEq)]
+enum GateFailure {
+    Success { best: f64, min: f64 },
+    Latency { fastest: Option<f64>, max: f64 },
+}
+
+impl GateFailure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            GateFailure::Success { .. } => EXIT_MIN_SUCCESS,
+            GateFailure::Latency { .. } => EXIT_MAX_AVG,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            GateFailure::Success { best, min } => format!(
+                "Best success rate {best:.1}% is {:.1} points below --min-success {min}%",
+                min - best
+            ),
+            GateFailure::Latency {
+                fastest: Some(avg),
+                max,
+            } => format!(
+                "Fastest average {avg:.0}ms is {:.0}ms over --max-avg {max}ms",
+                avg - max
+            ),
+            GateFailure::Latency { fastest: None, max } => {
+                format!("No endpoint answered, --max-avg {max}ms not met")
+            }
+        }
+    }
+}
+
+/// Report missed thresholds on stderr; the first one's exit code is left to
+/// main, so the `--stats` footer still prints
+fn finish(failed: &[GateFailure]) -> Result<Option<i32>> {
+    for f in failed {
+        eprintln!("{} {}", "🚨".red(), f.message().red().bold());
+    }
+    Ok(failed.first().map(GateFailure::exit_code))
+}
 
 struct BenchResult {
     name: String,
     url: String,
     latencies: Vec<u128>,
     errors: usize,
+    version: Option<String>,
 }
 
 impl BenchResult {
@@ -42,6 +139,30 @@ impl BenchResult {
     }
 }
 
+/// Last `ROLLING_WINDOW` cycle averages of one endpoint
+#[derive(Default)]
+struct Rolling(VecDeque<f64>);
+
+impl Rolling {
+    fn push(&mut self, avg: f64) {
+        // a cycle without a single answer has no latency to average
+        if avg == f64::MAX {
+            return;
+        }
+        if self.0.len() == ROLLING_WINDOW {
+            self.0.pop_front();
+        }
+        self.0.push_back(avg);
+    }
+
+    fn mean(&self) -> Option<f64> {
+        if self.0.is_empty() {
+            return None;
+        }
+        Some(self.0.iter().sum::<f64>() / self.0.len() as f64)
+    }
+}
+
 fn percentile(sorted: &[u128], pct: usize) -> u128 {
     if sorted.is_empty() {
         return 0;
@@ -50,7 +171,135 @@ fn percentile(sorted: &[u128], pct: usize) -> u128 {
     sorted[idx.min(sorted.len() - 1)]
 }
 
-pub async fn run(rpc_url: &str, extra_rpcs: Option<&str>, count: usize, json: bool) -> Result<()> {
+/// Count latencies into `bins` equal-width buckets between min and max
+fn bucket_counts(sorted: &[u128], bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins];
+    let (Some(&lo), Some(&hi)) = (sorted.first(), sorted.last()) else {
+        return counts;
+    };
+    let width = (hi - lo) as f64 / bins as f64;
+    for &l in sorted {
+        let idx = if width == 0.0 {
+            0
+        } else {
+            ((l - lo) as f64 / width) as usize
+        };
+        counts[idx.min(bins - 1)] += 1;
+    }
+    counts
+}
+
+/// Render bucket counts as a unicode sparkline (empty buckets are blank)
+fn sparkline(counts: &[usize]) -> String {
+    let peak = counts.iter().copied().max().unwrap_or(0);
+    counts
+        .iter()
+        .map(|&c| {
+            if c == 0 {
+                ' '
+            } else {
+                BLOCKS[(c * BLOCKS.len()).div_ceil(peak) - 1]
+            }
+        })
+        .collect()
+}
+
+/// Latency relative to the `--baseline` endpoint, as avg / baseline avg
+fn baseline_ratio(avg: f64, base: f64) -> Option<f64> {
+    // f64::MAX marks an endpoint that never answered
+    if avg == f64::MAX || base == f64::MAX || base <= 0.0 {
+        return None;
+    }
+    Some(avg / base)
+}
+
+/// Host of an endpoint URL, lowercase, without scheme, credentials, port or path
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, r)| r);
+    let authority = &rest[..rest.find(['/', '?']).unwrap_or(rest.len())];
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    host.split(':').next().unwrap_or(host).to_lowercase()
+}
+
+/// Provider behind an endpoint: a known name, else its domain (last two
+/// labels), so `a.example.com` and `b.example.com` count as one
+fn provider(url: &str) -> String {
+    let host = url_host(url);
+    let known = PROVIDERS
+        .iter()
+        .find(|(domain, _)| host == *domain || host.ends_with(&format!(".{domain}")));
+    if let Some((_, name)) = known {
+        return name.to_string();
+    }
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return host;
+    }
+    match host.rsplitn(3, '.').collect::<Vec<_>>()[..] {
+        [tld, name, ..] => format!("{name}.{tld}"),
+        _ => host,
+    }
+}
+
+/// `--group-by-provider`: (provider, endpoints, fastest endpoint), best
+/// provider first. Results come sorted, so the first seen is the best.
+fn group_by_provider(results: &[BenchResult]) -> Vec<(String, usize, &BenchResult)> {
+    let mut groups: Vec<(String, usize, &BenchResult)> = Vec::new();
+    for r in results {
+        let name = provider(&r.url);
+        match groups.iter_mut().find(|(p, _, _)| *p == name) {
+            Some(group) => group.1 += 1,
+            None => groups.push((name, 1, r)),
+        }
+    }
+    groups
+}
+
+/// Rows to rank: every endpoint, or with `--group-by-provider` each
+/// provider's fastest endpoint along with the provider and its endpoint count
+fn ranked_rows(
+    results: &[BenchResult],
+    by_provider: bool,
+) -> Vec<(Option<(String, usize)>, &BenchResult)> {
+    if !by_provider {
+        return results.iter().map(|r| (None, r)).collect();
+    }
+    group_by_provider(results)
+        .into_iter()
+        .map(|(name, endpoints, best)| (Some((name, endpoints)), best))
+        .collect()
+}
+
+fn describe_ratio(ratio: Option<f64>) -> String {
+    match ratio {
+        None => "—".to_string(),
+        Some(r) if (r - 1.0).abs() < 0.05 => "≈ baseline".to_string(),
+        Some(r) if r > 1.0 => format!("{r:.1}× slower"),
+        Some(r) => format!("{:.1}× faster", 1.0 / r),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    rpc_url: &str,
+    extra_rpcs: Option<&str>,
+    count: usize,
+    histogram: bool,
+    set_rpc: bool,
+    baseline: Option<&str>,
+    watch: Option<u64>,
+    gate: HealthGate,
+    by_provider: bool,
+    json: bool,
+) -> Result<Option<i32>> {
+    if set_rpc && json {
+        anyhow::bail!("--set-rpc is interactive and can't be combined with --json");
+    }
+    if gate
+        .min_success
+        .is_some_and(|p| !(0.0..=100.0).contains(&p))
+    {
+        anyhow::bail!("--min-success is a percentage between 0 and 100");
+    }
     let count = count.clamp(3, 100);
 
     // Build endpoint list - start with configured RPC
@@ -69,6 +318,27 @@ pub async fn run(rpc_url: &str, extra_rpcs: Option<&str>, count: usize, json: bo
         }
     }
 
+    // the baseline is always part of the run, once
+    if let Some(base) = baseline {
+        if !endpoints.iter().any(|(_, url)| url == base) {
+            endpoints.push(("Baseline", base.to_string()));
+        }
+    }
+
+    if let Some(interval) = watch {
+        return watch_endpoints(
+            &endpoints,
+            count,
+            histogram,
+            by_provider,
+            baseline,
+            interval,
+            json,
+        )
+        .await
+        .map(|()| None);
+    }
+
     if !json {
         println!(
             "\n{} Benchmarking {} endpoint(s) × {} requests…\n",
@@ -91,10 +361,49 @@ pub async fn run(rpc_url: &str, extra_rpcs: Option<&str>, count: usize, json: bo
         None
     };
 
+    let results = bench_endpoints(&endpoints, count, pb.as_ref()).await?;
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    let base_avg = baseline_avg(&results, baseline);
+    let failed = gate.check(&results);
+    if json {
+        let mut out = results_json(
+            &results,
+            count,
+            histogram,
+            by_provider,
+            baseline,
+            base_avg,
+            None,
+        );
+        if !failed.is_empty() {
+            let messages: Vec<String> = failed.iter().map(GateFailure::message).collect();
+            out["gate_failures"] = messages.into();
+        }
+        utils::print_json("rpc-bench", out);
+        return finish(&failed);
+    }
+    print_table(&results, histogram, by_provider, base_avg, None);
+
+    if set_rpc {
+        pick_endpoint(&results)?;
+    }
+
+    finish(&failed)
+}
+
+/// `count` getSlot calls per endpoint, best average first
+async fn bench_endpoints(
+    endpoints: &[(&str, String)],
+    count: usize,
+    pb: Option<&ProgressBar>,
+) -> Result<Vec<BenchResult>> {
     let mut results: Vec<BenchResult> = Vec::new();
 
-    for (name, url) in &endpoints {
-        if let Some(ref pb) = pb {
+    for (name, url) in endpoints {
+        if let Some(pb) = pb {
             pb.set_message(name.to_string());
         }
 
@@ -109,63 +418,203 @@ pub async fn run(rpc_url: &str, extra_rpcs: Option<&str>, count: usize, json: bo
                 Ok(_) => latencies.push(start.elapsed().as_millis()),
                 Err(_) => errors += 1,
             }
-            if let Some(ref pb) = pb {
+            if let Some(pb) = pb {
                 pb.inc(1);
             }
         }
 
         latencies.sort();
+
+        let client = rpc::client_with_timeout(url, Duration::from_secs(10));
+        let version = tokio::task::spawn_blocking(move || rpc::node_version(&client))
+            .await?
+            .ok();
+
         results.push(BenchResult {
             name: name.to_string(),
             url: url.clone(),
             latencies,
             errors,
+            version,
         });
     }
 
-    if let Some(pb) = pb {
-        pb.finish_and_clear();
-    }
-
     // sort best to worst
     results.sort_by(|a, b| a.avg().partial_cmp(&b.avg()).unwrap());
+    Ok(results)
+}
 
-    if json {
-        let data: Vec<serde_json::Value> = results
+/// `--watch`: rerun every `interval` seconds until Ctrl+C, redrawing the
+/// table (or one JSON object per cycle) with a rolling average per endpoint
+async fn watch_endpoints(
+    endpoints: &[(&str, String)],
+    count: usize,
+    histogram: bool,
+    by_provider: bool,
+    baseline: Option<&str>,
+    interval: u64,
+    json: bool,
+) -> Result<()> {
+    let mut rolling: HashMap<String, Rolling> = HashMap::new();
+    let stop = tokio::signal::ctrl_c();
+    tokio::pin!(stop);
+
+    for cycle in 1u64.. {
+        let results = tokio::select! {
+            res = bench_endpoints(endpoints, count, None) => res?,
+            _ = &mut stop => break,
+        };
+        for r in &results {
+            rolling.entry(r.url.clone()).or_default().push(r.avg());
+        }
+        let means: HashMap<String, f64> = rolling
             .iter()
-            .map(|r| {
-                serde_json::json!({
-                    "name": r.name,
-                    "url": r.url,
-                    "avg_ms": r.avg().round(),
-                    "min_ms": r.min(),
-                    "max_ms": r.max(),
-                    "p50_ms": r.p50(),
-                    "p99_ms": r.p99(),
-                    "success_rate": r.success_rate(),
-                    "errors": r.errors,
-                })
-            })
+            .filter_map(|(url, r)| Some((url.clone(), r.mean()?)))
             .collect();
+        let base_avg = baseline_avg(&results, baseline);
+        let time = chrono::Local::now().format("%H:%M:%S").to_string();
+
+        if json {
+            let mut out = results_json(
+                &results,
+                count,
+                histogram,
+                by_provider,
+                baseline,
+                base_avg,
+                Some(&means),
+            );
+            out["cycle"] = cycle.into();
+            out["time"] = time.into();
+            utils::print_json("rpc-bench", out);
+        } else {
+            // clear the screen and redraw from the top
+            print!("\x1B[2J\x1B[H");
+            println!(
+                "\n{} {} · cycle {} · every {}s · rolling avg of last {} (Ctrl+C to stop)\n",
+                "🏎️".bold(),
+                time.dimmed(),
+                cycle.to_string().cyan(),
+                interval,
+                ROLLING_WINDOW
+            );
+            print_table(&results, histogram, by_provider, base_avg, Some(&means));
+        }
 
-        println!("{}", serde_json::json!({ "results": data, "count": count }));
-        return Ok(());
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+            _ = &mut stop => break,
+        }
+    }
+    Ok(())
+}
+
+fn baseline_avg(results: &[BenchResult], baseline: Option<&str>) -> Option<f64> {
+    baseline.and_then(|b| results.iter().find(|r| r.url == b).map(|r| r.avg()))
+}
+
+/// JSON for one run; `rolling` (by URL) adds `rolling_avg_ms` in `--watch`
+fn results_json(
+    results: &[BenchResult],
+    count: usize,
+    histogram: bool,
+    by_provider: bool,
+    baseline: Option<&str>,
+    base_avg: Option<f64>,
+    rolling: Option<&HashMap<String, f64>>,
+) -> serde_json::Value {
+    let data: Vec<serde_json::Value> = ranked_rows(results, by_provider)
+        .into_iter()
+        .map(|(provider, r)| {
+            let mut v = serde_json::json!({
+                "name": r.name,
+                "url": r.url,
+                "avg_ms": r.avg().round(),
+                "min_ms": r.min(),
+                "max_ms": r.max(),
+                "p50_ms": r.p50(),
+                "p99_ms": r.p99(),
+                "success_rate": r.success_rate(),
+                "errors": r.errors,
+                "version": r.version,
+            });
+            if let Some((name, endpoints)) = provider {
+                v["provider"] = name.into();
+                v["endpoints"] = endpoints.into();
+            }
+            if histogram {
+                v["histogram"] = bucket_counts(&r.latencies, HISTOGRAM_BINS).into();
+            }
+            if let Some(base) = base_avg {
+                v["vs_baseline"] = serde_json::json!(baseline_ratio(r.avg(), base));
+            }
+            if let Some(avg) = rolling.and_then(|m| m.get(&r.url)) {
+                v["rolling_avg_ms"] = avg.round().into();
+            }
+            v
+        })
+        .collect();
+
+    let mut out = serde_json::json!({ "results": data, "count": count });
+    if let Some(base) = baseline {
+        out["baseline"] = base.into();
     }
+    out
+}
+
+/// Results table, histogram and fastest endpoint
+fn print_table(
+    results: &[BenchResult],
+    histogram: bool,
+    by_provider: bool,
+    base_avg: Option<f64>,
+    rolling: Option<&HashMap<String, f64>>,
+) {
+    // extra columns with --baseline and --watch
+    let vs_col = |r: &BenchResult| match base_avg {
+        Some(base) => format!(" {:>14}", describe_ratio(baseline_ratio(r.avg(), base))),
+        None => String::new(),
+    };
+    let roll_col = |r: &BenchResult| match rolling {
+        Some(m) => match m.get(&r.url) {
+            Some(avg) => format!(" {:>8}", format!("{avg:.0}ms")),
+            None => format!(" {:>8}", "—"),
+        },
+        None => String::new(),
+    };
 
     // output table
     println!(
-        "  {:<16} {:>7} {:>7} {:>7} {:>7} {:>7} {:>8}",
+        "  {:<16} {:>7} {:>7} {:>7} {:>7} {:>7} {:>8} {:>9}{}{}",
         "Endpoint".white().bold(),
         "Avg".white().bold(),
         "Min".white().bold(),
         "P50".white().bold(),
         "P99".white().bold(),
         "Max".white().bold(),
-        "Success".white().bold()
+        "Success".white().bold(),
+        "Version".white().bold(),
+        if base_avg.is_some() {
+            format!(" {:>14}", "vs Baseline".white().bold())
+        } else {
+            String::new()
+        },
+        if rolling.is_some() {
+            format!(" {:>8}", "Rolling".white().bold())
+        } else {
+            String::new()
+        }
     );
-    println!("  {}", "─".repeat(72).dimmed());
-
-    for (i, r) in results.iter().enumerate() {
+    let width =
+        82 + if base_avg.is_some() { 15 } else { 0 } + if rolling.is_some() { 9 } else { 0 };
+    println!("  {}", "─".repeat(width).dimmed());
+
+    let rows = ranked_rows(results, by_provider);
+    let label = |provider: &Option<(String, usize)>, r: &BenchResult| match provider {
+        Some((name, endpoints)) => format!("{name} ({endpoints})"),
+        None => r.name.clone(),
+    };
+    for (i, (provider, r)) in rows.iter().enumerate() {
         let rank = match i {
             0 => "🥇".to_string(),
             1 => "🥈".to_string(),
@@ -193,31 +642,93 @@ pub async fn run(rpc_url: &str, extra_rpcs: Option<&str>, count: usize, json: bo
             succ_str.red()
         };
 
+        let version = r.version.as_deref().unwrap_or("?");
+        let version_col = if rpc::is_outdated(version) {
+            version.yellow()
+        } else {
+            version.dimmed()
+        };
+
         println!(
-            "  {:<16} {:>7} {:>7} {:>7} {:>7} {:>7} {:>8}",
-            format!("{rank} {}", r.name).white(),
+            "  {:<16} {:>7} {:>7} {:>7} {:>7} {:>7} {:>8} {:>9}{}{}",
+            format!("{rank} {}", label(provider, r)).white(),
             avg_col,
             format!("{}ms", r.min()).dimmed(),
             format!("{}ms", r.p50()).dimmed(),
             format!("{}ms", r.p99()).dimmed(),
             format!("{}ms", r.max()).dimmed(),
             succ_col,
+            version_col,
+            vs_col(r).dimmed(),
+            roll_col(r).cyan(),
         );
     }
 
+    if histogram {
+        println!("\n  {}", "Latency distribution (min → max)".white().bold());
+        for r in results {
+            if r.latencies.is_empty() {
+                continue;
+            }
+            println!(
+                "  {:<16} {:>6} {} {}",
+                r.name.white(),
+                format!("{}ms", r.min()).dimmed(),
+                sparkline(&bucket_counts(&r.latencies, HISTOGRAM_BINS)).cyan(),
+                format!("{}ms", r.max()).dimmed(),
+            );
+        }
+    }
+
     println!();
-    if let Some(best) = results.first() {
+    if let Some((provider, best)) = rows.first() {
+        let via = match provider {
+            Some(_) => format!(" via {}", best.name),
+            None => String::new(),
+        };
         println!(
-            "  {} Fastest: {} ({:.0}ms avg)\n",
+            "  {} Fastest: {}{} ({:.0}ms avg)\n",
             "⚡".green(),
-            best.name.green().bold(),
+            label(provider, best).green().bold(),
+            via,
             best.avg()
         );
     }
+}
+
+/// `--set-rpc`: choose an endpoint (fastest first) and print the export line
+fn pick_endpoint(results: &[BenchResult]) -> Result<()> {
+    let usable: Vec<&BenchResult> = results.iter().filter(|r| !r.latencies.is_empty()).collect();
+    if usable.is_empty() {
+        println!("  {}", "No endpoint answered, nothing to pick".dimmed());
+        return Ok(());
+    }
 
+    let items: Vec<String> = usable
+        .iter()
+        .map(|r| format!("{} ({:.0}ms) {}", r.name, r.avg(), r.url))
+        .collect();
+    // --yes takes the fastest
+    let choice = if utils::assume_yes() {
+        0
+    } else {
+        utils::ensure_interactive()?;
+        dialoguer::Select::new()
+            .with_prompt("Use which endpoint?")
+            .items(&items)
+            .default(0)
+            .interact()?
+    };
+
+    println!("\n  Add this to your shell profile or .env:\n");
+    println!("  {}\n", export_line(&usable[choice].url).green());
     Ok(())
 }
 
+fn export_line(url: &str) -> String {
+    format!("export SOLANA_RPC_NODE={url}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -229,6 +740,7 @@ mod tests {
             url: "http://localhost".to_string(),
             latencies: vec![10, 20, 30, 40, 50],
             errors: 0,
+            version: None,
         };
 
         assert_eq!(res.min(), 10);
@@ -247,6 +759,7 @@ mod tests {
             url: "http://localhost".to_string(),
             latencies: vec![],
             errors: 0,
+            version: None,
         };
 
         assert_eq!(res.min(), 0);
@@ -261,11 +774,118 @@ mod tests {
             url: "http://localhost".to_string(),
             latencies: vec![10, 20],
             errors: 2,
+            version: None,
         };
         // 2 success, 2 errors = 4 total. 50% success
         assert_eq!(res.success_rate(), 50.0);
     }
 
+    #[test]
+    fn test_health_gate() {
+        let fast = BenchResult {
+            name: "Fast".to_string(),
+            url: "http://a".to_string(),
+            latencies: vec![100, 200],
+            errors: 2,
+            version: None,
+        };
+        let results = vec![fast];
+
+        assert!(HealthGate::default().check(&results).is_empty());
+        let gate = HealthGate {
+            min_success: Some(50.0),
+            max_avg: Some(150.0),
+        };
+        assert!(gate.check(&results).is_empty());
+
+        let gate = HealthGate {
+            min_success: Some(90.0),
+            max_avg: Some(100.0),
+        };
+        let failed = gate.check(&results);
+        assert_eq!(
+            failed,
+            vec![
+                GateFailure::Success {
+                    best: 50.0,
+                    min: 90.0
+                },
+                GateFailure::Latency {
+                    fastest: Some(150.0),
+                    max: 100.0
+                },
+            ]
+        );
+        assert_eq!(failed[0].exit_code(), EXIT_MIN_SUCCESS);
+        assert!(failed[0].message().contains("40.0 points below"));
+        assert!(failed[1].message().contains("50ms over"));
+
+        // nothing answered: no average can pass
+        assert_eq!(
+            HealthGate {
+                min_success: None,
+                max_avg: Some(1000.0),
+            }
+            .check(&[]),
+            vec![GateFailure::Latency {
+                fastest: None,
+                max: 1000.0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let data = vec![10, 11, 12, 13, 50, 100];
+        let counts = bucket_counts(&data, 10);
+        assert_eq!(counts.iter().sum::<usize>(), data.len());
+        assert_eq!(counts[0], 4);
+        assert_eq!(counts[9], 1); // max lands in the last bin
+
+        // all equal -> single bucket
+        assert_eq!(bucket_counts(&[5, 5, 5], 4), vec![3, 0, 0, 0]);
+        assert_eq!(bucket_counts(&[], 4), vec![0; 4]);
+
+        assert_eq!(sparkline(&[4, 0, 2, 1]), "█ ▄▂");
+    }
+
+    #[test]
+    fn test_rolling() {
+        let mut r = Rolling::default();
+        assert_eq!(r.mean(), None);
+
+        r.push(100.0);
+        r.push(f64::MAX); // no answers that cycle: skipped
+        r.push(300.0);
+        assert_eq!(r.mean(), Some(200.0));
+
+        // only the last ROLLING_WINDOW cycles count
+        for _ in 0..ROLLING_WINDOW {
+            r.push(50.0);
+        }
+        assert_eq!(r.mean(), Some(50.0));
+    }
+
+    #[test]
+    fn test_baseline_ratio() {
+        assert_eq!(baseline_ratio(180.0, 100.0), Some(1.8));
+        assert_eq!(baseline_ratio(f64::MAX, 100.0), None);
+        assert_eq!(baseline_ratio(100.0, f64::MAX), None);
+
+        assert_eq!(describe_ratio(Some(1.8)), "1.8× slower");
+        assert_eq!(describe_ratio(Some(0.5)), "2.0× faster");
+        assert_eq!(describe_ratio(Some(1.02)), "≈ baseline");
+        assert_eq!(describe_ratio(None), "—");
+    }
+
+    #[test]
+    fn test_export_line() {
+        assert_eq!(
+            export_line("https://rpc.example.com"),
+            "export SOLANA_RPC_NODE=https://rpc.example.com"
+        );
+    }
+
     #[test]
     fn test_percentile_calculation() {
         let data = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
@@ -277,4 +897,50 @@ mod tests {
         // p90: 0.9 * 9 = 8.1 -> 8. index 8 is 9.
         assert_eq!(percentile(&data, 90), 9);
     }
+
+    #[test]
+    fn test_provider_grouping() {
+        assert_eq!(
+            url_host("https://user:pw@Mainnet.Helius-RPC.com:443/?api-key=x"),
+            "mainnet.helius-rpc.com"
+        );
+        assert_eq!(
+            provider("https://mainnet.helius-rpc.com/?api-key=x"),
+            "Helius"
+        );
+        assert_eq!(
+            provider("https://api.mainnet-beta.solana.com"),
+            "Solana Foundation"
+        );
+        assert_eq!(provider("https://rpc.a.example.org/v1"), "example.org");
+        assert_eq!(provider("http://127.0.0.1:8899"), "127.0.0.1");
+        assert_eq!(provider("http://localhost:8899"), "localhost");
+
+        let result = |name: &str, url: &str| BenchResult {
+            name: name.to_string(),
+            url: url.to_string(),
+            latencies: vec![10],
+            errors: 0,
+            version: None,
+        };
+        let results = vec![
+            result("fast", "https://a.helius-rpc.com"),
+            result("public", "https://api.mainnet-beta.solana.com"),
+            result("slow", "https://b.helius-rpc.com"),
+        ];
+        let groups = group_by_provider(&results);
+        assert_eq!(groups.len(), 2);
+        assert_eq!((groups[0].0.as_str(), groups[0].1), ("Helius", 2));
+        assert_eq!(groups[0].2.name, "fast");
+        assert_eq!(groups[1].0, "Solana Foundation");
+
+        // one ranked row per provider, in JSON as in the table
+        let json = results_json(&results, 3, false, true, None, None, None);
+        let rows = json["results"].as_array().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["provider"], "Helius");
+        assert_eq!(rows[0]["endpoints"], 2);
+        assert_eq!(rows[0]["name"], "fast");
+        assert_eq!(ranked_rows(&results, false).len(), 3);
+    }
 }
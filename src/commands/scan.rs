@@ -1,16 +1,157 @@
 use anyhow::{Context, Result};
-use colored::Colorize;
-use solana_account_decoder::UiAccountEncoding;
-use solana_client::{
-    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
-    rpc_filter::{Memcmp, RpcFilterType},
-};
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use colored::{ColoredString, Colorize};
+use rayon::prelude::*;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::Semaphore;
+
+use crate::{das, price, token, utils};
+
+/// Max wallets scanned at once in multi-wallet mode
+const SCAN_CONCURRENCY: usize = 10;
+/// Below this many accounts a plain loop beats rayon's thread overhead
+const PARALLEL_MIN_ACCOUNTS: usize = 2_000;
+
+#[derive(Default)]
+struct WalletScan {
+    wallet: Pubkey,
+    sol: f64,
+    total_accs: usize,
+    empty_accs: usize,
+    balance_accs: usize,
+    delegate_accs: usize,
+    frozen_accs: usize,
+    /// Token accounts per mint
+    mint_counts: HashMap<Pubkey, usize>,
+    rent_locked: u64,
+    rent_reclaim: u64,
+    cnfts: Option<Result<Option<usize>>>,
+}
+
+impl WalletScan {
+    /// Count one token account into the totals
+    fn tally(mut self, acc: &Account, ignore: &HashSet<Pubkey>) -> Self {
+        let Some(tok) = token::parse_account(acc) else {
+            return self; // skip invalid
+        };
+
+        self.total_accs += 1;
+        self.rent_locked += acc.lamports;
+        *self.mint_counts.entry(tok.mint).or_default() += 1;
+
+        // `--ignore-mints` (WSOL by default): empty is normal, not clutter
+        let ignored = ignore.contains(&tok.mint);
+
+        if tok.is_reclaimable() && !ignored {
+            self.rent_reclaim += acc.lamports;
+        }
+        if tok.amount == 0 {
+            if !ignored {
+                self.empty_accs += 1;
+            }
+        } else {
+            self.balance_accs += 1;
+        }
+        if tok.delegated {
+            self.delegate_accs += 1;
+        }
+        if tok.frozen {
+            self.frozen_accs += 1;
+        }
+        self
+    }
+
+    /// Combine the totals of two partial tallies
+    fn merge(mut self, other: Self) -> Self {
+        self.total_accs += other.total_accs;
+        self.empty_accs += other.empty_accs;
+        self.balance_accs += other.balance_accs;
+        self.delegate_accs += other.delegate_accs;
+        self.frozen_accs += other.frozen_accs;
+        self.rent_locked += other.rent_locked;
+        self.rent_reclaim += other.rent_reclaim;
+        for (mint, n) in other.mint_counts {
+            *self.mint_counts.entry(mint).or_default() += n;
+        }
+        self
+    }
+
+    fn score(&self) -> u32 {
+        calc_score(
+            self.empty_accs,
+            self.delegate_accs,
+            self.frozen_accs,
+            self.total_accs,
+        )
+    }
+
+    /// The `n` mints with the most accounts, most first
+    fn top_mints(&self, n: usize) -> Vec<(Pubkey, usize)> {
+        let mut counts: Vec<(Pubkey, usize)> =
+            self.mint_counts.iter().map(|(m, c)| (*m, *c)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
 
-use crate::{price, utils};
+    /// USD fields are null without a SOL price
+    fn to_json(&self, sol_price: Option<f64>, top: usize) -> serde_json::Value {
+        let reclaim_sol = utils::lamports_to_sol(self.rent_reclaim);
+        let mut out = serde_json::json!({
+            "wallet": self.wallet.to_string(),
+            "balance": { "sol": self.sol, "usd": sol_price.map(|p| self.sol * p) },
+            "stats": {
+                "total_accounts": self.total_accs,
+                "empty": self.empty_accs,
+                "with_balance": self.balance_accs,
+                "delegated": self.delegate_accs,
+                "frozen": self.frozen_accs,
+                "unique_mints": self.mint_counts.len(),
+            },
+            "rent": {
+                "locked_sol": utils::lamports_to_sol(self.rent_locked),
+                "reclaimable_sol": reclaim_sol,
+                "reclaimable_usd": sol_price.map(|p| reclaim_sol * p),
+            },
+            "score": self.score(),
+        });
+        if let Some(res) = &self.cnfts {
+            out["compressed_nfts"] = serde_json::json!(res.as_ref().ok().copied().flatten());
+        }
+        if top > 0 {
+            let top: Vec<serde_json::Value> = self
+                .top_mints(top)
+                .iter()
+                .map(|(m, c)| {
+                    serde_json::json!({
+                        "mint": m.to_string(),
+                        "symbol": token::symbol(m),
+                        "accounts": c,
+                    })
+                })
+                .collect();
+            out["top_mints"] = top.into();
+        }
+        out
+    }
+}
 
-pub async fn run(rpc_url: &str, wallet_str: &str, json: bool) -> Result<()> {
-    let wallet = utils::parse_pubkey(wallet_str)?;
+pub async fn run(
+    rpc_url: &str,
+    wallet_strs: &[String],
+    with_cnfts: bool,
+    top_mints: usize,
+    ignore: &HashSet<Pubkey>,
+    json: bool,
+) -> Result<()> {
+    let wallets = wallet_strs
+        .iter()
+        .map(|w| utils::parse_pubkey(w))
+        .collect::<Result<Vec<_>>>()?;
+
+    let [wallet] = wallets[..] else {
+        return run_multi(rpc_url, &wallets, with_cnfts, top_mints, ignore, json).await;
+    };
 
     if !json {
         println!(
@@ -20,6 +161,146 @@ pub async fn run(rpc_url: &str, wallet_str: &str, json: bool) -> Result<()> {
         );
     }
 
+    let sol_price = price::sol_price().await;
+    let scan = scan_wallet(rpc_url, wallet, with_cnfts, ignore).await?;
+
+    // scan only reads SPL accounts, so confirm against Token-2022 too
+    if scan.sol == 0.0 && scan.total_accs == 0 {
+        let unused = tokio::task::spawn_blocking({
+            let c = crate::rpc::client(rpc_url);
+            move || crate::rpc::wallet_unused(&c, &wallet)
+        })
+        .await?
+        .unwrap_or(false);
+        if unused {
+            utils::print_unused_wallet("scan", &wallet, json);
+            return Ok(());
+        }
+    }
+
+    if json {
+        utils::print_json("scan", scan.to_json(sol_price, top_mints));
+    } else {
+        print_report(&scan, sol_price, top_mints);
+    }
+
+    Ok(())
+}
+
+/// Scan several wallets concurrently, then print a combined report
+async fn run_multi(
+    rpc_url: &str,
+    wallets: &[Pubkey],
+    with_cnfts: bool,
+    top_mints: usize,
+    ignore: &HashSet<Pubkey>,
+    json: bool,
+) -> Result<()> {
+    if !json {
+        println!(
+            "\n{} Scanning {} wallets…\n",
+            "🔍".bold(),
+            wallets.len().to_string().cyan()
+        );
+    }
+
+    let sol_price = price::sol_price().await;
+
+    let sem = Semaphore::new(SCAN_CONCURRENCY);
+    let sem = &sem;
+    let results = futures::future::join_all(wallets.iter().map(|w| async move {
+        let _permit = sem.acquire().await?;
+        scan_wallet(rpc_url, *w, with_cnfts, ignore).await
+    }))
+    .await;
+
+    let scans: Vec<&WalletScan> = results.iter().filter_map(|r| r.as_ref().ok()).collect();
+    let reclaim_sol = utils::lamports_to_sol(scans.iter().map(|s| s.rent_reclaim).sum());
+    let delegated: usize = scans.iter().map(|s| s.delegate_accs).sum();
+    let worst = scans.iter().min_by_key(|s| s.score());
+
+    if json {
+        let per_wallet: Vec<serde_json::Value> = wallets
+            .iter()
+            .zip(&results)
+            .map(|(w, r)| match r {
+                Ok(scan) => scan.to_json(sol_price, top_mints),
+                Err(e) => serde_json::json!({ "wallet": w.to_string(), "error": e.to_string() }),
+            })
+            .collect();
+
+        utils::print_json(
+            "scan",
+            serde_json::json!({
+                "wallets": per_wallet,
+                "aggregate": {
+                    "wallets": wallets.len(),
+                    "failed": wallets.len() - scans.len(),
+                    "reclaimable_sol": reclaim_sol,
+                    "reclaimable_usd": sol_price.map(|p| reclaim_sol * p),
+                    "delegated": delegated,
+                    "worst": worst.map(|s| serde_json::json!({
+                        "wallet": s.wallet.to_string(),
+                        "score": s.score(),
+                    })),
+                }
+            }),
+        );
+        return Ok(());
+    }
+
+    for (w, r) in wallets.iter().zip(&results) {
+        match r {
+            Ok(scan) => println!(
+                "  {} score {} · {} empty · {} delegated · {} reclaimable",
+                utils::short_key(w).white(),
+                score_label(scan.score()).0,
+                scan.empty_accs,
+                scan.delegate_accs,
+                utils::format_sol(utils::lamports_to_sol(scan.rent_reclaim)).green()
+            ),
+            Err(e) => println!(
+                "  {} {} {}",
+                utils::short_key(w).white(),
+                "✗".red(),
+                format!("failed: {e}").dimmed()
+            ),
+        }
+    }
+    println!();
+
+    println!("  {} {}", "▸".cyan(), "Aggregate".white().bold());
+    println!(
+        "    Wallets:      {}/{} scanned",
+        scans.len(),
+        wallets.len()
+    );
+    println!(
+        "    Reclaimable:  {} {}",
+        utils::format_sol(reclaim_sol).green().bold(),
+        utils::approx_usd(reclaim_sol, sol_price).dimmed()
+    );
+    println!("    Delegated:    {}", delegated);
+    if let Some(w) = worst {
+        let (col, label) = score_label(w.score());
+        println!(
+            "    Worst:        {} ({}/100 — {})",
+            utils::short_key(&w.wallet),
+            col,
+            label
+        );
+    }
+    println!();
+
+    Ok(())
+}
+
+async fn scan_wallet(
+    rpc_url: &str,
+    wallet: Pubkey,
+    with_cnfts: bool,
+    ignore: &HashSet<Pubkey>,
+) -> Result<WalletScan> {
     // 1. fetch balance
     let sol_bal = tokio::task::spawn_blocking({
         let c = crate::rpc::client(rpc_url);
@@ -29,100 +310,53 @@ pub async fn run(rpc_url: &str, wallet_str: &str, json: bool) -> Result<()> {
     .await?
     .context("Failed to get SOL balance")?;
 
-    let sol = utils::lamports_to_sol(sol_bal);
-    let sol_price = price::sol_price().await.unwrap_or(0.0);
-
     // 2. fetch token accounts
-    let config = RpcProgramAccountsConfig {
-        filters: Some(vec![
-            RpcFilterType::DataSize(165),
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, wallet.to_bytes().to_vec())),
-        ]),
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            commitment: Some(CommitmentConfig::confirmed()),
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
     let accounts = tokio::task::spawn_blocking({
         let c = crate::rpc::client(rpc_url);
-        move || c.get_program_accounts_with_config(&spl_token::id(), config)
+        move || crate::rpc::get_token_accounts(&c, &wallet, &spl_token::id())
     })
     .await?
     .context("Failed to get token accounts")?;
 
     // 3. analyze
-    let mut total_accs = 0;
-    let mut empty_accs = 0;
-    let mut balance_accs = 0;
-    let mut delegate_accs = 0;
-    let mut frozen_accs = 0;
-    let mut rent_locked = 0;
-    let mut rent_reclaim = 0;
-    let mut mints = std::collections::HashSet::new();
-
-    for (_, acc) in &accounts {
-        if acc.data.len() < 108 {
-            continue;
-        } // skip invalid
-
-        total_accs += 1;
-        rent_locked += acc.lamports;
-
-        let amount = u64::from_le_bytes(acc.data[64..72].try_into().unwrap());
-        let mint = Pubkey::try_from(&acc.data[0..32]).unwrap_or_default();
-        mints.insert(mint);
-
-        let has_delegate = u32::from_le_bytes(acc.data[72..76].try_into().unwrap()) == 1;
-        let is_frozen = acc.data[108] == 2;
-
-        if amount == 0 {
-            empty_accs += 1;
-            if !has_delegate && !is_frozen {
-                rent_reclaim += acc.lamports;
-            }
-        } else {
-            balance_accs += 1;
-        }
+    let mut scan = WalletScan {
+        wallet,
+        sol: utils::lamports_to_sol(sol_bal),
+        ..analyze(&accounts, ignore)
+    };
 
-        if has_delegate {
-            delegate_accs += 1;
-        }
-        if is_frozen {
-            frozen_accs += 1;
-        }
+    // optional: compressed NFTs (no token accounts, DAS only)
+    if with_cnfts {
+        scan.cnfts = Some(das::compressed_asset_count(rpc_url, &wallet).await);
     }
 
-    let reclaim_sol = utils::lamports_to_sol(rent_reclaim);
-    let reclaim_usd = reclaim_sol * sol_price;
-    let locked_sol = utils::lamports_to_sol(rent_locked);
+    Ok(scan)
+}
 
-    // 4. Output
-    if json {
-        println!(
-            "{}",
-            serde_json::json!({
-                "wallet": wallet_str,
-                "balance": { "sol": sol, "usd": sol * sol_price },
-                "stats": {
-                    "total_accounts": total_accs,
-                    "empty": empty_accs,
-                    "with_balance": balance_accs,
-                    "delegated": delegate_accs,
-                    "frozen": frozen_accs,
-                    "unique_mints": mints.len(),
-                },
-                "rent": {
-                    "locked_sol": locked_sol,
-                    "reclaimable_sol": reclaim_sol,
-                    "reclaimable_usd": reclaim_usd,
-                }
-            })
-        );
-        return Ok(());
+/// Tally token accounts, in parallel for huge wallets
+fn analyze(accounts: &[(Pubkey, Account)], ignore: &HashSet<Pubkey>) -> WalletScan {
+    if accounts.len() < PARALLEL_MIN_ACCOUNTS {
+        return accounts
+            .iter()
+            .fold(WalletScan::default(), |s, (_, acc)| s.tally(acc, ignore));
     }
+    accounts
+        .par_iter()
+        .fold(WalletScan::default, |s, (_, acc)| s.tally(acc, ignore))
+        .reduce(WalletScan::default, WalletScan::merge)
+}
+
+/// `(locked, reclaimable)` rent lamports, counted exactly as the scan report
+/// counts them
+pub fn rent_totals(accounts: &[(Pubkey, Account)], ignore: &HashSet<Pubkey>) -> (u64, u64) {
+    let scan = analyze(accounts, ignore);
+    (scan.rent_locked, scan.rent_reclaim)
+}
+
+fn print_report(scan: &WalletScan, sol_price: Option<f64>, top_mints: usize) {
+    let sol = scan.sol;
+    let reclaim_sol = utils::lamports_to_sol(scan.rent_reclaim);
+    let locked_sol = utils::lamports_to_sol(scan.rent_locked);
 
     // --- Overview ---
     let header = |t: &str| println!("  {} {}", "▸".cyan(), t.white().bold());
@@ -131,43 +365,53 @@ pub async fn run(rpc_url: &str, wallet_str: &str, json: bool) -> Result<()> {
     println!(
         "    SOL: {} {}",
         format!("{sol:.4}").green().bold(),
-        if sol_price > 0.0 {
-            format!("(≈ {})", utils::format_usd(sol * sol_price))
-                .dimmed()
-                .to_string()
-        } else {
-            "".into()
-        }
+        utils::approx_usd(sol, sol_price).dimmed()
     );
     println!();
 
     header("Token Accounts");
-    println!("    Total:        {}", total_accs.to_string().white());
-    println!("    With balance: {}", balance_accs.to_string().green());
+    println!("    Total:        {}", scan.total_accs.to_string().white());
+    println!(
+        "    With balance: {}",
+        scan.balance_accs.to_string().green()
+    );
     println!(
         "    Empty:        {}",
-        if empty_accs > 0 {
-            empty_accs.to_string().yellow().bold()
+        if scan.empty_accs > 0 {
+            scan.empty_accs.to_string().yellow().bold()
         } else {
             "0".green()
         }
     );
-    println!("    Unique mints: {}", mints.len().to_string().white());
+    println!(
+        "    Unique mints: {}",
+        scan.mint_counts.len().to_string().white()
+    );
+    for (mint, count) in scan.top_mints(top_mints) {
+        println!(
+            "      {} {}",
+            token::label(&mint).cyan(),
+            format!("{count} accounts").dimmed()
+        );
+    }
+    if let Some(res) = &scan.cnfts {
+        println!("    cNFTs:        {}", das::describe_count(res));
+    }
     println!();
 
     header("Security");
-    if delegate_accs > 0 {
+    if scan.delegate_accs > 0 {
         println!(
             "    {} {} accounts have active delegate approvals",
             "⚠".yellow(),
-            delegate_accs.to_string().yellow().bold()
+            scan.delegate_accs.to_string().yellow().bold()
         );
         println!("    {}", "Consider revoking unused approvals".dimmed());
     } else {
         println!("    {} No active delegate approvals", "✅".green());
     }
-    if frozen_accs > 0 {
-        println!("    {} {} frozen accounts", "❄️".blue(), frozen_accs);
+    if scan.frozen_accs > 0 {
+        println!("    {} {} frozen accounts", "❄️".blue(), scan.frozen_accs);
     }
     println!();
 
@@ -176,22 +420,16 @@ pub async fn run(rpc_url: &str, wallet_str: &str, json: bool) -> Result<()> {
         "    Total locked: {}",
         utils::format_sol(locked_sol).white()
     );
-    if empty_accs > 0 {
+    if scan.empty_accs > 0 {
         println!(
             "    {} Reclaimable: {} {}",
             "💰".green(),
             utils::format_sol(reclaim_sol).green().bold(),
-            if reclaim_usd > 0.0 {
-                format!("(≈ {})", utils::format_usd(reclaim_usd))
-                    .dimmed()
-                    .to_string()
-            } else {
-                "".into()
-            }
+            utils::approx_usd(reclaim_sol, sol_price).dimmed()
         );
         println!(
             "    {}",
-            format!("Run `sol-tool clean {wallet_str}` to reclaim").dimmed()
+            format!("Run `sol-tool clean {}` to reclaim", scan.wallet).dimmed()
         );
     } else {
         println!("    {} No rent to reclaim", "✅".green());
@@ -199,29 +437,31 @@ pub async fn run(rpc_url: &str, wallet_str: &str, json: bool) -> Result<()> {
     println!();
 
     // --- Health Score ---
-    let score = calc_score(empty_accs, delegate_accs, frozen_accs, total_accs);
+    let score = scan.score();
     header("Wallet Health");
 
-    let (col, label) = match score {
-        90..=100 => (score.to_string().green().bold(), "Excellent"),
-        70..=89 => (score.to_string().yellow().bold(), "Good"),
-        50..=69 => (score.to_string().yellow(), "Fair"),
-        _ => (score.to_string().red().bold(), "Needs attention"),
-    };
+    let (col, label) = score_label(score);
     println!("    Score: {}/100 — {}", col, label);
 
     if score < 90 {
         println!("    {}", "Recommendations:".dimmed());
-        if empty_accs > 0 {
-            println!("     • Close {} empty accounts", empty_accs);
+        if scan.empty_accs > 0 {
+            println!("     • Close {} empty accounts", scan.empty_accs);
         }
-        if delegate_accs > 0 {
-            println!("     • Revoke {} delegations", delegate_accs);
+        if scan.delegate_accs > 0 {
+            println!("     • Revoke {} delegations", scan.delegate_accs);
         }
     }
     println!();
+}
 
-    Ok(())
+fn score_label(score: u32) -> (ColoredString, &'static str) {
+    match score {
+        90..=100 => (score.to_string().green().bold(), "Excellent"),
+        70..=89 => (score.to_string().yellow().bold(), "Good"),
+        50..=69 => (score.to_string().yellow(), "Fair"),
+        _ => (score.to_string().red().bold(), "Needs attention"),
+    }
 }
 
 fn calc_score(empty: usize, delegates: usize, frozen: usize, total: usize) -> u32 {
@@ -249,6 +489,62 @@ fn calc_score(empty: usize, delegates: usize, frozen: usize, total: usize) -> u3
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_top_mints() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let scan = WalletScan {
+            mint_counts: HashMap::from([(a, 1), (b, 5), (c, 3)]),
+            ..Default::default()
+        };
+
+        assert_eq!(scan.top_mints(2), vec![(b, 5), (c, 3)]);
+        assert_eq!(scan.top_mints(10).len(), 3);
+        assert!(scan.top_mints(0).is_empty());
+    }
+
+    fn token_account(mint: Pubkey, amount: u64) -> Account {
+        let mut data = vec![0u8; 165];
+        data[..32].copy_from_slice(mint.as_ref());
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data[108] = 1; // initialized
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_analyze_parallel_matches_sequential() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let accounts: Vec<(Pubkey, Account)> = (0..PARALLEL_MIN_ACCOUNTS * 2)
+            .map(|i| {
+                let mint = if i % 3 == 0 { a } else { b };
+                (Pubkey::new_unique(), token_account(mint, (i % 2) as u64))
+            })
+            .collect();
+        let ignore = HashSet::from([a]);
+
+        let par = analyze(&accounts, &ignore);
+        let seq = accounts
+            .iter()
+            .fold(WalletScan::default(), |s, (_, acc)| s.tally(acc, &ignore));
+
+        assert_eq!(par.total_accs, accounts.len());
+        assert_eq!(par.total_accs, seq.total_accs);
+        assert_eq!(par.empty_accs, seq.empty_accs);
+        assert_eq!(par.balance_accs, seq.balance_accs);
+        assert_eq!(par.rent_locked, seq.rent_locked);
+        assert_eq!(par.rent_reclaim, seq.rent_reclaim);
+        assert_eq!(par.mint_counts, seq.mint_counts);
+    }
+
     #[test]
     fn test_calc_score_perfect() {
         assert_eq!(calc_score(0, 0, 0, 100), 100);
@@ -0,0 +1,266 @@
+//! Send SOL to another wallet, e.g. to sweep reclaimed rent
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use solana_sdk::{message::Message, signer::Signer, transaction::Transaction};
+
+#[allow(deprecated)]
+use solana_sdk::system_instruction;
+
+use crate::{solanapay, utils};
+
+/// CLI options for `send`
+pub struct SendOptions<'a> {
+    pub to: &'a str,
+    /// Amount in SOL, `None` for `--all` (clap requires one of them)
+    pub amount: Option<f64>,
+    pub keypair: Option<&'a str>,
+    pub connect: bool,
+    pub dry_run: bool,
+    /// Connect mode: also print the `solana:` link under the QR
+    pub deep_link: bool,
+    pub json: bool,
+}
+
+/// Lamports to send: `amount` SOL, or everything but the fee with `--all`
+fn send_amount(amount: Option<f64>, balance: u64, fee: u64) -> Result<u64> {
+    let lamports = match amount {
+        Some(sol) if !sol.is_finite() || sol <= 0.0 => {
+            anyhow::bail!("Amount must be a positive number of SOL")
+        }
+        Some(sol) => (sol * 1e9).round() as u64,
+        None => balance.saturating_sub(fee),
+    };
+    if lamports == 0 {
+        anyhow::bail!(
+            "Nothing to send: balance {} doesn't cover the fee",
+            utils::format_sol(utils::lamports_to_sol(balance))
+        );
+    }
+    if lamports.saturating_add(fee) > balance {
+        anyhow::bail!(
+            "Insufficient balance: {} + {} fee, have {}",
+            utils::format_sol(utils::lamports_to_sol(lamports)),
+            utils::format_sol(utils::lamports_to_sol(fee)),
+            utils::format_sol(utils::lamports_to_sol(balance))
+        );
+    }
+    Ok(lamports)
+}
+
+/// Both ends must stay rent-exempt: the sender keeps nothing or at least
+/// `rent_min`, and the recipient ends up with at least `rent_min`
+fn check_rent(lamports: u64, remainder: u64, recipient_balance: u64, rent_min: u64) -> Result<()> {
+    if remainder > 0 && remainder < rent_min {
+        anyhow::bail!(
+            "This would leave {} behind, below the rent-exempt minimum of {}: send less or use --all",
+            utils::format_sol(utils::lamports_to_sol(remainder)),
+            utils::format_sol(utils::lamports_to_sol(rent_min))
+        );
+    }
+    if recipient_balance.saturating_add(lamports) < rent_min {
+        anyhow::bail!(
+            "The recipient is a new account and needs at least {} (rent-exempt minimum), sending {}",
+            utils::format_sol(utils::lamports_to_sol(rent_min)),
+            utils::format_sol(utils::lamports_to_sol(lamports))
+        );
+    }
+    Ok(())
+}
+
+pub async fn run(rpc_url: &str, opts: SendOptions<'_>) -> Result<()> {
+    let SendOptions {
+        to,
+        amount,
+        keypair: keypair_path,
+        connect,
+        dry_run,
+        deep_link,
+        json,
+    } = opts;
+
+    let to = utils::parse_pubkey(to)?;
+
+    // wallet from the QR scan, or from the keypair
+    let (wallet, keypair) = if connect {
+        (solanapay::connect_wallet(deep_link, json).await?, None)
+    } else {
+        let kp = match keypair_path {
+            Some(arg) => utils::parse_keypair_arg(arg)?,
+            None => utils::load_keypair(None)?,
+        };
+        (kp.pubkey(), Some(kp))
+    };
+    if wallet == to {
+        anyhow::bail!("Sender and recipient are the same wallet");
+    }
+
+    let client = crate::rpc::client(rpc_url);
+    let balance = client.get_balance(&wallet)?;
+    let recent_hash = client.get_latest_blockhash()?;
+
+    // the fee doesn't depend on the amount, so price the message up front
+    let msg = Message::new_with_blockhash(
+        &[system_instruction::transfer(&wallet, &to, balance)],
+        Some(&wallet),
+        &recent_hash,
+    );
+    let fee = client
+        .get_fee_for_message(&msg)
+        .context("Failed to estimate fee")?;
+    let lamports = send_amount(amount, balance, fee)?;
+    let rent_min = client
+        .get_minimum_balance_for_rent_exemption(0)
+        .context("Failed to get rent-exempt minimum")?;
+    let recipient_balance = client.get_balance(&to)?;
+    check_rent(
+        lamports,
+        balance - lamports - fee,
+        recipient_balance,
+        rent_min,
+    )?;
+    let sol = utils::lamports_to_sol(lamports);
+
+    if json && dry_run {
+        utils::print_json(
+            "send",
+            serde_json::json!({
+                "status": "planned",
+                "from": wallet.to_string(),
+                "to": to.to_string(),
+                "lamports": lamports,
+                "sol": sol,
+                "fee_lamports": fee,
+                "dry_run": true,
+            }),
+        );
+        return Ok(());
+    }
+    if !json {
+        println!(
+            "\n{} Send {} from {} to {}",
+            "💸".bold(),
+            utils::format_sol(sol).green().bold(),
+            utils::short_key(&wallet).cyan(),
+            utils::short_key(&to).cyan()
+        );
+        println!(
+            "  Fee: {}  ·  Balance after: {}",
+            utils::format_sol(utils::lamports_to_sol(fee)),
+            utils::format_sol(utils::lamports_to_sol(balance - lamports - fee))
+        );
+        if dry_run {
+            println!("\n  {} Dry run — remove flag to execute.\n", "🔍".yellow());
+            return Ok(());
+        }
+    }
+    // moves funds, so --json asks too (the prompt is on stderr) unless --yes
+    if !utils::confirm(
+        &format!("Send {} to {}?", utils::format_sol(sol), to),
+        false,
+    )? {
+        if json {
+            utils::print_json("send", serde_json::json!({ "status": "cancelled" }));
+        } else {
+            println!("{}", "  Cancelled.".dimmed());
+        }
+        return Ok(());
+    }
+
+    let ix = system_instruction::transfer(&wallet, &to, lamports);
+
+    let Some(keypair) = keypair else {
+        // SOLANA PAY MODE
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&wallet));
+        tx.message.recent_blockhash = recent_hash;
+
+        let solana_pay_url = solanapay::upload_transactions(
+            solanapay::DEFAULT_RELAY_URL,
+            &[tx],
+            &wallet,
+            &format!("Send {}", utils::format_sol(sol)),
+        )
+        .await?;
+        solanapay::display_qr(&solana_pay_url, deep_link, json);
+        if json {
+            utils::print_json(
+                "send",
+                serde_json::json!({
+                    "status": "uploaded",
+                    "from": wallet.to_string(),
+                    "to": to.to_string(),
+                    "lamports": lamports,
+                    "sol": sol,
+                    "fee_lamports": fee,
+                }),
+            );
+            return Ok(());
+        }
+        println!(
+            "\n{}",
+            "Scan QR with your wallet to sign and send.".dimmed()
+        );
+        return Ok(());
+    };
+
+    // KEYPAIR MODE
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&wallet), &[&keypair], recent_hash);
+    let sig = client
+        .send_and_confirm_transaction(&tx)
+        .context("Failed to send SOL")?;
+
+    if json {
+        utils::print_json(
+            "send",
+            serde_json::json!({
+                "status": "sent",
+                "from": wallet.to_string(),
+                "to": to.to_string(),
+                "lamports": lamports,
+                "sol": sol,
+                "fee_lamports": fee,
+                "signature": sig.to_string(),
+            }),
+        );
+    } else {
+        println!(
+            "\n  {} Sent {}",
+            "✅".green(),
+            utils::format_sol(sol).green()
+        );
+        println!("     https://solscan.io/tx/{}\n", sig.to_string().dimmed());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_amount() {
+        // --all leaves exactly the fee
+        assert_eq!(send_amount(None, 1_000_000, 5_000).unwrap(), 995_000);
+        assert!(send_amount(None, 5_000, 5_000).is_err());
+
+        assert_eq!(
+            send_amount(Some(0.5), 1_000_000_000, 5_000).unwrap(),
+            500_000_000
+        );
+        assert!(send_amount(Some(1.0), 1_000_000_000, 5_000).is_err());
+        assert!(send_amount(Some(0.0), 1_000_000_000, 5_000).is_err());
+    }
+
+    #[test]
+    fn test_check_rent() {
+        let rent = 890_880;
+        // emptying the sender is fine, leaving a sliver is not
+        assert!(check_rent(rent, 0, 0, rent).is_ok());
+        assert!(check_rent(rent, 1, 0, rent).is_err());
+        assert!(check_rent(rent, rent, 0, rent).is_ok());
+
+        // a new recipient needs the minimum, an existing one doesn't
+        assert!(check_rent(1_000, 10 * rent, 0, rent).is_err());
+        assert!(check_rent(1_000, 10 * rent, rent, rent).is_ok());
+    }
+}
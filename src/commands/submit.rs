@@ -0,0 +1,168 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction};
+use std::io::Read;
+
+use crate::{solanapay, utils};
+
+/// Base64 transactions from `text`, one per non-empty line, labelled
+/// `source:line` for messages
+fn parse_input(source: &str, text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| (format!("{source}:{}", i + 1), l.trim().to_string()))
+        .collect()
+}
+
+/// Signers whose signature slot is still empty
+fn missing_signers(tx: &Transaction) -> Vec<Pubkey> {
+    let required = tx.message.header.num_required_signatures as usize;
+    tx.message
+        .account_keys
+        .iter()
+        .take(required)
+        .enumerate()
+        .filter(|(i, _)| {
+            tx.signatures
+                .get(*i)
+                .is_none_or(|s| *s == Signature::default())
+        })
+        .map(|(_, k)| *k)
+        .collect()
+}
+
+/// Fully signed, and every signature checks out
+fn check_signed(tx: &Transaction) -> Result<()> {
+    let missing = missing_signers(tx);
+    if !missing.is_empty() {
+        let keys: Vec<String> = missing.iter().map(utils::short_key).collect();
+        anyhow::bail!("missing signature from {}", keys.join(", "));
+    }
+    tx.verify().context("invalid signature")
+}
+
+/// Broadcast signed transactions from files (or stdin with none / `-`).
+/// Everything is decoded and checked before the first one is sent.
+pub async fn run(rpc_url: &str, files: &[String], json: bool) -> Result<()> {
+    let mut inputs = Vec::new();
+    if files.is_empty() || files.iter().any(|f| f == "-") {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read transactions from stdin")?;
+        inputs.extend(parse_input("stdin", &text));
+    }
+    for path in files.iter().filter(|f| *f != "-") {
+        let text = std::fs::read_to_string(path).context(format!("Failed to open file: {path}"))?;
+        inputs.extend(parse_input(path, &text));
+    }
+    if inputs.is_empty() {
+        anyhow::bail!("No transactions to submit");
+    }
+
+    let mut txs = Vec::new();
+    for (label, b64) in inputs {
+        let tx = solanapay::decode_transaction(&b64).context(label.clone())?;
+        check_signed(&tx).context(label.clone())?;
+        txs.push((label, tx));
+    }
+
+    if !json {
+        println!(
+            "\n{} Submitting {} transaction(s)…\n",
+            "📤".bold(),
+            txs.len().to_string().cyan()
+        );
+    }
+
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for (label, tx) in txs {
+        let client = crate::rpc::client(rpc_url);
+        let res =
+            tokio::task::spawn_blocking(move || client.send_and_confirm_transaction(&tx)).await?;
+        match res {
+            Ok(sig) => {
+                if !json {
+                    println!("  {} {} {}", "✓".green(), label.dimmed(), sig);
+                    println!("     https://solscan.io/tx/{}", sig.to_string().dimmed());
+                }
+                results.push(serde_json::json!({ "source": label, "signature": sig.to_string() }));
+            }
+            Err(e) => {
+                failed += 1;
+                if !json {
+                    println!("  {} {} {}", "✗".red(), label.dimmed(), e.to_string().red());
+                }
+                results.push(serde_json::json!({ "source": label, "error": e.to_string() }));
+            }
+        }
+    }
+
+    if json {
+        utils::print_json(
+            "submit",
+            serde_json::json!({
+                "sent": results.len() - failed,
+                "failed": failed,
+                "results": results,
+            }),
+        );
+    } else {
+        println!();
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{failed} transaction(s) failed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        signature::Keypair,
+        signer::Signer,
+    };
+
+    fn unsigned(payer: &Keypair) -> Transaction {
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(payer.pubkey(), true)],
+        );
+        Transaction::new_with_payer(&[ix], Some(&payer.pubkey()))
+    }
+
+    #[test]
+    fn test_parse_input() {
+        let inputs = parse_input("tx.b64", "AAA\n\n  BBB  \n");
+        assert_eq!(
+            inputs,
+            vec![
+                ("tx.b64:1".to_string(), "AAA".to_string()),
+                ("tx.b64:3".to_string(), "BBB".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_signed() {
+        let payer = Keypair::new();
+        let mut tx = unsigned(&payer);
+        assert_eq!(missing_signers(&tx), vec![payer.pubkey()]);
+        assert!(check_signed(&tx).is_err());
+
+        tx.sign(&[&payer], Hash::new_unique());
+        assert!(missing_signers(&tx).is_empty());
+        assert!(check_signed(&tx).is_ok());
+
+        // tampered after signing
+        tx.message.recent_blockhash = Hash::new_unique();
+        assert!(check_signed(&tx).is_err());
+    }
+}
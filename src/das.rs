@@ -0,0 +1,153 @@
+//! DAS (Digital Asset Standard) RPC helpers
+//! Only DAS-enabled RPCs (Helius, Triton, ...) implement these methods
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+
+const PAGE_LIMIT: usize = 1000;
+const MAX_PAGES: u32 = 10;
+
+/// JSON-RPC "method not found"
+const METHOD_NOT_FOUND: i64 = -32601;
+
+struct AssetsPage {
+    items: usize,
+    compressed: usize,
+}
+
+/// Count compressed assets (cNFTs) owned by wallet.
+/// Returns `None` when the RPC doesn't support `getAssetsByOwner`.
+pub async fn compressed_asset_count(rpc_url: &str, owner: &Pubkey) -> Result<Option<usize>> {
+    let client = reqwest::Client::builder()
+        .default_headers(crate::rpc::headers())
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+
+    let mut count = 0;
+    for page in 1..=MAX_PAGES {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getAssetsByOwner",
+            "params": {
+                "ownerAddress": owner.to_string(),
+                "page": page,
+                "limit": PAGE_LIMIT,
+            }
+        });
+
+        crate::rpc::count_call();
+        let resp: Value = client
+            .post(rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .context("DAS request failed")?
+            .json()
+            .await
+            .context("Invalid DAS response")?;
+        crate::rpc::print_raw("getAssetsByOwner", &resp);
+
+        let Some(p) = parse_assets_page(&resp)? else {
+            return Ok(None);
+        };
+        count += p.compressed;
+
+        if p.items < PAGE_LIMIT {
+            break;
+        }
+    }
+
+    Ok(Some(count))
+}
+
+/// Human-readable cNFT count for command output
+pub fn describe_count(res: &Result<Option<usize>>) -> String {
+    match res {
+        Ok(Some(n)) => n.to_string().white().to_string(),
+        Ok(None) => "unsupported by this RPC".dimmed().to_string(),
+        Err(e) => format!("lookup failed ({e})").dimmed().to_string(),
+    }
+}
+
+fn parse_assets_page(resp: &Value) -> Result<Option<AssetsPage>> {
+    if let Some(err) = resp.get("error") {
+        if err.get("code").and_then(Value::as_i64) == Some(METHOD_NOT_FOUND) {
+            return Ok(None);
+        }
+        anyhow::bail!(
+            "DAS error: {}",
+            err.get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+        );
+    }
+
+    let items = resp
+        .pointer("/result/items")
+        .and_then(Value::as_array)
+        .context("Invalid DAS response")?;
+
+    let compressed = items
+        .iter()
+        .filter(|i| {
+            i.pointer("/compression/compressed")
+                .and_then(Value::as_bool)
+                == Some(true)
+        })
+        .count();
+
+    Ok(Some(AssetsPage {
+        items: items.len(),
+        compressed,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_assets_page() {
+        let resp = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "total": 3,
+                "items": [
+                    { "id": "a", "compression": { "compressed": true } },
+                    { "id": "b", "compression": { "compressed": false } },
+                    { "id": "c", "compression": { "compressed": true } },
+                ]
+            },
+            "id": 1
+        });
+
+        let page = parse_assets_page(&resp).unwrap().unwrap();
+        assert_eq!(page.items, 3);
+        assert_eq!(page.compressed, 2);
+    }
+
+    #[test]
+    fn test_parse_assets_page_unsupported() {
+        let resp = json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32601, "message": "Method not found" },
+            "id": 1
+        });
+        assert!(parse_assets_page(&resp).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_assets_page_error() {
+        let resp = json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32602, "message": "Invalid params" },
+            "id": 1
+        });
+        assert!(parse_assets_page(&resp).is_err());
+
+        assert!(parse_assets_page(&json!({ "result": {} })).is_err());
+    }
+}
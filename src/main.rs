@@ -1,12 +1,14 @@
 mod commands;
+mod das;
 mod price;
 mod rpc;
 mod solanapay;
+mod token;
 mod utils;
 
 use clap::{Parser, Subcommand};
 use colored::Colorize;
-use std::env;
+use std::{env, time::Instant};
 
 #[derive(Parser)]
 #[command(
@@ -29,9 +31,50 @@ struct App {
     #[arg(long, global = true, env = "SOLANA_RPC_NODE")]
     rpc: Option<String>,
 
+    /// Extra RPC HTTP header, e.g. "Authorization: Bearer KEY" (repeatable)
+    #[arg(long, global = true)]
+    rpc_header: Vec<String>,
+
     /// JSON output
     #[arg(long, global = true)]
     json: bool,
+
+    /// Warn if the RPC node runs an outdated version
+    #[arg(long, global = true)]
+    check_rpc: bool,
+
+    /// Skip price lookups and the wallet relay, use only the RPC
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Answer yes to all confirmation prompts (for CI and scripts)
+    #[arg(short = 'y', long, global = true)]
+    yes: bool,
+
+    /// RPC commitment (default confirmed); after the subcommand it overrides
+    /// one given before it, e.g. `sol-tool clean --commitment finalized`
+    #[arg(long, global = true, value_parser = ["processed", "confirmed", "finalized"])]
+    commitment: Option<String>,
+
+    /// Print run time and RPC request count to stderr when done
+    #[arg(long, global = true)]
+    stats: bool,
+
+    /// Echo every RPC response (the JSON result, unparsed) to stderr, for debugging
+    #[arg(long, global = true)]
+    raw: bool,
+
+    /// Seconds between wallet relay polls in --connect mode (backs off to 5s)
+    #[arg(long, global = true, default_value_t = solanapay::DEFAULT_POLL_SECS)]
+    poll_interval: u64,
+
+    /// Tries per wallet relay request; connection errors and 5xx (cold starts) are retried
+    #[arg(long, global = true, default_value_t = solanapay::DEFAULT_RELAY_ATTEMPTS, value_parser = clap::value_parser!(u32).range(1..))]
+    relay_attempts: u32,
+
+    /// Timeout for price API requests, in seconds
+    #[arg(long, global = true, default_value_t = price::DEFAULT_TIMEOUT_SECS)]
+    price_timeout: u64,
 }
 
 #[derive(Subcommand)]
@@ -47,23 +90,169 @@ enum Commands {
         dry_run: bool,
         #[arg(long, default_value_t = 10)]
         batch: usize,
-        #[arg(long)]
+        /// Also close accounts holding at most this many tokens (mint decimals applied)
+        #[arg(long, value_name = "TOKENS")]
         dust: Option<f64>,
+        /// Also close accounts whose tokens are worth less than their rent
+        #[arg(long)]
+        dust_below_rent: bool,
+        /// Also close accounts holding less than this percent of the mint's supply
+        #[arg(long, value_name = "PCT")]
+        dust_supply_pct: Option<f64>,
         #[arg(long)]
         connect: bool,
+        /// Comma-separated RPC URLs used round-robin in batch mode (-f)
+        #[arg(long)]
+        rpc_pool: Option<String>,
+        /// Token program(s) to clean
+        #[arg(long, default_value = "both", value_parser = ["token", "token2022", "both"])]
+        owner_program: String,
+        /// Compute-unit price in micro-lamports, or "auto" to estimate per batch
+        #[arg(long, default_value = "1000")]
+        priority_fee: String,
+        /// Prompt before sending each batch (keypair mode)
+        #[arg(long, conflicts_with_all = ["connect", "file"])]
+        confirm_each: bool,
+        /// How many closeable accounts to list (0 = all)
+        #[arg(long, default_value_t = 12)]
+        preview: usize,
+        /// Decimal places for dust balances in the preview
+        #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u8).range(0..=18))]
+        precision: u8,
+        /// Summarize the preview per mint instead of listing every account
+        #[arg(long)]
+        group: bool,
+        /// With --burn, refuse if any single account's tokens are worth more (USD)
+        #[arg(long, default_value_t = 1.0)]
+        max_dust_usd: f64,
+        /// Burn even accounts above --max-dust-usd or without a price
+        #[arg(long)]
+        force: bool,
+        /// Leave one closeable account open for every mint (the ATA if empty),
+        /// unless the wallet keeps a funded account for it
+        #[arg(long)]
+        keep_one_per_mint: bool,
+        /// Batch mode (-f): stop all wallets after the first failure
+        #[arg(long, requires = "file")]
+        fail_fast: bool,
+        /// Burn dust balances first so --dust accounts can be closed
+        #[arg(long)]
+        burn: bool,
+        /// Let --burn destroy 0-decimal tokens (NFTs) and mints with unknown decimals
+        #[arg(long, requires = "burn")]
+        burn_nfts: bool,
+        /// Also print a tappable link under the QR (for phone terminals)
+        #[arg(long, requires = "connect")]
+        deep_link: bool,
+        /// Connect mode: split the batches over several QRs, N transactions each
+        #[arg(long, value_name = "N", requires = "connect", value_parser = clap::value_parser!(u16).range(1..))]
+        qr_chunk: Option<u16>,
+        /// Check the SOL balance change against the expected net (keypair mode)
+        #[arg(long, conflicts_with_all = ["connect", "dry_run"])]
+        verify: bool,
+        /// Mints never closed, comma-separated (default: WSOL; "none" clears)
+        #[arg(long)]
+        ignore_mints: Option<String>,
+        /// Write unsigned transactions (base64) to this directory instead of sending
+        #[arg(long, value_name = "DIR", conflicts_with_all = ["connect", "file", "dry_run"])]
+        dump_tx: Option<String>,
+        /// Durable nonce accounts for --dump-tx, one per batch, so the files don't expire
+        #[arg(
+            long,
+            value_name = "ACCOUNTS",
+            value_delimiter = ',',
+            requires = "dump_tx"
+        )]
+        nonce: Vec<String>,
+        /// Write the batch instructions as JSON for a multisig vault (the wallet), e.g. Squads
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["connect", "file", "dry_run", "dump_tx", "keypair"])]
+        multisig: Option<String>,
     },
 
     /// 💰 Token portfolio & prices
     Portfolio {
-        wallet: String,
+        #[arg(required_unless_present = "file")]
+        wallet: Option<String>,
+        /// File of wallet public keys (one per line) to aggregate
+        #[arg(short, long, conflicts_with = "wallet")]
+        file: Option<String>,
         #[arg(long, default_value_t = 0.01)]
         min_usd: f64,
+        /// Hide tokens with a smaller balance, even unpriced ones
+        #[arg(long, default_value_t = 0.0)]
+        min_balance: f64,
         #[arg(long, default_value = "value")]
         sort: String,
+        /// Flip the sort order (e.g. smallest value first)
+        #[arg(long)]
+        reverse: bool,
+        /// Hide tokens without a known price
+        #[arg(long)]
+        hide_unpriced: bool,
+        /// Show all tokens, ignoring --min-usd
+        #[arg(long)]
+        include_zero_value: bool,
+        /// Show only the top N tokens, summarize the rest (JSON stays complete)
+        #[arg(long)]
+        top: Option<usize>,
+        /// Drop these mints from display and totals (addresses or symbols, e.g. USDC,USDT)
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
+        /// Keep running and reprint totals when balances change (WebSocket)
+        #[arg(long, conflicts_with_all = ["file", "summary", "alert_below", "alert_above", "exclude"])]
+        watch: bool,
+        /// Seconds between refreshes for --watch when the WebSocket is unavailable or drops
+        #[arg(long, default_value_t = 30)]
+        watch_interval: u64,
+        /// Count compressed NFTs (needs a DAS-enabled RPC)
+        #[arg(long)]
+        with_cnfts: bool,
+        /// Add rent locked in closeable accounts to the total
+        #[arg(long)]
+        with_rent: bool,
+        /// Print only the totals on one line
+        #[arg(long)]
+        summary: bool,
+        /// Exit with code 10 if the total USD value drops below this
+        #[arg(long)]
+        alert_below: Option<f64>,
+        /// Exit with code 11 if the total USD value rises above this
+        #[arg(long)]
+        alert_above: Option<f64>,
+    },
+
+    /// 📋 List token accounts
+    Accounts {
+        wallet: String,
+        /// Only accounts with zero balance
+        #[arg(long)]
+        empty: bool,
+        /// Only frozen accounts
+        #[arg(long)]
+        frozen: bool,
+        /// Only accounts with a delegate approval
+        #[arg(long)]
+        delegated: bool,
+        /// Only accounts of this mint (address or symbol, e.g. USDC)
+        #[arg(long)]
+        mint: Option<String>,
     },
 
     /// 🔍 Wallet health check
-    Scan { wallet: String },
+    Scan {
+        /// One or more wallets (several give a combined report)
+        #[arg(required = true)]
+        wallets: Vec<String>,
+        /// Count compressed NFTs (needs a DAS-enabled RPC)
+        #[arg(long)]
+        with_cnfts: bool,
+        /// List the N mints with the most token accounts
+        #[arg(long, default_value_t = 0)]
+        top_mints: usize,
+        /// Mints not counted as empty clutter, comma-separated (default: WSOL; "none" clears)
+        #[arg(long)]
+        ignore_mints: Option<String>,
+    },
 
     /// 🏎️ RPC benchmark
     RpcBench {
@@ -71,6 +260,30 @@ enum Commands {
         extra: Option<String>,
         #[arg(long, default_value_t = 10)]
         count: usize,
+        /// Show a latency distribution sparkline per endpoint
+        #[arg(long)]
+        histogram: bool,
+        /// Pick an endpoint afterwards and print its export line
+        #[arg(long)]
+        set_rpc: bool,
+        /// Reference endpoint, always benchmarked; others shown relative to it
+        #[arg(long)]
+        baseline: Option<String>,
+        /// Rerun until Ctrl+C, redrawing the table with a rolling average
+        #[arg(long, conflicts_with = "set_rpc")]
+        watch: bool,
+        /// Seconds between --watch runs
+        #[arg(long, default_value_t = 30, requires = "watch")]
+        interval: u64,
+        /// Exit with code 12 if the best success rate (%) is below this
+        #[arg(long, conflicts_with = "watch")]
+        min_success: Option<f64>,
+        /// Exit with code 13 if the fastest average latency (ms) is above this
+        #[arg(long, conflicts_with = "watch")]
+        max_avg: Option<f64>,
+        /// Also show the best endpoint per provider (Helius, QuickNode, …)
+        #[arg(long)]
+        group_by_provider: bool,
     },
 
     /// 📡 Live tx monitor
@@ -78,12 +291,92 @@ enum Commands {
         wallet: String,
         #[arg(long, default_value_t = 3)]
         interval: u64,
+        /// Only show txs after this time (ISO date or relative: 2h, 3d)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only show txs before this time (ISO date or relative: 2h, 3d)
+        #[arg(long)]
+        until: Option<String>,
+        /// Print recent txs and exit instead of watching
+        #[arg(long)]
+        once: bool,
+        /// How many recent txs to fetch with --once
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Emit one JSON array (closed on Ctrl+C) instead of JSON lines
+        #[arg(long)]
+        json_array: bool,
+        /// How explorer links are shown
+        #[arg(long, default_value = "plain", value_parser = ["plain", "markdown", "none"])]
+        link_style: String,
+        /// Only show txs that invoke this program (repeatable)
+        #[arg(long = "program")]
+        programs: Vec<String>,
+    },
+
+    /// 📜 Recent transactions as a table
+    Activity {
+        wallet: String,
+        /// How many recent txs to list
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Fetch each tx for its SOL change (one request per tx)
+        #[arg(long)]
+        detailed: bool,
+        /// Only txs where SOL came in or went out (implies --detailed)
+        #[arg(long, value_enum)]
+        direction: Option<commands::activity::Direction>,
+        /// Only txs moving at least this much SOL (implies --detailed)
+        #[arg(long)]
+        min_amount: Option<f64>,
     },
 
     /// 🏦 Rent exemption table
     Rent {
         #[arg(long)]
         size: Option<usize>,
+        /// Total rent to fund this many accounts of --size
+        #[arg(long, default_value_t = 1, requires = "size", value_parser = clap::value_parser!(u64).range(1..))]
+        count: u64,
+        /// Explain that rent is refunded on close (net cost 0)
+        #[arg(long)]
+        net: bool,
+    },
+
+    /// 🔁 Convert between SOL, lamports and USD
+    Convert {
+        amount: f64,
+        #[arg(default_value = "sol", value_parser = ["sol", "lamports", "usd"])]
+        unit: String,
+    },
+
+    /// 📤 Broadcast signed transactions (base64, one per line)
+    Submit {
+        /// Files of signed transactions; none or "-" reads stdin
+        files: Vec<String>,
+    },
+
+    /// 💸 Send SOL to another wallet
+    Send {
+        /// Recipient address
+        #[arg(long)]
+        to: String,
+        /// Amount in SOL
+        #[arg(long, required_unless_present = "all", conflicts_with = "all")]
+        amount: Option<f64>,
+        /// Send the whole balance minus the fee
+        #[arg(long)]
+        all: bool,
+        #[arg(short, long)]
+        keypair: Option<String>,
+        #[arg(long, conflicts_with = "keypair")]
+        connect: bool,
+        /// Show the transfer and fee without sending
+        #[arg(long)]
+        dry_run: bool,
+        /// Also print a tappable link under the QR (for phone terminals)
+        #[arg(long, requires = "connect")]
+        deep_link: bool,
     },
 
     /// 🧪 Create ATA (test util)
@@ -92,10 +385,29 @@ enum Commands {
         wallet: Option<String>,
         #[arg(short, long)]
         keypair: Option<String>,
+        /// Mint address or symbol (USDC, USDT, SOL, BONK, ...), default USDC
         #[arg(long, short)]
         mint: Option<String>,
         #[arg(long)]
         connect: bool,
+        /// Simulate the transaction instead of sending it
+        #[arg(long)]
+        simulate: bool,
+        /// Retries on transient send failures (fresh blockhash each time)
+        #[arg(long, default_value_t = 3)]
+        max_retries: u32,
+        /// Show the derived ATA and planned instruction without sending
+        #[arg(long)]
+        dry_run: bool,
+        /// Also print a tappable link under the QR (for phone terminals)
+        #[arg(long, requires = "connect")]
+        deep_link: bool,
+        /// Write the unsigned transaction (base64) to this directory instead of sending
+        #[arg(long, value_name = "DIR", conflicts_with_all = ["connect", "simulate", "dry_run"])]
+        dump_tx: Option<String>,
+        /// Durable nonce account for --dump-tx, so the file doesn't expire
+        #[arg(long, value_name = "ACCOUNT", requires = "dump_tx")]
+        nonce: Option<String>,
     },
 }
 
@@ -105,12 +417,30 @@ async fn main() -> anyhow::Result<()> {
     let _ = dotenvy::dotenv();
 
     let app = App::parse();
+    rpc::set_headers(&app.rpc_header)?;
+    rpc::set_commitment(app.commitment.as_deref())?;
+    rpc::set_raw(app.raw);
+    price::set_timeout(app.price_timeout);
+    utils::set_offline(app.offline);
+    utils::set_assume_yes(app.yes);
+    solanapay::set_poll_interval(app.poll_interval);
+    solanapay::set_relay_attempts(app.relay_attempts);
+
+    // --rpc, SOLANA_RPC_NODE (env or .env), Solana CLI config, mainnet-beta
+    let rpc_url = app
+        .rpc
+        .or_else(|| env::var("SOLANA_RPC_NODE").ok())
+        .or_else(rpc::solana_cli_rpc_url)
+        .unwrap_or_else(|| "https://api.mainnet-beta.solana.com".into());
 
-    let rpc_url = app.rpc.unwrap_or_else(|| {
-        env::var("SOLANA_RPC_NODE").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".into())
-    });
+    let started = Instant::now();
+    if app.check_rpc {
+        check_rpc_version(&rpc_url).await;
+    }
 
-    match app.cmd {
+    // `--alert-*` / rpc-bench gates: exit code once the footer is out
+    let mut exit_code = None;
+    let result = match app.cmd {
         Commands::Clean {
             wallet,
             keypair,
@@ -118,48 +448,311 @@ async fn main() -> anyhow::Result<()> {
             dry_run,
             batch,
             dust,
+            dust_below_rent,
+            dust_supply_pct,
             connect,
+            rpc_pool,
+            owner_program,
+            priority_fee,
+            confirm_each,
+            preview,
+            precision,
+            group,
+            max_dust_usd,
+            force,
+            keep_one_per_mint,
+            fail_fast,
+            burn,
+            burn_nfts,
+            deep_link,
+            qr_chunk,
+            verify,
+            ignore_mints,
+            dump_tx,
+            nonce,
+            multisig,
         } => {
+            let ignore_mints = token::ignored_mints(ignore_mints.as_deref())?;
+            let nonce = nonce
+                .iter()
+                .map(|n| utils::parse_pubkey(n))
+                .collect::<anyhow::Result<Vec<_>>>()?;
             commands::clean::run(
                 &rpc_url,
-                wallet.as_deref(),
-                keypair.as_deref(),
-                file.as_deref(),
-                dry_run,
-                batch.clamp(1, 20),
-                dust,
-                connect,
-                app.json,
+                commands::clean::CleanOptions {
+                    wallet: wallet.as_deref(),
+                    keypair: keypair.as_deref(),
+                    file: file.as_deref(),
+                    dry_run,
+                    batch_size: batch.clamp(1, 20),
+                    dust,
+                    dust_below_rent,
+                    dust_supply_pct,
+                    connect,
+                    rpc_pool: rpc_pool.as_deref(),
+                    owner_program: &owner_program,
+                    priority_fee: &priority_fee,
+                    confirm_each,
+                    preview,
+                    precision: precision.into(),
+                    group,
+                    max_dust_usd,
+                    force,
+                    keep_one_per_mint,
+                    fail_fast,
+                    burn,
+                    burn_nfts,
+                    deep_link,
+                    qr_chunk: qr_chunk.map(usize::from),
+                    verify,
+                    ignore_mints: &ignore_mints,
+                    dump_tx: dump_tx.as_deref(),
+                    nonce: &nonce,
+                    multisig: multisig.as_deref(),
+                    json: app.json,
+                },
             )
             .await
         }
         Commands::Portfolio {
             wallet,
+            file,
             min_usd,
+            min_balance,
             sort,
-        } => commands::portfolio::run(&rpc_url, &wallet, min_usd, &sort, app.json).await,
-        Commands::Scan { wallet } => commands::scan::run(&rpc_url, &wallet, app.json).await,
-        Commands::RpcBench { extra, count } => {
-            commands::rpc_bench::run(&rpc_url, extra.as_deref(), count, app.json).await
+            reverse,
+            hide_unpriced,
+            include_zero_value,
+            top,
+            exclude,
+            watch,
+            watch_interval,
+            with_cnfts,
+            with_rent,
+            summary,
+            alert_below,
+            alert_above,
+        } => {
+            let exclude = exclude
+                .iter()
+                .map(|m| token::resolve_mint(m))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            commands::portfolio::run(
+                &rpc_url,
+                commands::portfolio::PortfolioOptions {
+                    wallet: wallet.as_deref(),
+                    file: file.as_deref(),
+                    min_usd,
+                    min_balance,
+                    sort: &sort,
+                    reverse,
+                    hide_unpriced,
+                    include_zero_value,
+                    top,
+                    exclude: &exclude,
+                    watch,
+                    watch_interval,
+                    with_cnfts,
+                    with_rent,
+                    summary,
+                    alert_below,
+                    alert_above,
+                    json: app.json,
+                },
+            )
+            .await
+            .map(|code| exit_code = code)
         }
-        Commands::Monitor { wallet, interval } => {
-            commands::monitor::run(&rpc_url, &wallet, interval).await
+        Commands::Accounts {
+            wallet,
+            empty,
+            frozen,
+            delegated,
+            mint,
+        } => {
+            let filter = commands::accounts::AccountFilter {
+                empty,
+                frozen,
+                delegated,
+                mint: mint.as_deref().map(token::resolve_mint).transpose()?,
+            };
+            commands::accounts::run(&rpc_url, &wallet, filter, app.json).await
+        }
+        Commands::Scan {
+            wallets,
+            with_cnfts,
+            top_mints,
+            ignore_mints,
+        } => {
+            let ignore = token::ignored_mints(ignore_mints.as_deref())?;
+            commands::scan::run(&rpc_url, &wallets, with_cnfts, top_mints, &ignore, app.json).await
+        }
+        Commands::RpcBench {
+            extra,
+            count,
+            histogram,
+            set_rpc,
+            baseline,
+            watch,
+            interval,
+            min_success,
+            max_avg,
+            group_by_provider,
+        } => commands::rpc_bench::run(
+            &rpc_url,
+            extra.as_deref(),
+            count,
+            histogram,
+            set_rpc,
+            baseline.as_deref(),
+            watch.then_some(interval.max(1)),
+            commands::rpc_bench::HealthGate {
+                min_success,
+                max_avg,
+            },
+            group_by_provider,
+            app.json,
+        )
+        .await
+        .map(|code| exit_code = code),
+        Commands::Monitor {
+            wallet,
+            interval,
+            since,
+            until,
+            once,
+            limit,
+            json_array,
+            link_style,
+            programs,
+        } => {
+            let programs = programs
+                .iter()
+                .map(|p| utils::parse_pubkey(p))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            commands::monitor::run(
+                &rpc_url,
+                &wallet,
+                interval,
+                since.as_deref(),
+                until.as_deref(),
+                once,
+                limit,
+                json_array,
+                &link_style,
+                &programs,
+                app.json,
+            )
+            .await
+        }
+        Commands::Activity {
+            wallet,
+            limit,
+            detailed,
+            direction,
+            min_amount,
+        } => {
+            let filter = commands::activity::ActivityFilter {
+                direction,
+                min_amount,
+            };
+            commands::activity::run(
+                &rpc_url,
+                commands::activity::ActivityOptions {
+                    wallet: &wallet,
+                    limit,
+                    detailed,
+                    filter,
+                    json: app.json,
+                },
+            )
+            .await
+        }
+        Commands::Rent { size, count, net } => {
+            commands::rent::run(&rpc_url, size, count, net, app.json).await
+        }
+        Commands::Convert { amount, unit } => commands::convert::run(amount, &unit, app.json).await,
+        Commands::Submit { files } => commands::submit::run(&rpc_url, &files, app.json).await,
+        Commands::Send {
+            to,
+            amount,
+            all: _,
+            keypair,
+            connect,
+            dry_run,
+            deep_link,
+        } => {
+            commands::send::run(
+                &rpc_url,
+                commands::send::SendOptions {
+                    to: &to,
+                    amount,
+                    keypair: keypair.as_deref(),
+                    connect,
+                    dry_run,
+                    deep_link,
+                    json: app.json,
+                },
+            )
+            .await
         }
-        Commands::Rent { size } => commands::rent::run(&rpc_url, size, app.json).await,
         Commands::CreateAta {
             wallet,
             keypair,
             mint,
             connect,
+            simulate,
+            max_retries,
+            dry_run,
+            deep_link,
+            dump_tx,
+            nonce,
         } => {
             commands::create_ata::run(
                 &rpc_url,
-                wallet.as_deref(),
-                keypair.as_deref(),
-                mint.as_deref(),
-                connect,
+                commands::create_ata::CreateAtaOptions {
+                    wallet: wallet.as_deref(),
+                    keypair: keypair.as_deref(),
+                    mint: mint.as_deref(),
+                    connect,
+                    simulate,
+                    dry_run,
+                    max_retries,
+                    deep_link,
+                    dump_tx: dump_tx.as_deref(),
+                    nonce: nonce.as_deref().map(utils::parse_pubkey).transpose()?,
+                    json: app.json,
+                },
             )
             .await
         }
+    };
+
+    // stderr, so `--json` output stays clean
+    if app.stats {
+        eprintln!(
+            "{}",
+            utils::stats_line(started.elapsed(), rpc::call_count()).dimmed()
+        );
+    }
+    result?;
+    if let Some(code) = exit_code {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// `--check-rpc`: warn on stderr so `--json` output stays clean
+async fn check_rpc_version(rpc_url: &str) {
+    let client = rpc::client(rpc_url);
+    match tokio::task::spawn_blocking(move || rpc::node_version(&client)).await {
+        Ok(Ok(v)) if rpc::is_outdated(&v) => eprintln!(
+            "{} RPC node runs {} (older than {}), some methods may misbehave",
+            "⚠".yellow(),
+            v.yellow(),
+            rpc::MIN_NODE_VERSION
+        ),
+        Ok(Ok(_)) => {}
+        _ => eprintln!("{} Could not query RPC node version", "⚠".yellow()),
     }
 }
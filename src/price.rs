@@ -2,37 +2,106 @@ use anyhow::Result;
 use reqwest::Client;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
 
+/// Default price endpoint, override with `JUPITER_PRICE_URL`
 const JUPITER_API: &str = "https://api.jup.ag/price/v2";
 pub const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
 
+/// Retries per chunk on timeouts, dropped connections, 429 and 5xx
+const PRICE_RETRIES: u32 = 2;
+const PRICE_BACKOFF: Duration = Duration::from_millis(400);
+pub const DEFAULT_TIMEOUT_SECS: u64 = 15;
+
+/// SOL/USD for this run, set by the first lookup that returns it
+static SOL_PRICE: OnceLock<f64> = OnceLock::new();
+
+/// Shared HTTP client, built on first use
+static CLIENT: OnceLock<Client> = OnceLock::new();
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+/// Set the price request timeout (`--price-timeout`), call before any lookup
+pub fn set_timeout(secs: u64) {
+    let _ = TIMEOUT.set(Duration::from_secs(secs));
+}
+
+fn client() -> &'static Client {
+    CLIENT.get_or_init(|| {
+        let timeout = TIMEOUT
+            .get()
+            .copied()
+            .unwrap_or(Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+        Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("build http client")
+    })
+}
+
+/// GET with a few retries on transient failures, `None` if it never worked
+async fn get_with_retry(url: &str) -> Option<reqwest::Response> {
+    let mut attempt = 0;
+    loop {
+        let res = client().get(url).send().await;
+        let retry = match &res {
+            Ok(r) => is_retryable_status(r.status().as_u16()),
+            Err(e) => e.is_timeout() || e.is_connect(),
+        };
+        if !retry || attempt >= PRICE_RETRIES {
+            return res.ok();
+        }
+        tokio::time::sleep(PRICE_BACKOFF * 2u32.pow(attempt)).await;
+        attempt += 1;
+    }
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+fn api_url() -> String {
+    std::env::var("JUPITER_PRICE_URL").unwrap_or_else(|_| JUPITER_API.to_string())
+}
+
+/// `price/v2` wraps prices in `data` as strings, v3 / lite is a bare map
+/// with numeric `usdPrice`. Unknown mints come back as `null` in v2.
 #[derive(Deserialize)]
-struct JupResponse {
-    data: HashMap<String, PriceData>,
+#[serde(untagged)]
+enum JupResponse {
+    V2 {
+        data: HashMap<String, Option<PriceV2>>,
+    },
+    V3(HashMap<String, PriceV3>),
 }
 
 #[derive(Deserialize)]
-struct PriceData {
+struct PriceV2 {
     price: String,
 }
 
+#[derive(Deserialize)]
+struct PriceV3 {
+    #[serde(rename = "usdPrice")]
+    usd_price: f64,
+}
+
 pub async fn fetch_prices(mints: &[String]) -> Result<HashMap<String, f64>> {
-    if mints.is_empty() {
+    // unpriced everywhere, callers already treat 0 / missing as unknown
+    if mints.is_empty() || crate::utils::is_offline() {
         return Ok(HashMap::new());
     }
 
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .build()?;
-
     let mut prices = HashMap::new();
 
+    let api = api_url();
+
     // Jupiter limits: 100 ids per call
     for chunk in mints.chunks(100) {
         let ids = chunk.join(",");
-        let url = format!("{}?ids={}", JUPITER_API, ids);
+        let url = format!("{}?ids={}", api, ids);
 
-        if let Ok(resp) = client.get(&url).send().await {
+        if let Some(resp) = get_with_retry(&url).await {
             if let Ok(text) = resp.text().await {
                 let parsed = parse_jupiter_response(&text);
                 for (m, p) in parsed {
@@ -42,24 +111,33 @@ pub async fn fetch_prices(mints: &[String]) -> Result<HashMap<String, f64>> {
         }
     }
 
+    if let Some(p) = prices.get(SOL_MINT) {
+        let _ = SOL_PRICE.set(*p);
+    }
+
     Ok(prices)
 }
 
-pub async fn sol_price() -> Result<f64> {
-    let prices = fetch_prices(&[SOL_MINT.to_string()]).await?;
-    Ok(prices.get(SOL_MINT).copied().unwrap_or(0.0))
+/// SOL/USD, fetched at most once per run (any `fetch_prices` including SOL counts).
+/// `None` if the API failed or `--offline` is set, so callers can show
+/// "price unavailable" instead of a misleading $0.00.
+pub async fn sol_price() -> Option<f64> {
+    if let Some(p) = SOL_PRICE.get().filter(|p| **p > 0.0) {
+        return Some(*p);
+    }
+    let prices = fetch_prices(&[SOL_MINT.to_string()]).await.ok()?;
+    prices.get(SOL_MINT).copied().filter(|p| *p > 0.0)
 }
 
 fn parse_jupiter_response(json: &str) -> HashMap<String, f64> {
-    let mut prices = HashMap::new();
-    if let Ok(body) = serde_json::from_str::<JupResponse>(json) {
-        for (m, d) in body.data {
-            if let Ok(p) = d.price.parse::<f64>() {
-                prices.insert(m, p);
-            }
-        }
+    match serde_json::from_str::<JupResponse>(json) {
+        Ok(JupResponse::V2 { data }) => data
+            .into_iter()
+            .filter_map(|(m, d)| Some((m, d?.price.parse::<f64>().ok()?)))
+            .collect(),
+        Ok(JupResponse::V3(data)) => data.into_iter().map(|(m, d)| (m, d.usd_price)).collect(),
+        Err(_) => HashMap::new(),
     }
-    prices
 }
 
 #[cfg(test)]
@@ -93,6 +171,54 @@ mod tests {
         assert_eq!(prices["EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"], 1.00);
     }
 
+    #[test]
+    fn test_parse_jupiter_v2_unknown_mint() {
+        let json = r#"{
+            "data": {
+                "So11111111111111111111111111111111111111112": { "price": "150.5" },
+                "UnknownMint1111111111111111111111111111111": null
+            }
+        }"#;
+
+        let prices = parse_jupiter_response(json);
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[SOL_MINT], 150.5);
+    }
+
+    #[test]
+    fn test_parse_jupiter_v3_response() {
+        let json = r#"{
+            "So11111111111111111111111111111111111111112": {
+                "usdPrice": 147.48,
+                "blockId": 348004023,
+                "decimals": 9,
+                "priceChange24h": 1.29
+            },
+            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v": {
+                "usdPrice": 0.9999,
+                "blockId": 348004023,
+                "decimals": 6,
+                "priceChange24h": 0.0
+            }
+        }"#;
+
+        let prices = parse_jupiter_response(json);
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[SOL_MINT], 147.48);
+        assert_eq!(
+            prices["EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"],
+            0.9999
+        );
+    }
+
+    #[test]
+    fn test_is_retryable_status() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(503));
+        assert!(!is_retryable_status(200));
+        assert!(!is_retryable_status(404));
+    }
+
     #[test]
     fn test_parse_invalid_json() {
         let prices = parse_jupiter_response("invalid json");
@@ -124,11 +250,9 @@ mod tests {
 
     #[tokio::test]
     async fn test_sol_price_integration() {
-        let result = sol_price().await;
-        assert!(result.is_ok());
-
-        let price = result.unwrap();
-        // Price should be non-negative (0 is acceptable if API is down/rate limited)
-        assert!(price >= 0.0, "SOL price should be non-negative: {}", price);
+        // None is acceptable if the API is down/rate limited, never a zero price
+        if let Some(price) = sol_price().await {
+            assert!(price > 0.0, "SOL price should be positive: {}", price);
+        }
     }
 }
@@ -1,19 +1,601 @@
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::commitment_config::CommitmentConfig;
-use std::time::Duration;
+use crate::token::{TOKEN_ACCOUNT_LEN, TOKEN_FIELDS_LEN};
+use anyhow::Context;
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_client::{RpcClient, RpcClientConfig},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    transaction::TransactionError,
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+/// Retries for heavy getProgramAccounts calls
+const GPA_RETRIES: u32 = 4;
+const GPA_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Token-2022 program ID
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEjPxuEb";
+
+/// Oldest node version known to handle every method we call
+pub const MIN_NODE_VERSION: &str = "1.18.0";
+
+/// System program `AdvanceNonceAccount` instruction index
+const ADVANCE_NONCE_TAG: u32 = 4;
+
+/// Compute-unit price (micro-lamports) when no estimate is available
+pub const DEFAULT_PRIORITY_FEE: u64 = 1000;
+/// Percentile of recent prioritization fees we suggest
+const PRIORITY_FEE_PERCENTILE: usize = 75;
+
+/// `--commitment`, `confirmed` unless set
+static COMMITMENT: OnceLock<CommitmentConfig> = OnceLock::new();
+
+/// Install the `--commitment` level, call once at startup
+pub fn set_commitment(level: Option<&str>) -> anyhow::Result<()> {
+    if let Some(level) = level {
+        let _ = COMMITMENT.set(parse_commitment(level)?);
+    }
+    Ok(())
+}
+
+/// Commitment for every RPC request
+pub fn commitment() -> CommitmentConfig {
+    COMMITMENT
+        .get()
+        .copied()
+        .unwrap_or(CommitmentConfig::confirmed())
+}
+
+/// Same, but at least `confirmed`: getTransaction and
+/// getSignaturesForAddress reject `processed`
+pub fn commitment_at_least_confirmed() -> CommitmentConfig {
+    match commitment() {
+        c if c.is_finalized() => c,
+        _ => CommitmentConfig::confirmed(),
+    }
+}
+
+fn parse_commitment(level: &str) -> anyhow::Result<CommitmentConfig> {
+    Ok(match level {
+        "processed" => CommitmentConfig::processed(),
+        "confirmed" => CommitmentConfig::confirmed(),
+        "finalized" => CommitmentConfig::finalized(),
+        _ => anyhow::bail!("Unknown commitment: {level} (use processed, confirmed or finalized)"),
+    })
+}
+
+/// RPC requests sent so far, for `--stats`
+static CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of RPC requests sent by this process
+pub fn call_count() -> u64 {
+    CALLS.load(Ordering::Relaxed)
+}
+
+/// Count a request sent outside `RpcClient` (e.g. DAS)
+pub fn count_call() {
+    CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// `--raw`: echo every RPC result to stderr before it is parsed
+static RAW: OnceLock<bool> = OnceLock::new();
+
+/// Turn on `--raw`, call once at startup
+pub fn set_raw(raw: bool) {
+    let _ = RAW.set(raw);
+}
+
+/// With `--raw`, print one response as the node sent it (e.g. DAS)
+pub fn print_raw(method: &str, response: &serde_json::Value) {
+    if RAW.get().copied().unwrap_or(false) {
+        eprintln!("{}", raw_line(method, response));
+    }
+}
+
+fn raw_line(method: &str, response: &serde_json::Value) -> String {
+    format!("[raw] {method} {response}")
+}
+
+/// `HttpSender` that counts every request it sends (and echoes it with `--raw`)
+struct CountingSender(HttpSender);
+
+#[async_trait]
+impl RpcSender for CountingSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        count_call();
+        let result = self.0.send(request, params).await?;
+        print_raw(&request.to_string(), &result);
+        Ok(result)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.0.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.0.url()
+    }
+}
+
+/// Extra HTTP headers for every RPC request (`--rpc-header`)
+static HEADERS: OnceLock<HeaderMap> = OnceLock::new();
+
+/// Parse and install `Key: Value` headers, call once at startup
+pub fn set_headers(raw: &[String]) -> anyhow::Result<()> {
+    let mut map = HeaderMap::new();
+    for h in raw {
+        let (name, value) = parse_header(h)?;
+        map.insert(name, value);
+    }
+    let _ = HEADERS.set(map);
+    Ok(())
+}
+
+/// Headers set via `--rpc-header` (empty if none)
+pub fn headers() -> HeaderMap {
+    HEADERS.get().cloned().unwrap_or_default()
+}
+
+fn parse_header(raw: &str) -> anyhow::Result<(HeaderName, HeaderValue)> {
+    let (name, value) = raw
+        .split_once(':')
+        .context(format!("Invalid header (expected \"Key: Value\"): {raw}"))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .context(format!("Invalid header name: {}", name.trim()))?;
+    let value =
+        HeaderValue::from_str(value.trim()).context(format!("Invalid header value for {name}"))?;
+    Ok((name, value))
+}
 
 pub fn client(url: &str) -> RpcClient {
-    RpcClient::new_with_timeout_and_commitment(
-        url.to_string(),
-        Duration::from_secs(30),
-        CommitmentConfig::confirmed(),
-    )
+    client_with_timeout(url, Duration::from_secs(30))
 }
 
 pub fn client_with_timeout(url: &str, timeout: Duration) -> RpcClient {
-    RpcClient::new_with_timeout_and_commitment(
-        url.to_string(),
-        timeout,
-        CommitmentConfig::confirmed(),
+    let headers = headers();
+    let sender = if headers.is_empty() {
+        HttpSender::new_with_timeout(url.to_string(), timeout)
+    } else {
+        // custom headers need our own http client behind the sender
+        let http = reqwest_011::Client::builder()
+            .default_headers(sender_headers(&headers))
+            .timeout(timeout)
+            .build()
+            .expect("build http client");
+        HttpSender::new_with_client(url.to_string(), http)
+    };
+    RpcClient::new_sender(
+        CountingSender(sender),
+        RpcClientConfig::with_commitment(commitment()),
     )
 }
+
+/// The same headers for `HttpSender`, which is built on reqwest 0.11
+fn sender_headers(headers: &HeaderMap) -> reqwest_011::header::HeaderMap {
+    use reqwest_011::header::{HeaderName, HeaderValue};
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                HeaderName::from_bytes(name.as_str().as_bytes()).expect("checked by parse_header"),
+                HeaderValue::from_bytes(value.as_bytes()).expect("checked by parse_header"),
+            )
+        })
+        .collect()
+}
+
+/// `json_rpc_url` from the Solana CLI config, if it sets one
+pub fn solana_cli_rpc_url() -> Option<String> {
+    crate::utils::solana_cli_config("json_rpc_url")
+}
+
+/// WebSocket endpoint for an RPC URL: `ws(s)://`, same path and query,
+/// and the validator convention of RPC port + 1 when a port is given
+pub fn ws_url(url: &str) -> String {
+    let (scheme, rest) = match url.split_once("://") {
+        Some(("https", r)) => ("wss", r),
+        Some((_, r)) => ("ws", r),
+        None => ("ws", url),
+    };
+    let split = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (host, tail) = rest.split_at(split);
+    let host = match host
+        .rsplit_once(':')
+        .and_then(|(h, p)| Some((h, p.parse::<u16>().ok()?.checked_add(1)?)))
+    {
+        Some((h, port)) => format!("{h}:{port}"),
+        None => host.to_string(),
+    };
+    format!("{scheme}://{host}{tail}")
+}
+
+/// All token accounts of `owner` under `program` (SPL Token or Token-2022).
+/// Blocking; retries with exponential backoff on rate limits / timeouts.
+/// Account data is cut to what `token::parse_account` reads, see `token_data_slice`.
+pub fn get_token_accounts(
+    client: &RpcClient,
+    owner: &Pubkey,
+    program: &Pubkey,
+) -> ClientResult<Vec<(Pubkey, Account)>> {
+    let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+        32,
+        owner.to_bytes().to_vec(),
+    ))];
+    // Token-2022 accounts grow with extensions, only classic SPL is fixed-size
+    let fixed_size = *program == spl_token::id();
+    if fixed_size {
+        filters.insert(0, RpcFilterType::DataSize(TOKEN_ACCOUNT_LEN as u64));
+    }
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: Some(token_data_slice(fixed_size)),
+            commitment: Some(commitment()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut attempt = 0;
+    loop {
+        match client.get_program_accounts_with_config(program, config.clone()) {
+            Err(e) if attempt < GPA_RETRIES && is_retriable(&e) => {
+                std::thread::sleep(GPA_BACKOFF * 2u32.pow(attempt));
+                attempt += 1;
+            }
+            res => {
+                return res.map(|accs| {
+                    accs.into_iter()
+                        .filter(|(_, a)| fixed_size || is_token_account_layout(&a.data))
+                        .collect()
+                })
+            }
+        }
+    }
+}
+
+/// Wallet with no SOL and no SPL / Token-2022 accounts. Blocking.
+pub fn wallet_unused(client: &RpcClient, wallet: &Pubkey) -> ClientResult<bool> {
+    if client.get_balance(wallet)? > 0 {
+        return Ok(false);
+    }
+    let t22: Pubkey = TOKEN_2022_PROGRAM_ID.parse().unwrap();
+    for program in [spl_token::id(), t22] {
+        if !get_token_accounts(client, wallet, &program)?.is_empty() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Suggested compute-unit price from recent fees paid for `accounts`
+/// (the writable ones). Blocking; falls back to `DEFAULT_PRIORITY_FEE`
+/// if the RPC doesn't support the method or has no data.
+pub fn estimate_priority_fee(client: &RpcClient, accounts: &[Pubkey]) -> u64 {
+    client
+        .get_recent_prioritization_fees(accounts)
+        .ok()
+        .and_then(|fees| {
+            fee_percentile(
+                fees.iter().map(|f| f.prioritization_fee).collect(),
+                PRIORITY_FEE_PERCENTILE,
+            )
+        })
+        .unwrap_or(DEFAULT_PRIORITY_FEE)
+}
+
+fn fee_percentile(mut fees: Vec<u64>, pct: usize) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    fees.sort_unstable();
+    let idx = (pct * (fees.len() - 1) + 50) / 100;
+    Some(fees[idx])
+}
+
+/// `--nonce`: a durable nonce account's stored blockhash and authority.
+/// A transaction using it stays valid until the nonce advances, so files
+/// signed offline don't expire after a minute.
+pub struct DurableNonce {
+    pub account: Pubkey,
+    pub authority: Pubkey,
+    pub blockhash: Hash,
+}
+
+impl DurableNonce {
+    /// Read an initialized nonce account. Blocking.
+    pub fn fetch(client: &RpcClient, account: &Pubkey) -> anyhow::Result<Self> {
+        let acc =
+            solana_client::nonce_utils::get_account_with_commitment(client, account, commitment())
+                .context(format!("Failed to read nonce account {account}"))?;
+        let data = solana_client::nonce_utils::data_from_account(&acc)
+            .context(format!("{account} is not an initialized nonce account"))?;
+        Ok(Self {
+            account: *account,
+            authority: data.authority,
+            blockhash: data.blockhash(),
+        })
+    }
+
+    /// `ixs` behind the AdvanceNonceAccount that has to come first
+    pub fn instructions(&self, ixs: &[Instruction]) -> Vec<Instruction> {
+        let mut out = vec![advance_nonce_ix(&self.account, &self.authority)];
+        out.extend_from_slice(ixs);
+        out
+    }
+}
+
+/// System `AdvanceNonceAccount`, built by hand like `send`'s transfer
+#[allow(deprecated)]
+fn advance_nonce_ix(nonce: &Pubkey, authority: &Pubkey) -> Instruction {
+    use solana_sdk::{system_program, sysvar::recent_blockhashes};
+    Instruction {
+        program_id: system_program::id(),
+        accounts: vec![
+            AccountMeta::new(*nonce, false),
+            AccountMeta::new_readonly(recent_blockhashes::id(), false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: ADVANCE_NONCE_TAG.to_le_bytes().to_vec(),
+    }
+}
+
+/// `solana-core` version reported by the node. Blocking.
+pub fn node_version(client: &RpcClient) -> ClientResult<String> {
+    client.get_version().map(|v| v.solana_core)
+}
+
+/// Older than `MIN_NODE_VERSION`? Unparseable versions get the benefit of the doubt.
+pub fn is_outdated(version: &str) -> bool {
+    match (parse_version(version), parse_version(MIN_NODE_VERSION)) {
+        (Some(v), Some(min)) => v < min,
+        _ => false,
+    }
+}
+
+fn parse_version(s: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = s.trim().split(['.', '-']).map(|p| p.parse().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// Only the parsed fields instead of the full account: a third less for
+/// classic SPL, all extension bytes for Token-2022. Token-2022 keeps the
+/// account type byte after the base layout, so mints can still be told
+/// apart (the data size filter does that for classic SPL).
+fn token_data_slice(fixed_size: bool) -> UiDataSliceConfig {
+    let length = if fixed_size {
+        TOKEN_FIELDS_LEN
+    } else {
+        TOKEN_ACCOUNT_LEN + 1
+    };
+    UiDataSliceConfig { offset: 0, length }
+}
+
+/// Base accounts are exactly 165 bytes; extended Token-2022 data carries an
+/// account-type byte at 165 (1 = mint, 2 = account)
+fn is_token_account_layout(data: &[u8]) -> bool {
+    data.len() == 165 || (data.len() > 165 && data[165] == 2)
+}
+
+/// Rate limits, timeouts and dropped connections
+fn is_transient(e: &ClientError) -> bool {
+    match e.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(re) => {
+            re.is_timeout() || re.is_connect() || re.status().map(|s| s.as_u16()) == Some(429)
+        }
+        _ => {
+            let msg = e.to_string().to_lowercase();
+            msg.contains("429") || msg.contains("too many requests") || msg.contains("timed out")
+        }
+    }
+}
+
+/// Worth another try: transient transport trouble or an expired blockhash.
+/// Anything the program or runtime rejected (bad account, program error,
+/// insufficient funds) fails the same way again.
+pub fn is_retriable(e: &ClientError) -> bool {
+    match e.get_transaction_error() {
+        Some(TransactionError::BlockhashNotFound) => return true,
+        Some(_) => return false,
+        None => {}
+    }
+    if is_transient(e) {
+        return true;
+    }
+    let msg = e.to_string().to_lowercase();
+    [
+        "connection reset",
+        "blockhash not found",
+        "block height exceeded",
+        "unable to confirm",
+    ]
+    .iter()
+    .any(|m| msg.contains(m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commitment() {
+        assert!(parse_commitment("finalized").unwrap().is_finalized());
+        assert!(parse_commitment("confirmed").unwrap().is_confirmed());
+        assert_eq!(
+            parse_commitment("processed").unwrap(),
+            CommitmentConfig::processed()
+        );
+        assert!(parse_commitment("max").is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_advance_nonce_ix_matches_sdk() {
+        let (nonce, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(
+            advance_nonce_ix(&nonce, &authority),
+            solana_sdk::system_instruction::advance_nonce_account(&nonce, &authority)
+        );
+
+        let durable = DurableNonce {
+            account: nonce,
+            authority,
+            blockhash: Hash::new_unique(),
+        };
+        let ixs = durable.instructions(&[advance_nonce_ix(&authority, &nonce)]);
+        assert_eq!(ixs.len(), 2);
+        assert_eq!(ixs[0].accounts[0].pubkey, nonce);
+    }
+
+    #[test]
+    fn test_ws_url() {
+        assert_eq!(
+            ws_url("https://api.mainnet-beta.solana.com"),
+            "wss://api.mainnet-beta.solana.com"
+        );
+        assert_eq!(ws_url("http://127.0.0.1:8899"), "ws://127.0.0.1:8900");
+        assert_eq!(
+            ws_url("https://mainnet.helius-rpc.com/?api-key=KEY"),
+            "wss://mainnet.helius-rpc.com/?api-key=KEY"
+        );
+    }
+
+    #[test]
+    fn test_is_outdated() {
+        assert_eq!(parse_version("2.2.14"), Some((2, 2, 14)));
+        assert_eq!(parse_version("1.18.0-beta"), Some((1, 18, 0)));
+        assert_eq!(parse_version("unknown"), None);
+
+        assert!(is_outdated("1.17.31"));
+        assert!(!is_outdated("1.18.0"));
+        assert!(!is_outdated("2.1.0"));
+        assert!(!is_outdated("garbage"));
+    }
+
+    #[test]
+    fn test_fee_percentile() {
+        assert_eq!(fee_percentile(vec![], 75), None);
+        assert_eq!(fee_percentile(vec![42], 75), Some(42));
+        // unsorted input, p75 of 0..=100 step 10 is 80 (index 7.5 rounds up)
+        let fees = vec![100, 0, 50, 10, 90, 20, 80, 30, 70, 40, 60];
+        assert_eq!(fee_percentile(fees.clone(), 75), Some(80));
+        assert_eq!(fee_percentile(fees, 100), Some(100));
+    }
+
+    #[test]
+    fn test_raw_line() {
+        let result = serde_json::json!({ "context": { "slot": 1 }, "value": 42 });
+        assert_eq!(
+            raw_line(&RpcRequest::GetBalance.to_string(), &result),
+            r#"[raw] getBalance {"context":{"slot":1},"value":42}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let (name, value) = parse_header("Authorization: Bearer abc:123").unwrap();
+        assert_eq!(name, "authorization");
+        assert_eq!(value, "Bearer abc:123");
+
+        let (name, value) = parse_header("x-api-key:KEY").unwrap();
+        assert_eq!(name, "x-api-key");
+        assert_eq!(value, "KEY");
+
+        assert!(parse_header("no-colon").is_err());
+        assert!(parse_header("bad name: v").is_err());
+
+        let (name, value) = parse_header("Authorization: Bearer abc").unwrap();
+        let mut map = HeaderMap::new();
+        map.insert(name, value);
+        let legacy = sender_headers(&map);
+        assert_eq!(legacy.len(), 1);
+        assert_eq!(legacy["authorization"], "Bearer abc");
+    }
+
+    #[test]
+    fn test_is_token_account_layout() {
+        assert!(is_token_account_layout(&[0u8; 165]));
+
+        let mut ext = vec![0u8; 200];
+        ext[165] = 2;
+        assert!(is_token_account_layout(&ext));
+
+        ext[165] = 1; // extended mint
+        assert!(!is_token_account_layout(&ext));
+        assert!(!is_token_account_layout(&[0u8; 82]));
+
+        // a Token-2022 slice keeps the account type byte
+        let mut sliced = vec![0u8; token_data_slice(false).length];
+        sliced[165] = 2;
+        assert!(is_token_account_layout(&sliced));
+        assert_eq!(token_data_slice(true).length, TOKEN_FIELDS_LEN);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let rate_limited = ClientError::from(ClientErrorKind::Custom(
+            "HTTP status client error (429 Too Many Requests)".into(),
+        ));
+        assert!(is_transient(&rate_limited));
+
+        let io = ClientError::from(ClientErrorKind::Io(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "reset",
+        )));
+        assert!(is_transient(&io));
+
+        let fatal = ClientError::from(ClientErrorKind::Custom("Invalid param".into()));
+        assert!(!is_transient(&fatal));
+    }
+
+    #[test]
+    fn test_is_retriable() {
+        let custom = |m: &str| ClientError::from(ClientErrorKind::Custom(m.into()));
+        let tx_err = |e| ClientError::from(ClientErrorKind::TransactionError(e));
+
+        assert!(is_retriable(&custom(
+            "HTTP status client error (429 Too Many Requests)"
+        )));
+        assert!(is_retriable(&custom("operation timed out")));
+        assert!(is_retriable(&custom(
+            "error sending request: connection reset by peer"
+        )));
+        assert!(is_retriable(&custom("Blockhash not found")));
+        assert!(is_retriable(&tx_err(TransactionError::BlockhashNotFound)));
+
+        assert!(!is_retriable(&tx_err(TransactionError::AccountNotFound)));
+        assert!(!is_retriable(&tx_err(TransactionError::InstructionError(
+            0,
+            solana_sdk::instruction::InstructionError::Custom(1),
+        ))));
+        assert!(!is_retriable(&custom(
+            "Invalid param: could not find account"
+        )));
+    }
+}
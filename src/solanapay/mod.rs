@@ -1,38 +1,97 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
 use solana_sdk::pubkey::Pubkey;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 mod relay;
 
 pub use relay::{
-    create_connect_session, display_qr, poll_session, session_to_solana_pay_url,
-    upload_transactions, DEFAULT_RELAY_URL,
+    create_connect_session, decode_transaction, display_qr, encode_transaction, poll_session,
+    session_to_solana_pay_url, set_relay_attempts, upload_transactions, DEFAULT_RELAY_ATTEMPTS,
+    DEFAULT_RELAY_URL,
 };
 
+pub const DEFAULT_POLL_SECS: u64 = 2;
+/// Backoff ceiling while waiting for the wallet
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+static POLL_INTERVAL: OnceLock<Duration> = OnceLock::new();
+
+/// First relay poll interval (`--poll-interval`), call before connecting
+pub fn set_poll_interval(secs: u64) {
+    let _ = POLL_INTERVAL.set(Duration::from_secs(secs.max(1)));
+}
+
+/// 2s → 3s → 4.5s → 5s: back off gently, the relay is shared
+fn next_poll_interval(current: Duration) -> Duration {
+    (current * 3 / 2).min(MAX_POLL_INTERVAL.max(current))
+}
+
 /// connect wallet flow (qr code)
 /// returns wallet pubkey
-pub async fn connect_wallet() -> Result<Pubkey> {
+///
+/// With `json` the QR and progress go to stderr and the connected wallet is
+/// printed to stdout as a `connect` JSON line.
+pub async fn connect_wallet(deep_link: bool, json: bool) -> Result<Pubkey> {
     use std::io::Write;
 
     // nice UI
-    println!("\n{}", "📱 Connect your wallet via QR".cyan().bold());
-    println!(
+    let out = |s: String| {
+        if json {
+            eprintln!("{s}")
+        } else {
+            println!("{s}")
+        }
+    };
+    out(format!(
+        "\n{}",
+        "📱 Connect your wallet via QR".cyan().bold()
+    ));
+    out(format!(
         "{}",
         "Scan with Phantom, Solflare, or Trust Wallet".dimmed()
-    );
+    ));
 
     // create session
     let session_id = create_connect_session(DEFAULT_RELAY_URL, "sol-tool connect").await?;
 
     // show qr
     let url = session_to_solana_pay_url(DEFAULT_RELAY_URL, &session_id);
-    display_qr(&url)?;
+    display_qr(&url, deep_link, json);
+
+    out(format!(
+        "\n{}",
+        "⏳ Waiting for wallet connection...".yellow()
+    ));
 
-    println!("\n{}", "⏳ Waiting for wallet connection...".yellow());
+    let started = Instant::now();
+    let mut interval = POLL_INTERVAL
+        .get()
+        .copied()
+        .unwrap_or(Duration::from_secs(DEFAULT_POLL_SECS));
 
     // poll loop
     loop {
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        // countdown to the next check, one tick per second
+        let mut left = interval.as_secs();
+        while left > 0 {
+            let tick = format!(
+                "\r  next check in {left}s · waiting {}s   ",
+                started.elapsed().as_secs()
+            );
+            if json {
+                eprint!("{tick}");
+                std::io::stderr().flush().ok();
+            } else {
+                print!("{tick}");
+                std::io::stdout().flush().ok();
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            left -= 1;
+        }
+        tokio::time::sleep(interval - Duration::from_secs(interval.as_secs())).await;
+        interval = next_poll_interval(interval);
 
         let poll = poll_session(DEFAULT_RELAY_URL, &session_id).await?;
 
@@ -45,14 +104,42 @@ pub async fn connect_wallet() -> Result<Pubkey> {
                     w.clone()
                 };
 
-                println!("{} Wallet connected: {}", "✓".green(), short);
+                out(format!("\n{} Wallet connected: {}", "✓".green(), short));
 
-                return w.parse().context("Invalid wallet address from relay");
+                let wallet: Pubkey = w.parse().context("Invalid wallet address from relay")?;
+                if json {
+                    crate::utils::print_json(
+                        "connect",
+                        serde_json::json!({
+                            "status": "connected",
+                            "wallet": wallet.to_string(),
+                        }),
+                    );
+                }
+                return Ok(wallet);
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_poll_interval() {
+        let mut d = Duration::from_secs(2);
+        let mut seen = Vec::new();
+        for _ in 0..5 {
+            seen.push(d.as_millis());
+            d = next_poll_interval(d);
+        }
+        assert_eq!(seen, vec![2000, 3000, 4500, 5000, 5000]);
 
-        // dot progress
-        print!(".");
-        std::io::stdout().flush().ok();
+        // a longer configured interval is never shortened
+        assert_eq!(
+            next_poll_interval(Duration::from_secs(10)),
+            Duration::from_secs(10)
+        );
     }
 }
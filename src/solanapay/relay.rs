@@ -3,12 +3,29 @@
 use anyhow::{Context, Result};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use colored::Colorize;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+use std::sync::OnceLock;
+use std::time::Duration;
 
 /// Default relay URL
 pub const DEFAULT_RELAY_URL: &str = "https://unrivaled-torte-81e36b.netlify.app";
 
+/// Max body chars quoted in error messages
+const SNIPPET_LEN: usize = 200;
+
+/// Tries per relay request, the first one included
+pub const DEFAULT_RELAY_ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled after each
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+static RELAY_ATTEMPTS: OnceLock<u32> = OnceLock::new();
+
+/// Tries per relay request (`--relay-attempts`), call before connecting
+pub fn set_relay_attempts(attempts: u32) {
+    let _ = RELAY_ATTEMPTS.set(attempts.max(1));
+}
+
 #[derive(Serialize)]
 struct UploadRequest {
     transactions: Vec<String>,
@@ -36,28 +53,22 @@ pub struct PollResponse {
 /// Create a connect session (no transactions yet)
 /// Returns session ID
 pub async fn create_connect_session(relay_url: &str, label: &str) -> Result<String> {
+    ensure_online()?;
     let request = ConnectRequest {
         mode: "connect".to_string(),
         label: label.to_string(),
     };
 
     let client = reqwest::Client::new();
-    let resp = client
-        .post(format!("{}/.netlify/functions/tx", relay_url))
-        .json(&request)
-        .send()
-        .await
-        .context("Failed to create session")?;
-
-    if !resp.status().is_success() {
-        let error = resp.text().await.unwrap_or_default();
-        anyhow::bail!("Relay error: {}", error);
-    }
+    let resp = send_with_retry(|| {
+        client
+            .post(format!("{}/.netlify/functions/tx", relay_url))
+            .json(&request)
+    })
+    .await
+    .context("Failed to create session")?;
 
-    let session: SessionResponse = resp
-        .json()
-        .await
-        .context("Failed to parse session response")?;
+    let session: SessionResponse = read_json(resp, "session").await?;
 
     Ok(session.id)
 }
@@ -65,21 +76,38 @@ pub async fn create_connect_session(relay_url: &str, label: &str) -> Result<Stri
 /// Poll session for wallet connection
 pub async fn poll_session(relay_url: &str, session_id: &str) -> Result<PollResponse> {
     let client = reqwest::Client::new();
-    let resp = client
-        .get(format!(
+    let resp = send_with_retry(|| {
+        client.get(format!(
             "{}/.netlify/functions/tx?id={}&poll=true",
             relay_url, session_id
         ))
-        .send()
-        .await
-        .context("Failed to poll session")?;
+    })
+    .await
+    .context("Failed to poll session")?;
 
-    if !resp.status().is_success() {
-        let error = resp.text().await.unwrap_or_default();
-        anyhow::bail!("Poll error: {}", error);
+    read_json(resp, "poll").await
+}
+
+/// The relay is a public web service, not reachable in `--offline` mode
+fn ensure_online() -> Result<()> {
+    if crate::utils::is_offline() {
+        anyhow::bail!("--connect needs the wallet relay, which --offline disables");
     }
+    Ok(())
+}
+
+/// Wire format of the relay (and `--dump-tx`): bincode, then base64
+pub fn encode_transaction(tx: &Transaction) -> String {
+    let bytes = bincode::serialize(tx).expect("serialize tx");
+    STANDARD.encode(&bytes)
+}
 
-    resp.json().await.context("Failed to parse poll response")
+/// Inverse of `encode_transaction`, surrounding whitespace ignored
+pub fn decode_transaction(b64: &str) -> Result<Transaction> {
+    let bytes = STANDARD
+        .decode(b64.trim())
+        .context("Transaction is not valid base64")?;
+    bincode::deserialize(&bytes).context("Not a serialized transaction")
 }
 
 /// Upload transactions to relay and return the Solana Pay URL
@@ -89,13 +117,8 @@ pub async fn upload_transactions(
     wallet: &Pubkey,
     label: &str,
 ) -> Result<String> {
-    let tx_base64: Vec<String> = transactions
-        .iter()
-        .map(|tx| {
-            let bytes = bincode::serialize(tx).expect("serialize tx");
-            STANDARD.encode(&bytes)
-        })
-        .collect();
+    ensure_online()?;
+    let tx_base64: Vec<String> = transactions.iter().map(encode_transaction).collect();
 
     let request = UploadRequest {
         transactions: tx_base64,
@@ -104,22 +127,15 @@ pub async fn upload_transactions(
     };
 
     let client = reqwest::Client::new();
-    let resp = client
-        .post(format!("{}/.netlify/functions/tx", relay_url))
-        .json(&request)
-        .send()
-        .await
-        .context("Failed to upload to relay")?;
-
-    if !resp.status().is_success() {
-        let error = resp.text().await.unwrap_or_default();
-        anyhow::bail!("Relay error: {}", error);
-    }
+    let resp = send_with_retry(|| {
+        client
+            .post(format!("{}/.netlify/functions/tx", relay_url))
+            .json(&request)
+    })
+    .await
+    .context("Failed to upload to relay")?;
 
-    let upload_resp: SessionResponse = resp
-        .json()
-        .await
-        .context("Failed to parse relay response")?;
+    let upload_resp: SessionResponse = read_json(resp, "upload").await?;
 
     let function_url = format!("{}/.netlify/functions/tx?id={}", relay_url, upload_resp.id);
     let solana_pay_url = format!("solana:{}", urlencoding::encode(&function_url));
@@ -127,21 +143,149 @@ pub async fn upload_transactions(
     Ok(solana_pay_url)
 }
 
+/// Send a relay request, retrying with backoff while the relay looks cold:
+/// connection errors, timeouts and 5xx. A 4xx comes back right away, since
+/// asking again won't change the answer.
+async fn send_with_retry<F>(request: F) -> reqwest::Result<reqwest::Response>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let attempts = RELAY_ATTEMPTS
+        .get()
+        .copied()
+        .unwrap_or(DEFAULT_RELAY_ATTEMPTS);
+    let mut delay = RETRY_DELAY;
+    for _ in 1..attempts {
+        match request().send().await {
+            Ok(resp) if !is_cold_start(resp.status().as_u16()) => return Ok(resp),
+            Err(e) if e.is_builder() => return Err(e),
+            _ => {}
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+    request().send().await
+}
+
+/// Netlify answers 5xx while a function spins up
+fn is_cold_start(status: u16) -> bool {
+    (500..=599).contains(&status)
+}
+
+/// Check status and content type, then parse the body.
+/// Netlify answers errors with HTML pages, so quote a snippet instead of a bare parse error.
+async fn read_json<T: DeserializeOwned>(resp: reqwest::Response, what: &str) -> Result<T> {
+    let status = resp.status();
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let body = resp.text().await.unwrap_or_default();
+
+    if !status.is_success() {
+        anyhow::bail!(status_error(status.as_u16(), what, &body));
+    }
+    if !is_json(&content_type, &body) {
+        anyhow::bail!(
+            "Relay sent {} instead of JSON for {what}: {}",
+            if content_type.is_empty() {
+                "no content type"
+            } else {
+                &content_type
+            },
+            snippet(&body)
+        );
+    }
+
+    serde_json::from_str(&body)
+        .with_context(|| format!("Unexpected {what} response: {}", snippet(&body)))
+}
+
+fn status_error(status: u16, what: &str, body: &str) -> String {
+    let hint = match status {
+        400..=499 => "request rejected (session expired or relay URL wrong?)",
+        500..=599 => "relay is having trouble, try again in a moment",
+        _ => "unexpected status",
+    };
+    format!(
+        "Relay {what} failed, HTTP {status}: {hint}: {}",
+        snippet(body)
+    )
+}
+
+fn is_json(content_type: &str, body: &str) -> bool {
+    content_type.contains("json") || (content_type.is_empty() && body.trim_start().starts_with('{'))
+}
+
+/// First `SNIPPET_LEN` chars of the body on one line
+fn snippet(body: &str) -> String {
+    let flat = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.is_empty() {
+        return "(empty body)".to_string();
+    }
+    match flat.char_indices().nth(SNIPPET_LEN) {
+        Some((i, _)) => format!("{}…", &flat[..i]),
+        None => flat,
+    }
+}
+
 /// Build Solana Pay URL from session ID
 pub fn session_to_solana_pay_url(relay_url: &str, session_id: &str) -> String {
     let function_url = format!("{}/.netlify/functions/tx?id={}", relay_url, session_id);
     format!("solana:{}", urlencoding::encode(&function_url))
 }
 
-/// Display QR code for Solana Pay URL
-pub fn display_qr(solana_pay_url: &str) -> Result<()> {
-    println!("\n{}", "📱 Scan this QR code with your wallet:".cyan());
-    println!("{}", "(Phantom, Solflare, or Trust Wallet)".dimmed());
-    println!();
+/// Display QR code for Solana Pay URL, plus the raw link with `deep_link`.
+/// With `json` everything goes to stderr so stdout stays machine-readable.
+/// Falls back to the raw link if the QR can't be rendered.
+pub fn display_qr(solana_pay_url: &str, deep_link: bool, json: bool) {
+    let out = |s: String| {
+        if json {
+            eprintln!("{s}")
+        } else {
+            println!("{s}")
+        }
+    };
 
-    qr2term::print_qr(solana_pay_url).context("Failed to generate QR code")?;
+    out(format!(
+        "\n{}",
+        "📱 Scan this QR code with your wallet:".cyan()
+    ));
+    out(format!(
+        "{}\n",
+        "(Phantom, Solflare, or Trust Wallet)".dimmed()
+    ));
+
+    // the session is already uploaded, a rendering hiccup must not lose it
+    let rendered = match qr2term::generate_qr_string(solana_pay_url) {
+        Ok(qr) => {
+            out(qr);
+            true
+        }
+        Err(e) => {
+            out(format!(
+                "{} Could not render the QR code ({e})",
+                "⚠".yellow()
+            ));
+            out("Paste this link into your wallet instead:"
+                .cyan()
+                .to_string());
+            out(solana_pay_url.to_string());
+            false
+        }
+    };
 
-    Ok(())
+    // wallets register the `solana:` scheme, so on a phone the QR payload
+    // itself is the deep link
+    if deep_link && rendered {
+        out(format!(
+            "\n{}",
+            "🔗 Or tap this link on the same device:".cyan()
+        ));
+        out(solana_pay_url.to_string());
+    }
 }
 
 #[cfg(test)]
@@ -185,6 +329,37 @@ mod tests {
         assert!(!poll_resp.connected);
     }
 
+    #[test]
+    fn test_relay_error_helpers() {
+        let html = "<html>\n  <body>Page not found</body>\n</html>";
+        assert_eq!(snippet(html), "<html> <body>Page not found</body> </html>");
+        assert_eq!(snippet(""), "(empty body)");
+        assert!(snippet(&"x".repeat(500)).ends_with('…'));
+
+        assert!(status_error(404, "poll", html).contains("rejected"));
+        assert!(status_error(502, "poll", "").contains("try again"));
+
+        // cold starts are retried, rejected requests are not
+        assert!(is_cold_start(502));
+        assert!(is_cold_start(503));
+        assert!(!is_cold_start(404));
+        assert!(!is_cold_start(200));
+
+        assert!(is_json("application/json; charset=utf-8", ""));
+        assert!(!is_json("text/html", "{"));
+        assert!(is_json("", "{\"id\":\"x\"}"));
+    }
+
+    #[test]
+    fn test_encode_transaction_roundtrip() {
+        let tx = Transaction::default();
+        let back = decode_transaction(&format!("{}\n", encode_transaction(&tx))).unwrap();
+        assert_eq!(back, tx);
+
+        assert!(decode_transaction("not base64!").is_err());
+        assert!(decode_transaction("aGVsbG8=").is_err());
+    }
+
     #[test]
     fn test_upload_request_serialization() {
         let tx = Transaction::default();
@@ -0,0 +1,373 @@
+//! SPL Token / Token-2022 account parsing shared by the commands
+
+use anyhow::Result;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use std::collections::HashSet;
+
+use crate::utils;
+
+/// Base token account size (Token-2022 extensions come after it)
+pub const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Bytes `parse_account` reads: mint through the state byte at 108
+pub const TOKEN_FIELDS_LEN: usize = 109;
+
+/// Symbols accepted wherever a mint is expected (mainnet)
+pub const KNOWN_MINTS: &[(&str, &str)] = &[
+    ("USDC", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+    ("USDT", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+    ("RAY", "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R"),
+    ("SOL", "So11111111111111111111111111111111111111112"),
+    ("BONK", "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"),
+    ("JUP", "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN"),
+    ("mSOL", "mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So"),
+    ("JitoSOL", "J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn"),
+];
+
+/// Mints `clean` and `scan` don't treat as clutter by default: wrapped SOL
+/// under SPL Token and Token-2022. Empty WSOL accounts are left behind by
+/// swaps and reused by wallets.
+pub const DEFAULT_IGNORED_MINTS: &[&str] = &[
+    "So11111111111111111111111111111111111111112",
+    "9pan9bMn5HatX4EJdBwg9VgCa7Uz5HL8N1m5D3NdXejP",
+];
+
+/// `--ignore-mints`: comma-separated mints or symbols replacing the default
+/// set; an empty value or `none` clears it
+pub fn ignored_mints(arg: Option<&str>) -> Result<HashSet<Pubkey>> {
+    match arg.map(str::trim) {
+        None => Ok(DEFAULT_IGNORED_MINTS
+            .iter()
+            .map(|m| m.parse().unwrap())
+            .collect()),
+        Some(list) if list.eq_ignore_ascii_case("none") => Ok(HashSet::new()),
+        Some(list) => list
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(resolve_mint)
+            .collect(),
+    }
+}
+
+/// A `--mint` value: known symbol (any case) or a mint address
+pub fn resolve_mint(input: &str) -> Result<Pubkey> {
+    match KNOWN_MINTS
+        .iter()
+        .find(|(sym, _)| sym.eq_ignore_ascii_case(input.trim()))
+    {
+        Some((_, addr)) => Ok(addr.parse().unwrap()),
+        None => utils::parse_pubkey(input),
+    }
+}
+
+/// Symbol of a known mint
+pub fn symbol(mint: &Pubkey) -> Option<&'static str> {
+    let mint = mint.to_string();
+    KNOWN_MINTS
+        .iter()
+        .find(|(_, addr)| *addr == mint)
+        .map(|(sym, _)| *sym)
+}
+
+/// Symbol of a known mint, else the shortened address
+pub fn label(mint: &Pubkey) -> String {
+    symbol(mint)
+        .map(str::to_string)
+        .unwrap_or_else(|| utils::short_key(mint))
+}
+
+/// The fields we care about from a token account
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedTokenAccount {
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Delegate approval set (COption tag at 72)
+    pub delegated: bool,
+    /// Account state at 108 (2 = frozen)
+    pub frozen: bool,
+}
+
+impl ParsedTokenAccount {
+    /// Empty and unencumbered, so closing it returns the rent
+    pub fn is_reclaimable(&self) -> bool {
+        self.amount == 0 && !self.delegated && !self.frozen
+    }
+}
+
+/// Parse a token account, `None` if the data is too short to be one.
+/// The first `TOKEN_FIELDS_LEN` bytes are enough (a `dataSlice` fetch).
+pub fn parse_account(acc: &Account) -> Option<ParsedTokenAccount> {
+    parse_data(&acc.data)
+}
+
+fn parse_data(data: &[u8]) -> Option<ParsedTokenAccount> {
+    if data.len() < TOKEN_FIELDS_LEN {
+        return None;
+    }
+    Some(ParsedTokenAccount {
+        mint: Pubkey::try_from(&data[0..32]).ok()?,
+        amount: u64::from_le_bytes(data[64..72].try_into().ok()?),
+        delegated: u32::from_le_bytes(data[72..76].try_into().ok()?) == 1,
+        frozen: data[108] == 2,
+    })
+}
+
+/// Token-2022 `TransferFeeConfig` extension type
+const EXT_TRANSFER_FEE_CONFIG: u16 = 1;
+/// Account type byte marking a Token-2022 mint, right after the base layout
+const ACCOUNT_TYPE_MINT: u8 = 1;
+
+/// One transfer fee schedule of a Token-2022 mint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferFee {
+    /// First epoch this schedule applies to
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    fn parse(data: &[u8]) -> Option<Self> {
+        Some(TransferFee {
+            epoch: u64::from_le_bytes(data.get(0..8)?.try_into().ok()?),
+            maximum_fee: u64::from_le_bytes(data.get(8..16)?.try_into().ok()?),
+            basis_points: u16::from_le_bytes(data.get(16..18)?.try_into().ok()?),
+        })
+    }
+
+    /// Fee withheld when moving `amount` raw units: basis points rounded
+    /// up, capped at `maximum_fee` (same as the token program)
+    pub fn calculate(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(10_000);
+        (fee as u64).min(self.maximum_fee)
+    }
+}
+
+/// Older and newer fee schedules of a `TransferFeeConfig`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    /// Schedule in effect at `epoch`
+    pub fn at(&self, epoch: u64) -> TransferFee {
+        if epoch >= self.newer.epoch {
+            self.newer
+        } else {
+            self.older
+        }
+    }
+}
+
+/// Walk the TLV extensions of a Token-2022 mint for its transfer fee config.
+/// `None` for classic mints and mints without the extension.
+pub fn transfer_fee_config(mint_data: &[u8]) -> Option<TransferFeeConfig> {
+    // base mint padded to the account length, then the account type byte
+    if *mint_data.get(TOKEN_ACCOUNT_LEN)? != ACCOUNT_TYPE_MINT {
+        return None;
+    }
+    let mut tlv = &mint_data[TOKEN_ACCOUNT_LEN + 1..];
+    while tlv.len() >= 4 {
+        let ext = u16::from_le_bytes([tlv[0], tlv[1]]);
+        let len = u16::from_le_bytes([tlv[2], tlv[3]]) as usize;
+        let value = tlv.get(4..4 + len)?;
+        if ext == EXT_TRANSFER_FEE_CONFIG {
+            // two authorities (32 + 32) and the withheld amount (8) first
+            return Some(TransferFeeConfig {
+                older: TransferFee::parse(value.get(72..90)?)?,
+                newer: TransferFee::parse(value.get(90..108)?)?,
+            });
+        }
+        tlv = &tlv[4 + len..];
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Token-2022 mint with a `TransferFeeConfig` after some other extension
+    fn fee_mint(older: (u64, u64, u16), newer: (u64, u64, u16)) -> Vec<u8> {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data.push(ACCOUNT_TYPE_MINT);
+        // an unrelated extension first (type 3, 2 bytes)
+        data.extend_from_slice(&[3, 0, 2, 0, 0xaa, 0xbb]);
+        data.extend_from_slice(&EXT_TRANSFER_FEE_CONFIG.to_le_bytes());
+        data.extend_from_slice(&108u16.to_le_bytes());
+        data.extend_from_slice(&[0u8; 72]);
+        for (epoch, max, bps) in [older, newer] {
+            data.extend_from_slice(&epoch.to_le_bytes());
+            data.extend_from_slice(&max.to_le_bytes());
+            data.extend_from_slice(&bps.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_transfer_fee_config() {
+        let data = fee_mint((0, 1_000, 50), (600, 5_000, 100));
+        let cfg = transfer_fee_config(&data).unwrap();
+        assert_eq!(cfg.at(599).basis_points, 50);
+        assert_eq!(cfg.at(600).basis_points, 100);
+        assert_eq!(cfg.at(600).maximum_fee, 5_000);
+
+        // classic mint: nothing past the base layout
+        assert!(transfer_fee_config(&[0u8; 82]).is_none());
+        // cut off inside the extension
+        assert!(transfer_fee_config(&data[..data.len() - 10]).is_none());
+    }
+
+    #[test]
+    fn test_transfer_fee_calculate() {
+        let fee = TransferFee {
+            epoch: 0,
+            maximum_fee: 1_000,
+            basis_points: 100, // 1%
+        };
+        assert_eq!(fee.calculate(10_000), 100);
+        assert_eq!(fee.calculate(150), 2); // rounds up
+        assert_eq!(fee.calculate(1_000_000), 1_000); // capped
+        assert_eq!(fee.calculate(0), 0);
+    }
+
+    fn create_token_account(amount: u64, delegate: Option<Pubkey>, state: u8) -> Account {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        if let Some(d) = delegate {
+            data[72..76].copy_from_slice(&1u32.to_le_bytes()); // Option::Some
+            data[76..108].copy_from_slice(d.as_ref());
+        }
+        data[108] = state; // 1=Initialized, 2=Frozen
+
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_known_mints_are_valid() {
+        for (name, addr) in KNOWN_MINTS {
+            assert!(
+                addr.parse::<Pubkey>().is_ok(),
+                "Invalid mint address for {name}: {addr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ignored_mints() {
+        let wsol: Pubkey = DEFAULT_IGNORED_MINTS[0].parse().unwrap();
+        let defaults = ignored_mints(None).unwrap();
+        assert_eq!(defaults.len(), DEFAULT_IGNORED_MINTS.len());
+        assert!(defaults.contains(&wsol));
+
+        assert!(ignored_mints(Some("")).unwrap().is_empty());
+        assert!(ignored_mints(Some("none")).unwrap().is_empty());
+
+        let custom = ignored_mints(Some("usdc, BONK")).unwrap();
+        assert_eq!(custom.len(), 2);
+        assert!(!custom.contains(&wsol));
+        assert!(ignored_mints(Some("USDC,nope")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_mint() {
+        let usdc: Pubkey = KNOWN_MINTS[0].1.parse().unwrap();
+        assert_eq!(resolve_mint("USDC").unwrap(), usdc);
+        assert_eq!(resolve_mint("usdc").unwrap(), usdc);
+        assert_eq!(resolve_mint("msol").unwrap(), resolve_mint("mSOL").unwrap());
+        assert_eq!(resolve_mint(&usdc.to_string()).unwrap(), usdc);
+        assert!(resolve_mint("NOPE").is_err());
+
+        assert_eq!(symbol(&usdc), Some("USDC"));
+        assert_eq!(symbol(&Pubkey::new_unique()), None);
+
+        assert_eq!(label(&usdc), "USDC");
+        let other = Pubkey::new_unique();
+        assert_eq!(label(&other), utils::short_key(&other));
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let mint = Pubkey::new_unique();
+        let mut acc = create_token_account(42, None, 1);
+        acc.data[0..32].copy_from_slice(mint.as_ref());
+
+        let parsed = parse_account(&acc).unwrap();
+        assert_eq!(parsed.mint, mint);
+        assert_eq!(parsed.amount, 42);
+        assert!(!parsed.delegated);
+        assert!(!parsed.frozen);
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        let parsed = parse_account(&create_token_account(0, None, 1)).unwrap();
+        assert_eq!(parsed.amount, 0);
+    }
+
+    #[test]
+    fn test_parse_delegated() {
+        let acc = create_token_account(0, Some(Pubkey::new_unique()), 1);
+        assert!(parse_account(&acc).unwrap().delegated);
+    }
+
+    #[test]
+    fn test_parse_frozen() {
+        let acc = create_token_account(0, None, 2);
+        assert!(parse_account(&acc).unwrap().frozen);
+    }
+
+    #[test]
+    fn test_is_reclaimable() {
+        assert!(parse_account(&create_token_account(0, None, 1))
+            .unwrap()
+            .is_reclaimable());
+        assert!(!parse_account(&create_token_account(1, None, 1))
+            .unwrap()
+            .is_reclaimable());
+        assert!(
+            !parse_account(&create_token_account(0, Some(Pubkey::new_unique()), 1))
+                .unwrap()
+                .is_reclaimable()
+        );
+        assert!(!parse_account(&create_token_account(0, None, 2))
+            .unwrap()
+            .is_reclaimable());
+    }
+
+    #[test]
+    fn test_parse_token_2022_extended() {
+        // extensions are appended after the base layout
+        let mut acc = create_token_account(7, None, 1);
+        acc.data.extend_from_slice(&[2, 0, 0, 0]);
+        assert_eq!(parse_account(&acc).unwrap().amount, 7);
+    }
+
+    #[test]
+    fn test_parse_too_short() {
+        let mut acc = create_token_account(0, None, 1);
+        acc.data.truncate(108);
+        assert!(parse_account(&acc).is_none());
+        assert!(parse_data(&[]).is_none());
+    }
+
+    #[test]
+    fn test_parse_sliced() {
+        let mut acc = create_token_account(5, Some(Pubkey::new_unique()), 2);
+        let full = parse_account(&acc).unwrap();
+        acc.data.truncate(TOKEN_FIELDS_LEN);
+        assert_eq!(parse_account(&acc).unwrap(), full);
+    }
+}
@@ -1,34 +1,218 @@
 use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use colored::Colorize;
 use solana_sdk::{
     pubkey::Pubkey,
     signature::{Keypair, Signature},
     signer::Signer,
+    transaction::Transaction,
 };
 use std::{path::PathBuf, str::FromStr};
+use zeroize::Zeroizing;
 
+/// Parse an address, also accepting a pasted `solana:` URI or explorer URL
 pub fn parse_pubkey(s: &str) -> Result<Pubkey> {
-    Pubkey::from_str(s).context(format!("Invalid pubkey: {s}"))
+    if let Some(param) = payment_param(s) {
+        anyhow::bail!(
+            "Invalid pubkey: {s} (payment request with `{param}`, pass the address alone and set the amount/token explicitly)"
+        );
+    }
+    let addr = strip_address_url(s);
+    Pubkey::from_str(addr).map_err(|_| match pubkey_hint(addr) {
+        Some(hint) => anyhow::anyhow!("Invalid pubkey: {s} ({hint})"),
+        None => anyhow::anyhow!("Invalid pubkey: {s}"),
+    })
+}
+
+/// `--offline`: skip price lookups and the wallet relay, RPC only
+static OFFLINE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+
+pub fn set_offline(offline: bool) {
+    let _ = OFFLINE.set(offline);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.get().copied().unwrap_or(false)
+}
+
+static ASSUME_YES: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+
+/// `--yes`: answer every prompt with yes
+pub fn set_assume_yes(yes: bool) {
+    let _ = ASSUME_YES.set(yes);
+}
+
+pub fn assume_yes() -> bool {
+    ASSUME_YES.get().copied().unwrap_or(false)
+}
+
+/// Fail fast instead of blocking on a prompt nobody can answer
+pub fn ensure_interactive() -> Result<()> {
+    use std::io::IsTerminal;
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "stdin is not a terminal, can't ask for confirmation (pass --yes to proceed)"
+        );
+    }
+    Ok(())
+}
+
+/// Yes/no prompt, auto-confirmed with `--yes`
+pub fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    if assume_yes() {
+        return Ok(true);
+    }
+    ensure_interactive()?;
+    Ok(dialoguer::Confirm::new()
+        .with_prompt(prompt)
+        .default(default)
+        .interact()?)
+}
+
+/// `--stats` footer, e.g. "Done in 2.4s, 37 RPC calls"
+pub fn stats_line(elapsed: std::time::Duration, rpc_calls: u64) -> String {
+    let plural = if rpc_calls == 1 { "" } else { "s" };
+    format!(
+        "Done in {:.1}s, {rpc_calls} RPC call{plural}",
+        elapsed.as_secs_f64()
+    )
+}
+
+/// No SOL and no token accounts: the wallet was never used
+pub fn is_unused(lamports: u64, token_accounts: usize) -> bool {
+    lamports == 0 && token_accounts == 0
+}
+
+/// Same message for every command instead of an all-zero report
+pub fn print_unused_wallet(command: &str, wallet: &Pubkey, json: bool) {
+    if json {
+        print_json(
+            command,
+            serde_json::json!({ "wallet": wallet.to_string(), "status": "unused" }),
+        );
+    } else {
+        println!(
+            "\n  💤 {}\n",
+            "This wallet appears unused / has no activity".dimmed()
+        );
+    }
+}
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Explorer URL path segments followed by an address
+const EXPLORER_SEGMENTS: &[&str] = &["account", "address", "token"];
+
+/// `solana:` query keys that make the URI a payment request, which an
+/// address alone would silently drop
+const PAYMENT_PARAMS: &[&str] = &["amount", "spl-token"];
+
+/// The first payment key set in a `solana:` URI
+fn payment_param(s: &str) -> Option<&'static str> {
+    let query = s.trim().strip_prefix("solana:")?.split_once('?')?.1;
+    let query = query.split('#').next().unwrap_or(query);
+    query
+        .split('&')
+        .filter_map(|kv| kv.split('=').next())
+        .find_map(|key| PAYMENT_PARAMS.iter().copied().find(|p| *p == key))
+}
+
+/// Bare address out of a `solana:<addr>` URI or an explorer URL
+/// (solscan.io/account/…, explorer.solana.com/address/…), else the input
+fn strip_address_url(s: &str) -> &str {
+    let s = s.trim();
+    if let Some(rest) = s.strip_prefix("solana:") {
+        return rest.split(['?', '#']).next().unwrap_or(rest);
+    }
+    if s.starts_with("https://") || s.starts_with("http://") {
+        let path = s.split(['?', '#']).next().unwrap_or(s);
+        let segments: Vec<&str> = path.split('/').collect();
+        if let Some(addr) = segments
+            .iter()
+            .position(|seg| EXPLORER_SEGMENTS.contains(seg))
+            .and_then(|i| segments.get(i + 1))
+            .filter(|a| !a.is_empty())
+        {
+            return addr;
+        }
+    }
+    s
+}
+
+/// Explain why `s` isn't a wallet address, for the common mistakes
+fn pubkey_hint(s: &str) -> Option<String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Some("address is empty".into());
+    }
+    if s.starts_with("0x") {
+        return Some("looks like an EVM address, not a Solana wallet".into());
+    }
+    if s.contains('.') {
+        return Some("looks like a domain name, use the wallet address it points to".into());
+    }
+    if let Some((i, c)) = s
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !BASE58_ALPHABET.contains(*c))
+    {
+        return Some(format!(
+            "contains invalid base58 character '{c}' at position {}",
+            i + 1
+        ));
+    }
+    match s.len() {
+        86..=88 => Some("looks like a transaction signature, not a wallet".into()),
+        n if !(32..=44).contains(&n) => {
+            Some(format!("has {n} characters, a wallet address has 32-44"))
+        }
+        _ => None,
+    }
 }
 
 pub fn parse_signature(s: &str) -> Result<Signature> {
     Signature::from_str(s).context(format!("Invalid signature: {s}"))
 }
 
-// simple keypair loader
+/// A value from the Solana CLI config (`~/.config/solana/cli/config.yml`),
+/// if the file exists and sets it
+pub fn solana_cli_config(key: &str) -> Option<String> {
+    let home = std::env::var("HOME").ok()?;
+    let path = PathBuf::from(home).join(".config/solana/cli/config.yml");
+    parse_cli_config(&std::fs::read_to_string(path).ok()?, key)
+}
+
+/// The config is flat YAML; values may be quoted
+fn parse_cli_config(yaml: &str, key: &str) -> Option<String> {
+    let value = yaml
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix(':'))?
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'');
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// simple keypair loader: the given path, else the Solana CLI's keypair_path,
+// else ~/.config/solana/id.json
 pub fn load_keypair(path: Option<&str>) -> Result<Keypair> {
     let p = match path {
         Some(p) => PathBuf::from(p),
-        None => {
-            let home = std::env::var("HOME").context("HOME not set")?;
-            PathBuf::from(home).join(".config/solana/id.json")
-        }
+        None => match solana_cli_config("keypair_path") {
+            Some(p) => PathBuf::from(p),
+            None => {
+                let home = std::env::var("HOME").context("HOME not set")?;
+                PathBuf::from(home).join(".config/solana/id.json")
+            }
+        },
     };
 
-    let data =
-        std::fs::read_to_string(&p).context(format!("Can't read keypair: {}", p.display()))?;
+    let data = Zeroizing::new(
+        std::fs::read_to_string(&p).context(format!("Can't read keypair: {}", p.display()))?,
+    );
 
     // try to parse json
     if let Ok(bytes) = serde_json::from_str::<Vec<u8>>(&data) {
+        let bytes = Zeroizing::new(bytes);
         return Keypair::try_from(&bytes[..])
             .map_err(|e| anyhow::anyhow!("Invalid keypair bytes: {}", e));
     }
@@ -37,6 +221,62 @@ pub fn load_keypair(path: Option<&str>) -> Result<Keypair> {
     anyhow::bail!("Invalid keypair file format");
 }
 
+type SecretDecoder = fn(&str) -> Option<Vec<u8>>;
+
+/// Secret key encodings, tried in this order
+const SECRET_FORMATS: [(&str, SecretDecoder); 3] = [
+    ("JSON byte array", |s| serde_json::from_str(s).ok()),
+    ("base58", |s| bs58::decode(s).into_vec().ok()),
+    ("base64", |s| STANDARD.decode(s).ok()),
+];
+
+/// Decode a 64-byte secret key given as a JSON byte array, base58 or
+/// base64. The bytes are wiped when dropped.
+pub fn decode_secret(secret: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let secret = secret.trim();
+    for (_, decode) in SECRET_FORMATS {
+        if let Some(bytes) = decode(secret).map(Zeroizing::new) {
+            if bytes.len() == 64 {
+                return Ok(bytes);
+            }
+        }
+    }
+    let tried: Vec<&str> = SECRET_FORMATS.iter().map(|(name, _)| *name).collect();
+    anyhow::bail!(
+        "Invalid keypair: not a 64-byte secret as {}",
+        tried.join(", ")
+    )
+}
+
+/// Parse a secret key given as a JSON byte array, base58 or base64
+pub fn parse_secret(secret: &str) -> Result<Keypair> {
+    let bytes = decode_secret(secret)?;
+    Keypair::try_from(&bytes[..]).map_err(|e| anyhow::anyhow!("Invalid bytes: {}", e))
+}
+
+/// `--keypair`: `-` for stdin, an existing keypair file, or the secret
+/// itself (JSON byte array, base58, base64)
+pub fn parse_keypair_arg(arg: &str) -> Result<Keypair> {
+    if arg == "-" {
+        return read_keypair_stdin();
+    }
+    if std::path::Path::new(arg).exists() {
+        return solana_sdk::signature::read_keypair_file(arg)
+            .map_err(|e| anyhow::anyhow!("Failed keypair file: {}", e));
+    }
+    parse_secret(arg).context("Not an existing keypair file either")
+}
+
+/// Read a secret key from one line of stdin (`--keypair -`)
+/// keeps it out of shell history and `ps`
+pub fn read_keypair_stdin() -> Result<Keypair> {
+    let mut line = Zeroizing::new(String::new());
+    std::io::stdin()
+        .read_line(&mut line)
+        .context("Failed to read keypair from stdin")?;
+    parse_secret(&line)
+}
+
 pub fn verify_keypair(keypair: &Keypair, wallet: &Pubkey) -> Result<()> {
     if keypair.pubkey() != *wallet {
         anyhow::bail!(
@@ -48,6 +288,75 @@ pub fn verify_keypair(keypair: &Keypair, wallet: &Pubkey) -> Result<()> {
     Ok(())
 }
 
+/// `--dump-tx`: write unsigned transactions to `dir` as `<name>-NNN.b64`
+/// (relay encoding) for signing elsewhere, returns the files written
+pub fn dump_transactions(dir: &str, name: &str, txs: &[Transaction]) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir).context(format!("Failed to create {dir}"))?;
+    txs.iter()
+        .enumerate()
+        .map(|(i, tx)| {
+            let path = PathBuf::from(dir).join(format!("{name}-{:03}.b64", i + 1));
+            std::fs::write(&path, crate::solanapay::encode_transaction(tx))
+                .context(format!("Failed to write {}", path.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Progress line for humans: stdout, or stderr with `--json` so stdout
+/// carries only JSON
+pub fn print_status(line: impl std::fmt::Display, json: bool) {
+    if json {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
+/// Print where `--dump-tx` put the transactions; `durable` with `--nonce`
+pub fn print_dumped(command: &str, files: &[PathBuf], durable: bool, json: bool) {
+    let files: Vec<String> = files.iter().map(|p| p.display().to_string()).collect();
+    if json {
+        print_json(
+            command,
+            serde_json::json!({ "status": "dumped", "files": files, "durable_nonce": durable }),
+        );
+        return;
+    }
+    println!(
+        "\n  {} Wrote {} unsigned transaction(s):",
+        "💾".bold(),
+        files.len()
+    );
+    for f in &files {
+        println!("    {}", f.dimmed());
+    }
+    let hint = if durable {
+        "Valid until the nonce advances, sign (with the nonce authority too) and submit any time."
+    } else {
+        "Sign and submit before the blockhash expires (about a minute)."
+    };
+    println!("  {}", hint.dimmed());
+}
+
+/// Version of the `--json` output format.
+/// Bump major on breaking changes (renamed/removed fields), minor on additions.
+pub const JSON_SCHEMA_VERSION: &str = "1.0";
+
+/// Print a command's JSON output, tagged with schema version and command name
+pub fn print_json(command: &str, value: serde_json::Value) {
+    println!("{}", with_schema(command, value));
+}
+
+/// Tag a JSON object with schema version and command name
+pub fn with_schema(command: &str, mut value: serde_json::Value) -> serde_json::Value {
+    if let serde_json::Value::Object(map) = &mut value {
+        map.insert("schema_version".into(), JSON_SCHEMA_VERSION.into());
+        map.insert("command".into(), command.into());
+    }
+    value
+}
+
 pub fn lamports_to_sol(l: u64) -> f64 {
     l as f64 / 1_000_000_000.0
 }
@@ -97,6 +406,19 @@ pub fn format_usd(usd: f64) -> String {
     format!("${}.{}", result.chars().rev().collect::<String>(), frac)
 }
 
+/// `format_usd`, or "—" when the price is unavailable (not the same as $0.00)
+pub fn format_usd_opt(usd: Option<f64>) -> String {
+    usd.map(format_usd).unwrap_or_else(|| "—".to_string())
+}
+
+/// "(≈ $1.23)" next to a SOL amount, "(price unavailable)" without a SOL price
+pub fn approx_usd(sol: f64, sol_usd: Option<f64>) -> String {
+    match sol_usd {
+        Some(p) => format!("(≈ {})", format_usd(sol * p)),
+        None => "(price unavailable)".to_string(),
+    }
+}
+
 pub fn short_key(pk: &Pubkey) -> String {
     let s = pk.to_string();
     format!("{}…{}", &s[..4], &s[s.len() - 4..])
@@ -115,12 +437,65 @@ pub fn token_amount(raw: u64, decimals: u8) -> f64 {
     raw as f64 / 10f64.powi(decimals as i32)
 }
 
+/// Parse a time bound into a unix timestamp.
+/// Accepts RFC3339 (`2024-05-01T12:00:00Z`), a plain date (`2024-05-01`, UTC midnight)
+/// or a relative offset into the past (`30m`, `2h`, `3d`, `1w`).
+pub fn parse_time_bound(s: &str) -> Result<i64> {
+    parse_time_bound_at(s, chrono::Utc::now().timestamp())
+}
+
+fn parse_time_bound_at(s: &str, now: i64) -> Result<i64> {
+    let s = s.trim();
+
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.timestamp());
+    }
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        if let Some(dt) = d.and_hms_opt(0, 0, 0) {
+            return Ok(dt.and_utc().timestamp());
+        }
+    }
+
+    // relative: <number><unit>
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: i64 = num
+        .parse()
+        .context(format!("Invalid time: {s} (use ISO date or e.g. 2h, 3d)"))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => anyhow::bail!("Invalid time unit in {s} (use s, m, h, d or w)"),
+    };
+    Ok(now - n * secs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use solana_sdk::pubkey::Pubkey;
     use std::str::FromStr;
 
+    #[test]
+    fn test_parse_cli_config() {
+        let yaml = "---\njson_rpc_url: \"https://api.devnet.solana.com\"\nwebsocket_url: ''\nkeypair_path: /home/me/wallets/hot.json\n";
+        assert_eq!(
+            parse_cli_config(yaml, "json_rpc_url").as_deref(),
+            Some("https://api.devnet.solana.com")
+        );
+        assert_eq!(
+            parse_cli_config(yaml, "keypair_path").as_deref(),
+            Some("/home/me/wallets/hot.json")
+        );
+        assert_eq!(parse_cli_config(yaml, "websocket_url"), None);
+        assert_eq!(parse_cli_config(yaml, "commitment"), None);
+        // a key is matched whole, not as a prefix of another
+        assert_eq!(parse_cli_config(yaml, "keypair"), None);
+    }
+
     #[test]
     fn test_format_sol() {
         assert_eq!(format_sol(0.0), "0 SOL");
@@ -128,12 +503,36 @@ mod tests {
         assert_eq!(format_sol(0.000000001), "0.000000001 SOL");
     }
 
+    #[test]
+    fn test_with_schema() {
+        let v = with_schema("scan", serde_json::json!({ "wallet": "abc" }));
+        assert_eq!(v["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(v["command"], "scan");
+        assert_eq!(v["wallet"], "abc");
+    }
+
     #[test]
     fn test_format_usd() {
         assert_eq!(format_usd(0.0), "$0.00");
         assert_eq!(format_usd(0.005), "$0.005000");
         assert_eq!(format_usd(10.50), "$10.50");
         assert_eq!(format_usd(1234.56), "$1,234.56");
+
+        assert_eq!(format_usd_opt(Some(0.0)), "$0.00");
+        assert_eq!(format_usd_opt(None), "—");
+        assert_eq!(approx_usd(2.0, Some(150.0)), "(≈ $300.00)");
+        assert_eq!(approx_usd(0.0, Some(150.0)), "(≈ $0.00)");
+        assert_eq!(approx_usd(2.0, None), "(price unavailable)");
+    }
+
+    #[test]
+    fn test_stats_line() {
+        use std::time::Duration;
+        assert_eq!(
+            stats_line(Duration::from_millis(2430), 37),
+            "Done in 2.4s, 37 RPC calls"
+        );
+        assert_eq!(stats_line(Duration::ZERO, 1), "Done in 0.0s, 1 RPC call");
     }
 
     #[test]
@@ -154,6 +553,24 @@ mod tests {
         assert_eq!(token_amount(123456, 6), 0.123456); // USDC
     }
 
+    #[test]
+    fn test_parse_time_bound() {
+        let now = 1_700_000_000;
+        assert_eq!(parse_time_bound_at("2h", now).unwrap(), now - 7200);
+        assert_eq!(parse_time_bound_at("3d", now).unwrap(), now - 3 * 86_400);
+        assert_eq!(
+            parse_time_bound_at("2024-01-01", now).unwrap(),
+            1_704_067_200
+        );
+        assert_eq!(
+            parse_time_bound_at("2024-01-01T01:00:00Z", now).unwrap(),
+            1_704_070_800
+        );
+
+        assert!(parse_time_bound_at("2y", now).is_err());
+        assert!(parse_time_bound_at("yesterday", now).is_err());
+    }
+
     #[test]
     fn test_verify_keypair_success() {
         let kp = Keypair::new();
@@ -188,6 +605,77 @@ mod tests {
         std::fs::remove_file(tmp).ok();
     }
 
+    #[test]
+    fn test_dump_transactions() {
+        let dir = std::env::temp_dir().join("sol_tool_dump_tx_test");
+        let dir = dir.to_str().unwrap();
+        let txs = vec![Transaction::default(), Transaction::default()];
+
+        let files = dump_transactions(dir, "clean", &txs).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[1].ends_with("clean-002.b64"));
+        let body = std::fs::read_to_string(&files[0]).unwrap();
+        assert_eq!(body, crate::solanapay::encode_transaction(&txs[0]));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_parse_secret() {
+        let kp = Keypair::new();
+
+        let b58 = bs58::encode(kp.to_bytes()).into_string();
+        assert_eq!(parse_secret(&b58).unwrap().pubkey(), kp.pubkey());
+
+        // trailing newline, as read from stdin
+        let json = serde_json::to_string(&kp.to_bytes().to_vec()).unwrap();
+        assert_eq!(
+            parse_secret(&format!("{json}\n")).unwrap().pubkey(),
+            kp.pubkey()
+        );
+
+        let b64 = STANDARD.encode(kp.to_bytes());
+        assert_eq!(parse_secret(&b64).unwrap().pubkey(), kp.pubkey());
+
+        assert!(parse_secret("not-a-key").is_err());
+        assert!(parse_secret("[1, 2, 3]").is_err());
+    }
+
+    #[test]
+    fn test_parse_keypair_arg() {
+        let kp = Keypair::new();
+
+        let json = serde_json::to_string(&kp.to_bytes().to_vec()).unwrap();
+        assert_eq!(parse_keypair_arg(&json).unwrap().pubkey(), kp.pubkey());
+
+        let b58 = bs58::encode(kp.to_bytes()).into_string();
+        assert_eq!(parse_keypair_arg(&b58).unwrap().pubkey(), kp.pubkey());
+
+        let b64 = STANDARD.encode(kp.to_bytes());
+        assert_eq!(parse_keypair_arg(&b64).unwrap().pubkey(), kp.pubkey());
+
+        let tmp = std::env::temp_dir().join("test_keypair_arg.json");
+        std::fs::write(&tmp, &json).unwrap();
+        let loaded = parse_keypair_arg(tmp.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.pubkey(), kp.pubkey());
+        std::fs::remove_file(tmp).ok();
+
+        let err = parse_keypair_arg("/no/such/key.json").unwrap_err();
+        let msg = format!("{err:#}");
+        assert!(msg.contains("keypair file"));
+        assert!(msg.contains("base64"));
+    }
+
+    #[test]
+    fn test_decode_secret_is_zeroizing() {
+        let kp = Keypair::new();
+        let b58 = bs58::encode(kp.to_bytes()).into_string();
+
+        // type annotation is the check: secret bytes must be wiped on drop
+        let bytes: Zeroizing<Vec<u8>> = decode_secret(&b58).unwrap();
+        assert_eq!(&bytes[..], &kp.to_bytes()[..]);
+    }
+
     #[test]
     fn test_load_keypair_invalid_file() {
         let result = load_keypair(Some("/nonexistent/path.json"));
@@ -203,6 +691,65 @@ mod tests {
         assert!(parse_pubkey(invalid).is_err());
     }
 
+    #[test]
+    fn test_parse_pubkey_urls() {
+        let pk = "9sRRkYzseywA5zjLd2tqZLAgNgK6X4MVbagrNTmM8jAw";
+        let expected = Pubkey::from_str(pk).unwrap();
+        for input in [
+            format!("https://solscan.io/account/{pk}"),
+            format!("https://solscan.io/account/{pk}?cluster=devnet#portfolio"),
+            format!("https://explorer.solana.com/address/{pk}"),
+            format!("https://explorer.solana.com/address/{pk}/tokens?cluster=devnet"),
+            format!("https://solana.fm/address/{pk}"),
+            format!("https://solscan.io/token/{pk}"),
+            format!("solana:{pk}"),
+            format!("solana:{pk}?label=Coffee"),
+            format!("  {pk}\n"),
+        ] {
+            assert_eq!(parse_pubkey(&input).unwrap(), expected, "{input}");
+        }
+
+        // URLs without an address segment still fail with the original input
+        let err = parse_pubkey("https://solscan.io/tx/abc").unwrap_err();
+        assert!(err.to_string().contains("https://solscan.io/tx/abc"));
+        assert!(parse_pubkey("solana:").is_err());
+
+        // payment requests would lose their amount / token
+        for input in [
+            format!("solana:{pk}?amount=1&label=Coffee"),
+            format!("solana:{pk}?label=x&spl-token={pk}"),
+        ] {
+            let err = parse_pubkey(&input).unwrap_err().to_string();
+            assert!(err.contains("payment request"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_is_unused() {
+        assert!(is_unused(0, 0));
+        assert!(!is_unused(1, 0));
+        assert!(!is_unused(0, 1));
+    }
+
+    #[test]
+    fn test_pubkey_hint() {
+        let sig = "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+        assert!(pubkey_hint(sig).unwrap().contains("transaction signature"));
+        assert!(pubkey_hint("toly.sol").unwrap().contains("domain"));
+        assert!(pubkey_hint("0x52908400098527886E0F7030069857D2E4169EE7")
+            .unwrap()
+            .contains("EVM"));
+        assert_eq!(
+            pubkey_hint("Tokenkeg0feZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap(),
+            "contains invalid base58 character '0' at position 9"
+        );
+        assert!(pubkey_hint("abc").unwrap().contains("has 3 characters"));
+        assert!(pubkey_hint("  ").unwrap().contains("empty"));
+
+        let err = parse_pubkey(sig).unwrap_err().to_string();
+        assert!(err.contains("transaction signature"));
+    }
+
     #[test]
     fn test_parse_signature() {
         // Valid signature (88 chars base58)